@@ -1,6 +1,197 @@
+use crate::{api, config::Profile, sources::platform, subcommands::filters::effective_filters};
+use anyhow::{bail, Context as _, Error, Result};
 use colored::Colorize as _;
-use libium::{add::Error, iter_ext::IterExt as _};
-use std::collections::HashMap;
+use libium::{
+    add::Error::AlreadyAdded,
+    config::{
+        filters::Filter,
+        structs::{Mod, ModIdentifier},
+    },
+    iter_ext::IterExt as _,
+};
+use reqwest::Url;
+use std::{
+    collections::HashMap,
+    fs::read_to_string,
+    io::{read_to_string as read_all, stdin},
+    path::Path,
+};
+
+/// Разделы Modrinth, ссылки на проекты в которых можно добавить
+const MODRINTH_PROJECT_KINDS: [&str; 6] = [
+    "mod",
+    "plugin",
+    "datapack",
+    "resourcepack",
+    "shader",
+    "modpack",
+];
+
+/// Превращает ссылку на страницу проекта на Modrinth, CurseForge или GitHub в идентификатор,
+/// который понимает `libium::add::parse_id`
+///
+/// Ссылка на коллекцию Modrinth превращается в идентификаторы всех её проектов,
+/// остальные идентификаторы возвращаются без изменений.
+pub async fn resolve_url(id: &str) -> Result<Vec<String>> {
+    let Ok(url) = Url::parse(id) else {
+        return Ok(vec![id.to_owned()]);
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return Ok(vec![id.to_owned()]);
+    }
+    let host = url.host_str().unwrap_or_default();
+    let host = host.strip_prefix("www.").unwrap_or(host);
+    let segments = url
+        .path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).collect_vec())
+        .unwrap_or_default();
+
+    Ok(match (host, segments.as_slice()) {
+        ("modrinth.com", ["collection", collection, ..]) => {
+            api::get_collection_projects(collection).await?
+        }
+        ("modrinth.com", [kind, slug, ..]) if MODRINTH_PROJECT_KINDS.contains(kind) => {
+            vec![(*slug).to_owned()]
+        }
+        ("curseforge.com" | "legacy.curseforge.com", ["projects", project_id, ..])
+            if project_id.parse::<i32>().is_ok() =>
+        {
+            vec![(*project_id).to_owned()]
+        }
+        ("curseforge.com" | "legacy.curseforge.com", ["minecraft", _, slug, ..]) => {
+            vec![api::get_mod_id_from_slug(slug).await?.to_string()]
+        }
+        ("github.com", [owner, repo, ..]) => {
+            vec![format!("{owner}/{}", repo.trim_end_matches(".git"))]
+        }
+        _ => bail!(
+            "Не удалось распознать ссылку на проект. Чтобы скачать файл по прямой ссылке, используйте `url:{id}`"
+        ),
+    })
+}
+
+/// Добавляет в `profile` моды Modrinth, CurseForge и GitHub с идентификаторами `identifiers`
+///
+/// Если `perform_checks`, мод добавляется, только если у него есть файл, подходящий под фильтры.
+/// `filters` и `override_profile` сохраняются в добавленных модах.
+/// Возвращает названия добавленных модов, и идентификаторы модов, которые не удалось добавить, вместе с причиной.
+pub async fn add(
+    profile: &mut Profile,
+    identifiers: Vec<ModIdentifier>,
+    perform_checks: bool,
+    override_profile: bool,
+    filters: Vec<Filter>,
+) -> (Vec<String>, Vec<(String, Error)>) {
+    let mut successes = Vec::new();
+    let mut failures = Vec::new();
+    for identifier in identifiers {
+        let id = match &identifier {
+            ModIdentifier::CurseForgeProject(id) => id.to_string(),
+            ModIdentifier::ModrinthProject(id) => id.clone(),
+            ModIdentifier::GitHubRepository((owner, repo)) => format!("{owner}/{repo}"),
+        };
+        let mod_ = match fetch_mod(identifier, &filters, override_profile).await {
+            Ok(mod_) => mod_,
+            Err(err) => {
+                failures.push((id, err));
+                continue;
+            }
+        };
+        if profile.mods.iter().any(|existing| {
+            existing.identifier == mod_.identifier || existing.name.eq_ignore_ascii_case(&mod_.name)
+        }) || profile
+            .external_mods
+            .iter()
+            .any(|existing| existing.name.eq_ignore_ascii_case(&mod_.name))
+        {
+            failures.push((id, AlreadyAdded.into()));
+            continue;
+        }
+        if perform_checks {
+            let filters = effective_filters(&profile.filters, &mod_);
+            if let Err(err) = platform::fetch_download_file(&mod_.identifier, &filters).await {
+                failures.push((id, err));
+                continue;
+            }
+        }
+        successes.push(mod_.name.clone());
+        profile.mods.push(mod_);
+    }
+    (successes, failures)
+}
+
+/// Получает название мода с `identifier`, и его идентификатор, под которым он хранится в профиле
+async fn fetch_mod(
+    identifier: ModIdentifier,
+    filters: &[Filter],
+    override_filters: bool,
+) -> Result<Mod> {
+    let (name, identifier, slug) = match identifier {
+        ModIdentifier::ModrinthProject(id) => {
+            let project = api::get_project(&id)
+                .await
+                .with_context(|| format!("Не удалось получить проект Modrinth `{id}`"))?;
+            (
+                project.title,
+                ModIdentifier::ModrinthProject(project.id),
+                Some(project.slug),
+            )
+        }
+        ModIdentifier::CurseForgeProject(id) => {
+            let project = api::get_mod(id).await?;
+            (
+                project.name,
+                ModIdentifier::CurseForgeProject(project.id),
+                Some(project.slug),
+            )
+        }
+        ModIdentifier::GitHubRepository((owner, repo)) => {
+            let repository = api::get_repository(&owner, &repo).await?;
+            let owner = repository.owner.map_or(owner, |owner| owner.login);
+            (
+                repository.name.clone(),
+                ModIdentifier::GitHubRepository((owner, repository.name)),
+                None,
+            )
+        }
+    };
+    Ok(Mod {
+        name,
+        identifier,
+        slug,
+        filters: filters.to_vec(),
+        override_filters,
+    })
+}
+
+/// Возвращает идентификаторы всех проектов коллекции Modrinth с ID или ссылкой `collection`
+pub async fn collection(collection: &str) -> Result<Vec<String>> {
+    let id = collection
+        .trim_end_matches('/')
+        .rsplit('/')
+        .next()
+        .unwrap_or(collection);
+    api::get_collection_projects(id).await
+}
+
+/// Читает список идентификаторов или ссылок из файла `path`, по одному на строку
+///
+/// Если `path` это `-`, список читается из стандартного ввода.
+/// Пустые строки и строки, начинающиеся с `#`, пропускаются.
+pub fn read_list(path: &Path) -> Result<Vec<String>> {
+    let contents = if path == Path::new("-") {
+        read_all(stdin()).context("Не удалось прочитать список модов из стандартного ввода")?
+    } else {
+        read_to_string(path)
+            .with_context(|| format!("Не удалось прочитать список модов из {}", path.display()))?
+    };
+    Ok(contents
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(ToOwned::to_owned)
+        .collect())
+}
 
 pub fn display_successes_failures(successes: &[String], failures: Vec<(String, Error)>) -> bool {
     if !successes.is_empty() {
@@ -13,7 +204,7 @@ pub fn display_successes_failures(successes: &[String], failures: Vec<(String, E
     // Нет необходимости снова печатать ID, если есть только один
     } else if failures.len() == 1 {
         let err = &failures[0].1;
-        return if matches!(err, libium::add::Error::AlreadyAdded) {
+        return if matches!(err.downcast_ref::<libium::add::Error>(), Some(AlreadyAdded)) {
             println!("{}", err.to_string().yellow());
             false
         } else {
@@ -43,7 +234,7 @@ pub fn display_successes_failures(successes: &[String], failures: Vec<(String, E
         println!(
             "{:pad_len$}: {}",
             // Преобразуем "уже добавлено" в предупреждение
-            if err == libium::add::Error::AlreadyAdded.to_string() {
+            if err == AlreadyAdded.to_string() {
                 err.yellow()
             } else {
                 exit_error = true;
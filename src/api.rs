@@ -0,0 +1,501 @@
+//! The base URLs of the platforms' APIs and of the download host
+//!
+//! By default ferium talks to the public services. These can be pointed at a caching mirror,
+//! a self-hosted instance, or a local mock server using the `api` section of the config,
+//! or the `FERIUM_MODRINTH_URL`, `FERIUM_CURSEFORGE_URL`, `FERIUM_GITHUB_URL`,
+//! and `FERIUM_DOWNLOAD_HOST` environment variables, which take precedence.
+
+use crate::{
+    rate_limit::{reset_duration, Platform, Quota},
+    sources::{get_json, send_json},
+};
+use anyhow::{Context as _, Result};
+use ferinth::structures::{project::Project, tag::GameVersion, user::TeamMember, version::Version};
+use furse::structures::{file_structs::File, mod_structs::Mod};
+use libium::CURSEFORGE_API;
+use octocrab::models::{repos::Release, Repository};
+use reqwest::{header::ACCEPT, Client, RequestBuilder, Url};
+use serde::{
+    de::{DeserializeOwned, IgnoredAny},
+    Deserialize, Serialize,
+};
+use serde_json::json;
+use std::{
+    collections::HashMap,
+    env::var,
+    sync::{LazyLock, OnceLock},
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiUrls {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub modrinth: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub curseforge: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub github: Option<String>,
+
+    /// Files are downloaded from this host instead, keeping their path
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub download_host: Option<String>,
+}
+
+impl ApiUrls {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+static URLS: OnceLock<ApiUrls> = OnceLock::new();
+
+/// Use the URLs in `config`, unless they are overridden by environment variables
+pub fn configure(config: &ApiUrls) -> Result<()> {
+    let _ = URLS.set(resolve(config)?);
+    Ok(())
+}
+
+fn resolve(config: &ApiUrls) -> Result<ApiUrls> {
+    let get = |name: &str, variable: &str, configured: &Option<String>| {
+        var(variable)
+            .ok()
+            .or_else(|| configured.clone())
+            .map(|url| {
+                let url = url.trim_end_matches('/').to_owned();
+                Url::parse(&url).with_context(|| format!("Invalid {name} URL `{url}`"))?;
+                Ok(url)
+            })
+            .transpose()
+    };
+    Ok(ApiUrls {
+        modrinth: get("Modrinth API", "FERIUM_MODRINTH_URL", &config.modrinth)?,
+        curseforge: get(
+            "CurseForge API",
+            "FERIUM_CURSEFORGE_URL",
+            &config.curseforge,
+        )?,
+        github: get("GitHub API", "FERIUM_GITHUB_URL", &config.github)?,
+        download_host: get(
+            "download host",
+            "FERIUM_DOWNLOAD_HOST",
+            &config.download_host,
+        )?,
+    })
+}
+
+fn urls() -> &'static ApiUrls {
+    URLS.get_or_init(|| resolve(&ApiUrls::default()).unwrap_or_default())
+}
+
+/// The base URL of the Modrinth API
+pub fn modrinth_url() -> &'static str {
+    urls()
+        .modrinth
+        .as_deref()
+        .unwrap_or("https://api.modrinth.com")
+}
+
+/// The base URL of the GitHub API
+pub fn github_url() -> &'static str {
+    urls().github.as_deref().unwrap_or("https://api.github.com")
+}
+
+/// Point `url` at the download host if one is configured, keeping its path and query
+pub fn download_url(url: &Url) -> Url {
+    let Some(host) = &urls().download_host else {
+        return url.clone();
+    };
+    let query = url
+        .query()
+        .map_or(String::new(), |query| format!("?{query}"));
+    Url::parse(&format!("{host}{}{query}", url.path())).unwrap_or_else(|_| url.clone())
+}
+
+// Requests are always sent using ferium's own client, so that the network settings apply
+
+pub async fn list_game_versions() -> Result<Vec<GameVersion>> {
+    get_json(
+        &format!("{}/v2/tag/game_version", modrinth_url()),
+        Platform::Modrinth,
+    )
+    .await
+}
+
+pub async fn get_project(id: &str) -> Result<Project> {
+    get_json(
+        &format!("{}/v2/project/{id}", modrinth_url()),
+        Platform::Modrinth,
+    )
+    .await
+}
+
+pub async fn get_multiple_projects(ids: &[&str]) -> Result<Vec<Project>> {
+    let url = Url::parse_with_params(
+        &format!("{}/v2/projects", modrinth_url()),
+        [("ids", serde_json::to_string(ids)?)],
+    )?;
+    get_json(url.as_str(), Platform::Modrinth).await
+}
+
+pub async fn list_multiple_teams_members(ids: &[&str]) -> Result<Vec<Vec<TeamMember>>> {
+    let url = Url::parse_with_params(
+        &format!("{}/v2/teams", modrinth_url()),
+        [("ids", serde_json::to_string(ids)?)],
+    )?;
+    get_json(url.as_str(), Platform::Modrinth).await
+}
+
+/// The versions of the files with the SHA-512 hashes `hashes`, by hash
+pub async fn versions_from_hashes(hashes: &[String]) -> Result<HashMap<String, Version>> {
+    let body = json!({ "hashes": hashes, "algorithm": "sha512" });
+    send_json(
+        |client| {
+            client
+                .post(format!("{}/v2/version_files", modrinth_url()))
+                .json(&body)
+        },
+        Platform::Modrinth,
+    )
+    .await
+}
+
+pub async fn list_versions(project_id: &str) -> Result<Vec<Version>> {
+    get_json(
+        &format!("{}/v2/project/{project_id}/version", modrinth_url()),
+        Platform::Modrinth,
+    )
+    .await
+}
+
+#[derive(Deserialize)]
+struct Collection {
+    projects: Vec<String>,
+}
+
+/// The IDs of the projects in the Modrinth collection `id`
+///
+/// Collections are only available in version 3 of the API, which ferinth does not support.
+pub async fn get_collection_projects(id: &str) -> Result<Vec<String>> {
+    let collection: Collection = get_json(
+        &format!("{}/v3/collection/{id}", modrinth_url()),
+        Platform::Modrinth,
+    )
+    .await
+    .with_context(|| format!("Could not get the Modrinth collection `{id}`"))?;
+    Ok(collection.projects)
+}
+
+#[derive(Deserialize)]
+struct SearchResults {
+    hits: Vec<SearchHit>,
+}
+
+/// A project found by searching Modrinth
+#[derive(Deserialize, Debug, Clone)]
+pub struct SearchHit {
+    pub project_id: String,
+    pub slug: String,
+    pub title: String,
+    pub description: String,
+    pub author: String,
+    pub downloads: usize,
+}
+
+/// Search Modrinth for mods matching `query` that have files for `game_versions` and `loader`
+///
+/// Uses the search endpoint directly, since ferinth's search does not take facets.
+pub async fn search_mods(
+    query: &str,
+    game_versions: &[String],
+    loader: Option<&str>,
+) -> Result<Vec<SearchHit>> {
+    let mut facets = vec![vec!["project_type:mod".to_owned()]];
+    if !game_versions.is_empty() {
+        facets.push(
+            game_versions
+                .iter()
+                .map(|version| format!("versions:{version}"))
+                .collect(),
+        );
+    }
+    if let Some(loader) = loader {
+        facets.push(vec![format!("categories:{}", loader.to_lowercase())]);
+    }
+    let url = Url::parse_with_params(
+        &format!("{}/v2/search", modrinth_url()),
+        [
+            ("query", query.to_owned()),
+            ("facets", serde_json::to_string(&facets)?),
+            ("limit", "20".to_owned()),
+        ],
+    )?;
+    let results: SearchResults = get_json(url.as_str(), Platform::Modrinth)
+        .await
+        .with_context(|| format!("Could not search Modrinth for `{query}`"))?;
+    Ok(results.hits)
+}
+
+/// CurseForge wraps the contents of its responses in a `data` field
+#[derive(Deserialize)]
+struct Data<T> {
+    data: T,
+}
+
+/// The base URL of the CurseForge API
+fn curseforge_url() -> &'static str {
+    urls()
+        .curseforge
+        .as_deref()
+        .unwrap_or("https://api.curseforge.com")
+}
+
+/// The API key sent with CurseForge requests, which is the one provided or libium's default key
+///
+/// furse does not expose the key of libium's client, but includes it in the client's debug output.
+static CURSEFORGE_KEY: LazyLock<Option<String>> = LazyLock::new(|| {
+    var("CURSEFORGE_API_KEY").ok().or_else(|| {
+        let debug = format!("{:?}", *CURSEFORGE_API);
+        let (_, key) = debug.split_once("api_key: \"")?;
+        Some(key.split_once('"')?.0.to_owned())
+    })
+});
+
+/// A page of a CurseForge listing
+#[derive(Deserialize)]
+struct Page<T> {
+    data: Vec<T>,
+    pagination: Pagination,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Pagination {
+    result_count: usize,
+    total_count: usize,
+}
+
+/// The most results CurseForge returns in one page
+const CURSEFORGE_PAGE_SIZE: usize = 50;
+
+/// Send the CurseForge request built by `request`, with the API key
+async fn curseforge_response<T: DeserializeOwned>(
+    request: impl Fn(&Client) -> RequestBuilder,
+) -> Result<T> {
+    send_json(
+        |client| match &*CURSEFORGE_KEY {
+            Some(key) => request(client).header("x-api-key", key),
+            None => request(client),
+        },
+        Platform::CurseForge,
+    )
+    .await
+}
+
+async fn curseforge<T: DeserializeOwned>(request: impl Fn(&Client) -> RequestBuilder) -> Result<T> {
+    Ok(curseforge_response::<Data<T>>(request).await?.data)
+}
+
+/// Get every page of the CurseForge listing at `url`
+async fn curseforge_pages<T: DeserializeOwned>(url: &str) -> Result<Vec<T>> {
+    let mut items = Vec::new();
+    loop {
+        let page: Page<T> = curseforge_response(|client| {
+            client
+                .get(url)
+                .query(&[("index", items.len()), ("pageSize", CURSEFORGE_PAGE_SIZE)])
+        })
+        .await?;
+        let done = page.pagination.result_count < CURSEFORGE_PAGE_SIZE
+            || items.len() + page.pagination.result_count >= page.pagination.total_count;
+        items.extend(page.data);
+        if done {
+            return Ok(items);
+        }
+    }
+}
+
+pub async fn get_mod(id: i32) -> Result<Mod> {
+    curseforge(|client| client.get(format!("{}/v1/mods/{id}", curseforge_url()))).await
+}
+
+/// The ID of the CurseForge mod with the URL slug `slug`
+pub async fn get_mod_id_from_slug(slug: &str) -> Result<i32> {
+    let url = Url::parse_with_params(
+        &format!("{}/v1/mods/search", curseforge_url()),
+        [("gameId", "432"), ("classId", "6"), ("slug", slug)],
+    )?;
+    let mods: Vec<Mod> = curseforge(|client| client.get(url.clone()))
+        .await
+        .with_context(|| {
+            format!("Could not look up the CurseForge mod `{slug}`, try its project ID instead")
+        })?;
+    mods.into_iter()
+        .find(|mod_| mod_.slug == slug)
+        .map(|mod_| mod_.id)
+        .with_context(|| format!("There is no CurseForge mod `{slug}`"))
+}
+
+/// Search CurseForge for mods matching `query`, the most downloaded first
+pub async fn search_curseforge(query: &str) -> Result<Vec<Mod>> {
+    let url = Url::parse_with_params(
+        &format!("{}/v1/mods/search", curseforge_url()),
+        [
+            ("gameId", "432"),
+            ("classId", "6"),
+            ("searchFilter", query),
+            ("sortField", "6"),
+            ("sortOrder", "desc"),
+            ("pageSize", "20"),
+        ],
+    )?;
+    curseforge(|client| client.get(url.clone()))
+        .await
+        .with_context(|| format!("Could not search CurseForge for `{query}`"))
+}
+
+pub async fn get_mods(ids: Vec<i32>) -> Result<Vec<Mod>> {
+    let body = json!({ "modIds": ids });
+    curseforge(|client| {
+        client
+            .post(format!("{}/v1/mods", curseforge_url()))
+            .json(&body)
+    })
+    .await
+}
+
+pub async fn get_mod_files(id: i32) -> Result<Vec<File>> {
+    curseforge_pages(&format!("{}/v1/mods/{id}/files", curseforge_url())).await
+}
+
+pub async fn get_files(ids: Vec<i32>) -> Result<Vec<File>> {
+    let body = json!({ "fileIds": ids });
+    curseforge(|client| {
+        client
+            .post(format!("{}/v1/mods/files", curseforge_url()))
+            .json(&body)
+    })
+    .await
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatches {
+    exact_matches: Vec<FingerprintMatch>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintMatch {
+    id: i32,
+    file: FingerprintFile,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct FingerprintFile {
+    file_fingerprint: u32,
+}
+
+/// The IDs of the mods whose files have the `fingerprints`, along with the fingerprint of the file
+pub async fn get_fingerprint_matches(fingerprints: &[u32]) -> Result<Vec<(u32, i32)>> {
+    let body = json!({ "fingerprints": fingerprints });
+    let matches: FingerprintMatches = curseforge(|client| {
+        client
+            .post(format!("{}/v1/fingerprints/432", curseforge_url()))
+            .json(&body)
+    })
+    .await?;
+    Ok(matches
+        .exact_matches
+        .into_iter()
+        .map(|found| (found.file.file_fingerprint, found.id))
+        .collect())
+}
+
+/// Send a GET request for `path` to the GitHub API, with the token if one was provided
+async fn github<T: DeserializeOwned>(path: &str) -> Result<T> {
+    let url = format!("{}{path}", github_url());
+    let token = var("GITHUB_TOKEN").ok();
+    send_json(
+        |client| {
+            let request = client
+                .get(&url)
+                .header(ACCEPT, "application/vnd.github+json");
+            match &token {
+                Some(token) => request.bearer_auth(token),
+                None => request,
+            }
+        },
+        Platform::GitHub,
+    )
+    .await
+}
+
+pub async fn get_repository(owner: &str, repo: &str) -> Result<Repository> {
+    github(&format!("/repos/{owner}/{repo}")).await
+}
+
+/// The most results GitHub returns in one page
+pub const GITHUB_PAGE_SIZE: usize = 100;
+
+/// List a page of the releases of the repository, newest first
+///
+/// Pages start at 1, and a page with fewer than [`GITHUB_PAGE_SIZE`] releases is the last one.
+pub async fn list_releases_page(owner: &str, repo: &str, page: usize) -> Result<Vec<Release>> {
+    github(&format!(
+        "/repos/{owner}/{repo}/releases?per_page={GITHUB_PAGE_SIZE}&page={page}"
+    ))
+    .await
+}
+
+/// List every release of the repository, newest first
+pub async fn list_releases(owner: &str, repo: &str) -> Result<Vec<Release>> {
+    let mut releases = Vec::new();
+    for page in 1.. {
+        let batch = list_releases_page(owner, repo, page).await?;
+        let done = batch.len() < GITHUB_PAGE_SIZE;
+        releases.extend(batch);
+        if done {
+            break;
+        }
+    }
+    Ok(releases)
+}
+
+#[derive(Deserialize)]
+struct RateLimit {
+    resources: RateLimitResources,
+}
+
+#[derive(Deserialize)]
+struct RateLimitResources {
+    core: Rate,
+}
+
+#[derive(Deserialize)]
+struct Rate {
+    limit: u64,
+    remaining: u64,
+    reset: u64,
+}
+
+/// The quota of GitHub's core API, which is what ferium uses
+pub async fn github_quota() -> Result<Quota> {
+    let core = github::<RateLimit>("/rate_limit").await?.resources.core;
+    Ok(Quota {
+        limit: Some(core.limit),
+        remaining: Some(core.remaining),
+        reset: Some(reset_duration(core.reset)),
+    })
+}
+
+/// Check that GitHub accepts the token that was provided
+pub async fn check_github_token() -> Result<()> {
+    github::<IgnoredAny>("/user").await?;
+    Ok(())
+}
@@ -0,0 +1,340 @@
+//! Резервные копии файлов, которые вытеснило обновление, в папке `.old` папки вывода
+//!
+//! Файлы каждого обновления хранятся в отдельной папке, названной по его времени (и номеру,
+//! если в ту же секунду уже была сделана копия),
+//! а старые копии удаляются согласно [`OldRetention`] профиля.
+//! Файлы, перемещённые `ferium doctor` или при восстановлении файла, хранятся в папках
+//! с названием причины после времени, и не учитываются в количестве хранимых обновлений.
+
+use crate::config::OldRetention;
+use anyhow::Result;
+use chrono::{Local, NaiveDateTime, TimeDelta};
+use fs_extra::file::{move_file, CopyOptions};
+use libium::iter_ext::IterExt as _;
+use std::{
+    fs::{create_dir_all, read_dir, remove_dir_all, remove_file},
+    path::{Path, PathBuf},
+};
+
+/// Формат имён папок копий, при сортировке по имени они идут по времени
+const FORMAT: &str = "%Y-%m-%d_%H-%M-%S";
+/// Длина времени в именах папок копий
+const FORMAT_LENGTH: usize = "2000-01-01_00-00-00".len();
+
+/// Папка с резервными копиями папки вывода `directory`
+pub fn old_dir(directory: &Path) -> PathBuf {
+    directory.join(".old")
+}
+
+/// Резервная копия файлов одного обновления
+pub struct Backup {
+    /// Время обновления, или `None` для файлов, перемещённых до того, как копии стали храниться по отдельности
+    pub time: Option<NaiveDateTime>,
+    /// Является ли копия копией обновления, а не файлов, перемещённых по другой причине
+    pub upgrade: bool,
+    pub path: PathBuf,
+}
+
+impl Backup {
+    /// Копия для обновления, которое происходит сейчас
+    ///
+    /// Её папка создаётся при перемещении первого файла.
+    pub fn new(directory: &Path) -> Self {
+        Self::dated(directory, Local::now().naive_local(), None)
+    }
+
+    /// Копия файлов, которые перемещаются сейчас по причине `reason`, а не из-за обновления
+    pub fn other(directory: &Path, reason: &str) -> Self {
+        Self::dated(directory, Local::now().naive_local(), Some(reason))
+    }
+
+    /// Копия со временем `time`, обновления если `reason` это `None`
+    ///
+    /// Если папка копии с тем же временем уже есть, после времени добавляется номер,
+    /// чтобы файлы разных копий, сделанных в одну секунду, не заменяли друг друга.
+    fn dated(directory: &Path, time: NaiveDateTime, reason: Option<&str>) -> Self {
+        let old_dir = old_dir(directory);
+        let prefix = time.format(FORMAT).to_string();
+        let suffix = reason.map_or(String::new(), |reason| format!("_{reason}"));
+        let mut path = old_dir.join(format!("{prefix}{suffix}"));
+        let mut number = 1;
+        while path.exists() {
+            number += 1;
+            path = old_dir.join(format!("{prefix}.{number}{suffix}"));
+        }
+        Self {
+            path,
+            time: Some(time),
+            upgrade: reason.is_none(),
+        }
+    }
+
+    /// Имя копии, по которому её можно выбрать
+    pub fn name(&self) -> String {
+        match self.time {
+            Some(_) => self
+                .path
+                .file_name()
+                .map(|name| name.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            None => "undated".to_owned(),
+        }
+    }
+
+    /// Перемещает `file` в копию, заменяя файл с тем же именем
+    pub fn store(&self, file: &Path) -> Result<()> {
+        if let Some(filename) = file.file_name() {
+            create_dir_all(&self.path)?;
+            move_file(
+                file,
+                self.path.join(filename),
+                &CopyOptions::new().overwrite(true),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Имена файлов в копии, по алфавиту
+    pub fn files(&self) -> Result<Vec<String>> {
+        let mut files = read_dir(&self.path)?
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .map(|entry| entry.file_name().to_string_lossy().into_owned())
+            .collect_vec();
+        files.sort_unstable();
+        Ok(files)
+    }
+
+    /// Удаляет копию
+    ///
+    /// Из папки без даты удаляются только файлы, ведь в ней лежат остальные копии.
+    pub fn remove(&self) -> Result<()> {
+        if self.time.is_some() {
+            remove_dir_all(&self.path)?;
+        } else {
+            for file in self.files()? {
+                remove_file(self.path.join(file))?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Резервные копии папки вывода `directory`, от старых к новым
+///
+/// Файлы, лежащие прямо в `.old`, составляют копию без даты, которая идёт первой.
+pub fn list(directory: &Path) -> Result<Vec<Backup>> {
+    let old_dir = old_dir(directory);
+    if !old_dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let mut backups = read_dir(&old_dir)?
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_dir()))
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let time = NaiveDateTime::parse_from_str(name.get(..FORMAT_LENGTH)?, FORMAT).ok()?;
+            let rest = name.get(FORMAT_LENGTH..)?;
+            // Номер копии, сделанной в ту же секунду, что и другая
+            let (number, reason) = match rest.strip_prefix('.') {
+                Some(rest) => {
+                    let (number, reason) = rest.split_at(rest.find('_').unwrap_or(rest.len()));
+                    (number.parse::<u32>().ok()?, reason)
+                }
+                None => (1, rest),
+            };
+            if !reason.is_empty() && !reason.starts_with('_') {
+                return None;
+            }
+            Some((
+                number,
+                Backup {
+                    time: Some(time),
+                    upgrade: reason.is_empty(),
+                    path: entry.path(),
+                },
+            ))
+        })
+        .collect_vec();
+    backups.sort_by_key(|(number, backup)| (backup.time, *number, !backup.upgrade));
+    let mut backups = backups.into_iter().map(|(_, backup)| backup).collect_vec();
+
+    let undated = Backup {
+        time: None,
+        upgrade: false,
+        path: old_dir,
+    };
+    if !undated.files()?.is_empty() {
+        backups.insert(0, undated);
+    }
+    Ok(backups)
+}
+
+/// Удаляет копии папки вывода `directory`, которые не нужно хранить согласно `retention`, и возвращает их
+///
+/// Копия без даты не удаляется, её можно удалить только вручную.
+pub fn prune(directory: &Path, retention: OldRetention) -> Result<Vec<Backup>> {
+    let expired = expired(list(directory)?, retention, Local::now().naive_local());
+    for backup in &expired {
+        backup.remove()?;
+    }
+    Ok(expired)
+}
+
+/// Копии из `backups` (от старых к новым), которые не нужно хранить в момент `now` согласно `retention`
+///
+/// Только копии обновлений учитываются в количестве хранимых обновлений,
+/// а остальные копии с датой хранятся столько же, сколько самое старое хранимое обновление.
+/// `OldRetention::Upgrades(0)` не удаляет ничего, как будто хранение не ограничено.
+pub fn expired(backups: Vec<Backup>, retention: OldRetention, now: NaiveDateTime) -> Vec<Backup> {
+    // Копии старше этого времени удаляются
+    let oldest = match retention {
+        OldRetention::Upgrades(0) => None,
+        OldRetention::Upgrades(count) => backups
+            .iter()
+            .rev()
+            .filter(|backup| backup.upgrade)
+            .nth(count - 1)
+            .and_then(|backup| backup.time),
+        // Если срок больше, чем может вместить `TimeDelta`, ничего не удаляется
+        OldRetention::Days(days) => i64::try_from(days)
+            .ok()
+            .and_then(TimeDelta::try_days)
+            .and_then(|age| now.checked_sub_signed(age)),
+    };
+    let Some(oldest) = oldest else {
+        return Vec::new();
+    };
+    backups
+        .into_iter()
+        .filter(|backup| backup.time.is_some_and(|time| time < oldest))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{env::temp_dir, fs::write};
+
+    fn time(time: &str) -> Result<NaiveDateTime> {
+        Ok(NaiveDateTime::parse_from_str(time, FORMAT)?)
+    }
+
+    fn backup(name: &str) -> Result<Backup> {
+        Ok(Backup {
+            time: Some(time(&name[..FORMAT_LENGTH])?),
+            upgrade: name.len() == FORMAT_LENGTH,
+            path: PathBuf::from(".old").join(name),
+        })
+    }
+
+    /// Имена копий из `BACKUPS`, которые не нужно хранить 6 января согласно `retention`
+    fn expired_names(retention: OldRetention) -> Result<Vec<String>> {
+        let backups = BACKUPS
+            .iter()
+            .map(|name| backup(name))
+            .collect::<Result<_>>()?;
+        Ok(expired(backups, retention, time("2024-01-06_00-00-00")?)
+            .iter()
+            .map(Backup::name)
+            .collect())
+    }
+
+    const BACKUPS: [&str; 5] = [
+        "2024-01-01_00-00-00",
+        "2024-01-02_00-00-00_restore",
+        "2024-01-03_00-00-00",
+        "2024-01-04_00-00-00_doctor",
+        "2024-01-05_00-00-00",
+    ];
+
+    #[test]
+    fn only_upgrades_are_counted() -> Result<()> {
+        assert_eq!(
+            expired_names(OldRetention::Upgrades(2))?,
+            ["2024-01-01_00-00-00", "2024-01-02_00-00-00_restore"]
+        );
+        assert_eq!(
+            expired_names(OldRetention::Upgrades(1))?,
+            [
+                "2024-01-01_00-00-00",
+                "2024-01-02_00-00-00_restore",
+                "2024-01-03_00-00-00",
+                "2024-01-04_00-00-00_doctor",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn fewer_upgrades_than_kept() -> Result<()> {
+        assert!(expired_names(OldRetention::Upgrades(3))?.is_empty());
+        assert!(expired_names(OldRetention::Upgrades(10))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn zero_upgrades_keeps_everything() -> Result<()> {
+        assert!(expired_names(OldRetention::Upgrades(0))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn days() -> Result<()> {
+        assert_eq!(
+            expired_names(OldRetention::Days(3))?,
+            ["2024-01-01_00-00-00", "2024-01-02_00-00-00_restore"]
+        );
+        assert!(expired_names(OldRetention::Days(u64::MAX))?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn same_second_backups_are_numbered() -> Result<()> {
+        let directory = temp_dir().join(format!("ferium-backup-{}", rand::random::<u64>()));
+        create_dir_all(&directory)?;
+        let time = time("2024-01-01_00-00-00")?;
+        for reason in [None, None, Some("doctor"), Some("doctor")] {
+            let file = directory.join("mod.jar");
+            write(&file, "jar")?;
+            Backup::dated(&directory, time, reason).store(&file)?;
+        }
+
+        let backups = list(&directory)?;
+        remove_dir_all(&directory)?;
+        assert_eq!(
+            backups.iter().map(Backup::name).collect_vec(),
+            [
+                "2024-01-01_00-00-00",
+                "2024-01-01_00-00-00_doctor",
+                "2024-01-01_00-00-00.2",
+                "2024-01-01_00-00-00.2_doctor",
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn undated_is_kept() -> Result<()> {
+        let backups = vec![
+            Backup {
+                time: None,
+                upgrade: false,
+                path: PathBuf::from(".old"),
+            },
+            backup("2024-01-01_00-00-00")?,
+            backup("2024-01-02_00-00-00")?,
+        ];
+        let expired = expired(
+            backups,
+            OldRetention::Upgrades(1),
+            time("2024-02-01_00-00-00")?,
+        );
+        assert_eq!(
+            expired.iter().map(Backup::name).collect_vec(),
+            ["2024-01-01_00-00-00"]
+        );
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 #![deny(missing_docs)]
 
-use clap::{Args, Parser, Subcommand, ValueEnum, ValueHint};
+use crate::config::UntrackedFiles;
+use clap::{builder::RangedU64ValueParser, Args, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_complete::Shell;
 use libium::config::{
     filters::{self, Filter},
@@ -25,13 +26,52 @@ pub struct Ferium {
     #[clap(long, short, visible_aliases = ["config", "conf"])]
     #[clap(value_hint(ValueHint::FilePath))]
     pub config_file: Option<PathBuf>,
+    /// Use this profile instead of the active one, without switching to it
+    #[clap(long, global = true)]
+    pub profile: Option<String>,
+    /// Use this modpack instead of the active one, without switching to it
+    #[clap(long, global = true)]
+    pub modpack: Option<String>,
+    #[command(flatten)]
+    pub network: NetworkArguments,
+}
+
+/// Override the `network` section of the config
+#[derive(Args)]
+pub struct NetworkArguments {
+    /// Send all requests through this HTTP, HTTPS, or SOCKS5 proxy
+    #[clap(long)]
+    pub proxy: Option<String>,
+    /// Trust the root certificates in this PEM file, in addition to the ones in the config
+    #[clap(long = "ca-certificate", visible_alias = "cacert")]
+    #[clap(value_hint(ValueHint::FilePath))]
+    pub ca_certificates: Vec<PathBuf>,
+    /// How long to wait for a connection to be established, in seconds
+    #[clap(long, value_name = "SECONDS")]
+    pub connect_timeout: Option<u64>,
+    /// How long to wait for a response to send more data, in seconds
+    #[clap(long, value_name = "SECONDS")]
+    pub read_timeout: Option<u64>,
+    /// Text to append to ferium's user agent
+    #[clap(long)]
+    pub user_agent_suffix: Option<String>,
+    /// How many times to retry a failed download before giving up on the file
+    #[clap(long)]
+    pub retries: Option<u32>,
 }
 
 #[derive(Subcommand)]
 pub enum SubCommands {
     Add {
-        #[clap(required = true)]
+        /// Project IDs, slugs, `owner/repo`, or links to the projects' pages
+        #[clap(required_unless_present_any = ["collection", "from_file"])]
         identifiers: Vec<String>,
+        /// Add every project in this Modrinth collection, given its ID or link
+        #[clap(long)]
+        collection: Option<String>,
+        /// Add the identifiers or links in this file, one per line, or `-` to read them from stdin
+        #[clap(long, value_hint(ValueHint::FilePath))]
+        from_file: Option<PathBuf>,
         #[clap(long, short, visible_alias = "override")]
         force: bool,
         #[command(flatten)]
@@ -52,12 +92,54 @@ pub enum SubCommands {
         #[clap(value_enum)]
         shell: Shell,
     },
+    /// Check the config and output directories for common problems
+    Doctor {
+        /// Fix the problems that can be fixed automatically
+        #[clap(long)]
+        fix: bool,
+    },
+    /// Manage optional groups of mods in the active profile
+    Group {
+        #[clap(subcommand)]
+        subcommand: Option<GroupSubCommands>,
+    },
+    /// Manage the config file itself
+    Config {
+        #[clap(subcommand)]
+        subcommand: ConfigSubCommands,
+    },
+    /// Change the settings of a mod in the active profile
+    Mod {
+        #[clap(subcommand)]
+        subcommand: ModSubCommands,
+    },
+    /// Inspect and restore the files that upgrading replaced in the active profile
+    Old {
+        #[clap(subcommand)]
+        subcommand: Option<OldSubCommands>,
+    },
     #[clap(visible_alias = "mods")]
     List {
         #[clap(long, short)]
         verbose: bool,
         #[clap(long, short, visible_alias = "md")]
         markdown: bool,
+        /// Show a table of the game versions and mod loaders each mod has files for
+        #[clap(long, conflicts_with = "verbose")]
+        matrix: bool,
+        /// The game versions to show in the matrix, separated by commas
+        #[clap(long, value_delimiter = ',', requires = "matrix")]
+        versions: Vec<String>,
+        /// The mod loaders to show in the matrix, separated by commas
+        #[clap(long, value_delimiter = ',', requires = "matrix")]
+        #[clap(value_enum)]
+        loaders: Vec<ModLoader>,
+        /// Output the matrix as CSV
+        #[clap(long, requires = "matrix", conflicts_with = "markdown")]
+        csv: bool,
+        /// List the mods under the groups they belong to
+        #[clap(long, short, conflicts_with_all = ["verbose", "matrix"])]
+        groups: bool,
     },
     Modpack {
         #[clap(subcommand)]
@@ -73,8 +155,114 @@ pub enum SubCommands {
     Remove {
         mod_names: Vec<String>,
     },
+    /// Keep mods in the profile without installing them
+    Disable {
+        mod_names: Vec<String>,
+    },
+    /// Install mods that were disabled again
+    Enable {
+        mod_names: Vec<String>,
+    },
+    /// Show how many requests are left before each platform's rate limit is reached
+    Status,
+    /// Browse and manage profiles in a full-screen terminal interface
+    Tui,
     #[clap(visible_aliases = ["download", "install"])]
-    Upgrade,
+    Upgrade {
+        /// Upgrade every profile instead of only the active one
+        #[clap(long, short, conflicts_with = "profiles")]
+        all: bool,
+        /// Upgrade these profiles instead of only the active one, separated by commas
+        #[clap(long, value_delimiter = ',')]
+        profiles: Vec<String>,
+        /// Don't ask what to do with files that ferium did not install,
+        /// and use the profile's setting for them instead
+        #[clap(long, short)]
+        yes: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum ConfigSubCommands {
+    /// Upgrade the config file to the current version, after backing it up
+    Migrate {
+        /// Only report what would change, and fail if the config needs to be migrated
+        #[clap(long)]
+        check: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum GroupSubCommands {
+    /// Add mods to a group, creating it if it does not exist
+    Add {
+        group: String,
+        #[clap(required = true)]
+        mod_names: Vec<String>,
+    },
+    /// Remove mods from a group, or delete the group if no mods are given
+    #[clap(visible_alias = "rm")]
+    Remove {
+        group: String,
+        mod_names: Vec<String>,
+    },
+    /// Install the mods in a group when upgrading
+    Enable {
+        group: String,
+    },
+    /// Stop installing the mods in a group
+    Disable {
+        group: String,
+    },
+    List,
+}
+
+#[derive(Subcommand)]
+pub enum ModSubCommands {
+    /// Change the filters used to pick the mod's files, or pick which to change if none are given
+    ///
+    /// Each filter given replaces the mod's filter of the same kind.
+    #[clap(visible_aliases = ["config", "conf"])]
+    Configure {
+        /// The name or identifier of the mod
+        mod_name: String,
+        #[clap(flatten)]
+        filters: FilterArguments,
+        /// Use the profile's filters again for the kinds the mod does not have its own of
+        #[clap(long, conflicts_with = "override_profile")]
+        use_profile: bool,
+        /// Remove the mod's own filters before applying the given ones
+        #[clap(long)]
+        clear: bool,
+        /// Fall back to files up to this release channel when the mod has none in its own,
+        /// instead of the profile's fallback
+        #[clap(long, value_enum)]
+        channel_fallback: Option<filters::ReleaseChannel>,
+        /// Fall back like the rest of the profile again
+        #[clap(long, conflicts_with = "channel_fallback")]
+        profile_fallback: bool,
+    },
+}
+
+#[derive(Subcommand)]
+pub enum OldSubCommands {
+    /// List the backups in the `.old` directory and the files in them, from the oldest
+    List,
+    /// Move a file from a backup back into the output directory,
+    /// and the installed files of the same mod into a new backup
+    Restore {
+        /// The name of the file, or a part of it, to pick from a list if not given
+        file: Option<String>,
+        /// Restore it from this backup instead of the latest one that has it
+        #[clap(long)]
+        from: Option<String>,
+    },
+    /// Delete the backups that the profile does not keep anymore
+    Purge {
+        /// Delete every backup, including the files from before they were dated
+        #[clap(long, short)]
+        all: bool,
+    },
 }
 
 #[derive(Subcommand)]
@@ -91,6 +279,25 @@ pub enum ProfileSubCommands {
         #[clap(long, short)]
         #[clap(value_hint(ValueHint::DirPath))]
         output_dir: Option<PathBuf>,
+        #[command(flatten)]
+        inheritance: InheritanceArguments,
+        #[command(flatten)]
+        filters: ProfileFilterArguments,
+        /// Fall back to files up to this release channel when a mod has none in the profile's,
+        /// where `release` stops falling back
+        #[clap(long, value_enum)]
+        channel_fallback: Option<filters::ReleaseChannel>,
+        /// What to do with files in the output directory that ferium did not install,
+        /// when upgrading with `--yes` or outside of a terminal
+        #[clap(long, value_enum)]
+        untracked_files: Option<UntrackedFiles>,
+        /// Keep the files replaced by this many of the latest upgrades in the `.old` directory
+        #[clap(long, value_name = "COUNT", conflicts_with = "keep_old_days")]
+        #[clap(value_parser = RangedU64ValueParser::<usize>::new().range(1..))]
+        keep_old_upgrades: Option<usize>,
+        /// Keep the files replaced in this many days in the `.old` directory
+        #[clap(long, value_name = "DAYS")]
+        keep_old_days: Option<u64>,
     },
     #[clap(visible_alias = "new")]
     Create {
@@ -107,6 +314,14 @@ pub enum ProfileSubCommands {
         #[clap(long, short)]
         #[clap(value_hint(ValueHint::DirPath))]
         output_dir: Option<PathBuf>,
+        /// Inherit the mods of this profile, can be used multiple times
+        #[clap(long = "parent", visible_alias = "inherit")]
+        parents: Vec<String>,
+        #[command(flatten)]
+        filters: ProfileFilterArguments,
+        /// Fall back to files up to this release channel when a mod has none in the profile's
+        #[clap(long, value_enum)]
+        channel_fallback: Option<filters::ReleaseChannel>,
     },
     #[clap(visible_aliases = ["remove", "rm"])]
     Delete {
@@ -116,6 +331,22 @@ pub enum ProfileSubCommands {
     },
     Info,
     List,
+    /// Check which mods are available for another game version, then migrate the profile to it
+    Migrate {
+        /// The game version to migrate to
+        #[clap(long)]
+        to: String,
+        /// The mod loader to migrate to, instead of keeping the current one
+        #[clap(long, short)]
+        #[clap(value_enum)]
+        loader: Option<ModLoader>,
+        /// Create a new profile with this name and the compatible mods, without asking
+        #[clap(long, conflicts_with = "update")]
+        create: Option<String>,
+        /// Update this profile with the compatible mods, without asking
+        #[clap(long)]
+        update: bool,
+    },
     Switch {
         profile_name: Option<String>,
     },
@@ -151,7 +382,14 @@ pub enum ModpackSubCommands {
         modpack_name: Option<String>,
     },
     #[clap(visible_aliases = ["download", "install"])]
-    Upgrade,
+    Upgrade {
+        /// Upgrade every modpack instead of only the active one
+        #[clap(long, short, conflicts_with = "modpacks")]
+        all: bool,
+        /// Upgrade these modpacks instead of only the active one, separated by commas
+        #[clap(long, value_delimiter = ',')]
+        modpacks: Vec<String>,
+    },
 }
 
 #[derive(Args)]
@@ -210,6 +448,61 @@ impl From<FilterArguments> for Vec<Filter> {
     }
 }
 
+/// The kinds of filters a profile can have one of each of
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FilterKind {
+    ModLoader,
+    GameVersion,
+    ReleaseChannel,
+    Filename,
+    Title,
+    Description,
+}
+
+#[derive(Args)]
+pub struct ProfileFilterArguments {
+    /// The least stable release channel to install files from
+    #[clap(long)]
+    pub release_channel: Option<filters::ReleaseChannel>,
+    /// Only install files with a filename matching this regex
+    #[clap(long)]
+    pub filename: Option<String>,
+    /// Only install files with a title matching this regex
+    #[clap(long)]
+    pub title: Option<String>,
+    /// Only install files with a description matching this regex
+    #[clap(long)]
+    pub description: Option<String>,
+    /// Also accept files for other minor versions of the game versions
+    #[clap(long, conflicts_with = "strict_versions")]
+    pub minor_versions: bool,
+    /// Only accept files for exactly the game versions
+    #[clap(long)]
+    pub strict_versions: bool,
+    /// Remove the filter of this kind, can be used multiple times
+    #[clap(long, value_enum)]
+    pub remove_filter: Vec<FilterKind>,
+    /// Put the filters in this order, separated by commas, with the kinds not listed after them
+    #[clap(long, value_enum, value_delimiter = ',')]
+    pub filter_order: Vec<FilterKind>,
+}
+
+#[derive(Args)]
+pub struct InheritanceArguments {
+    /// Replace the profiles this profile inherits mods from, can be used multiple times
+    #[clap(long = "parent", visible_alias = "inherit")]
+    pub parents: Vec<String>,
+    /// Stop inheriting mods from any profile
+    #[clap(long, conflicts_with = "parents")]
+    pub clear_parents: bool,
+    /// Do not install this inherited mod, by name or identifier
+    #[clap(long)]
+    pub exclude: Vec<String>,
+    /// Install this previously excluded mod again, by name or identifier
+    #[clap(long)]
+    pub include: Vec<String>,
+}
+
 #[derive(Clone, Copy, Default, ValueEnum)]
 pub enum Platform {
     #[default]
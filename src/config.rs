@@ -0,0 +1,517 @@
+//! Ferium's view of the config file
+//!
+//! libium owns the structure of profiles and modpacks, these types wrap them
+//! so that ferium can store the settings libium does not know about in the same file.
+
+pub mod migrate;
+pub mod workspace;
+
+use crate::{api::ApiUrls, network::NetworkSettings, sources::ExternalMod};
+use anyhow::{anyhow, ensure, Context as _, Result};
+use clap::ValueEnum;
+use libium::{
+    config::{
+        filters::ReleaseChannel,
+        structs::{
+            Mod, ModIdentifier, ModLoader, Modpack, ModpackIdentifier, Profile as BaseProfile,
+        },
+    },
+    iter_ext::IterExt as _,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use serde_path_to_error::Segment;
+use std::{
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::{Seek as _, Write as _},
+    ops::{Deref, DerefMut},
+    path::PathBuf,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct Config {
+    /// The version of the config's structure, used to migrate configs written by older versions
+    #[serde(default)]
+    pub version: u64,
+
+    #[serde(skip_serializing_if = "is_zero")]
+    #[serde(default)]
+    pub active_profile: usize,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub profiles: Vec<Profile>,
+
+    #[serde(skip_serializing_if = "is_zero")]
+    #[serde(default)]
+    pub active_modpack: usize,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub modpacks: Vec<Modpack>,
+
+    #[serde(skip_serializing_if = "ApiUrls::is_empty")]
+    #[serde(default)]
+    pub api: ApiUrls,
+
+    #[serde(skip_serializing_if = "NetworkSettings::is_empty")]
+    #[serde(default)]
+    pub network: NetworkSettings,
+}
+
+impl Config {
+    /// Whether a modpack with `identifier` has already been added
+    pub fn has_modpack(&self, identifier: &ModpackIdentifier) -> bool {
+        self.modpacks
+            .iter()
+            .any(|modpack| modpack.identifier == *identifier)
+    }
+
+    /// Find the profile called `name`, ignoring case
+    pub fn find_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles
+            .iter()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Find the profile called `name`, ignoring case, to change it
+    pub fn find_profile_mut(&mut self, name: &str) -> Option<&mut Profile> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+    }
+
+    /// Collect the mods that `profile` inherits from its parents
+    ///
+    /// Mods that `profile` already has (by identifier or name) or has excluded are skipped,
+    /// and a mod inherited through multiple parents is only included once.
+    pub fn inherited_mods(&self, profile: &Profile) -> Result<Inherited> {
+        let mut inherited = Inherited::default();
+        self.collect_inherited(profile, &mut vec![profile.name.clone()], &mut inherited)?;
+        Ok(inherited)
+    }
+
+    fn collect_inherited(
+        &self,
+        profile: &Profile,
+        chain: &mut Vec<String>,
+        inherited: &mut Inherited,
+    ) -> Result<()> {
+        for parent_name in &profile.parents {
+            let parent = self.find_profile(parent_name).with_context(|| {
+                format!(
+                    "Profile `{}` inherits from `{parent_name}`, which does not exist",
+                    profile.name
+                )
+            })?;
+            ensure!(
+                !chain
+                    .iter()
+                    .any(|name| name.eq_ignore_ascii_case(&parent.name)),
+                "Profile inheritance is circular: {} -> {}",
+                chain.iter().display(" -> "),
+                parent.name
+            );
+
+            // Everything the parent would install, including what it inherits itself
+            let mut from_parent = Inherited {
+                mods: parent
+                    .mods
+                    .iter()
+                    .filter(|mod_| parent.is_mod_installed(mod_))
+                    .map(|mod_| (parent.name.clone(), mod_.clone()))
+                    .collect(),
+                external_mods: parent
+                    .external_mods
+                    .iter()
+                    .filter(|mod_| parent.is_external_mod_installed(mod_))
+                    .map(|mod_| (parent.name.clone(), mod_.clone()))
+                    .collect(),
+            };
+            chain.push(parent.name.clone());
+            self.collect_inherited(parent, chain, &mut from_parent)?;
+            chain.pop();
+
+            for (origin, mod_) in from_parent.mods {
+                if !profile.is_excluded(&mod_.name, &platform_id(&mod_.identifier))
+                    && !profile.has_mod(&mod_.name)
+                    && !profile.mods.iter().any(|m| m.identifier == mod_.identifier)
+                    && !inherited.mods.iter().any(|(_, m)| {
+                        m.identifier == mod_.identifier || m.name.eq_ignore_ascii_case(&mod_.name)
+                    })
+                {
+                    inherited.mods.push((origin, mod_));
+                }
+            }
+            for (origin, mod_) in from_parent.external_mods {
+                if !profile.is_excluded(&mod_.name, &mod_.identifier.display_id())
+                    && !profile.has_mod(&mod_.name)
+                    && !profile
+                        .external_mods
+                        .iter()
+                        .any(|m| m.identifier == mod_.identifier)
+                    && !inherited.external_mods.iter().any(|(_, m)| {
+                        m.identifier == mod_.identifier || m.name.eq_ignore_ascii_case(&mod_.name)
+                    })
+                {
+                    inherited.external_mods.push((origin, mod_));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// A copy of `profile` with the mods it inherits added, which is what gets installed
+    pub fn merged(&self, profile: &Profile) -> Result<Profile> {
+        let inherited = self.inherited_mods(profile)?;
+        let mut merged = profile.clone();
+        merged
+            .mods
+            .extend(inherited.mods.into_iter().map(|(_, mod_)| mod_));
+        merged
+            .external_mods
+            .extend(inherited.external_mods.into_iter().map(|(_, mod_)| mod_));
+        Ok(merged)
+    }
+}
+
+/// The mods a profile inherits, along with the name of the profile each one comes from
+#[derive(Debug, Clone, Default)]
+pub struct Inherited {
+    pub mods: Vec<(String, Mod)>,
+    pub external_mods: Vec<(String, ExternalMod)>,
+}
+
+impl Inherited {
+    pub fn len(&self) -> usize {
+        self.mods.len() + self.external_mods.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct Profile {
+    #[serde(flatten)]
+    pub base: BaseProfile,
+
+    /// Mods from sources that libium cannot resolve, such as GitLab or Forgejo releases
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub external_mods: Vec<ExternalMod>,
+
+    /// The names of the profiles this profile inherits mods from
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub parents: Vec<String>,
+
+    /// The names or identifiers of inherited mods that should not be installed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub exclude: Vec<String>,
+
+    /// The identifiers of the mods that are kept in the profile but not installed
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub disabled: Vec<String>,
+
+    /// Optional sets of mods, which are only installed while their group is enabled
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub groups: Vec<ModGroup>,
+
+    /// The least stable release channel to fall back to when a mod has no files
+    /// in the release channel of its filters
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub channel_fallback: Option<ReleaseChannel>,
+
+    /// Mods that fall back to a different release channel than the profile's
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub mod_channel_fallbacks: Vec<ChannelFallback>,
+
+    /// What to do with files in the output directory that ferium did not install,
+    /// when upgrading without being able to ask
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub untracked_files: Option<UntrackedFiles>,
+
+    /// How long to keep the files that upgrading replaced in the `.old` directory
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub keep_old: Option<OldRetention>,
+
+    /// Mods that had no compatible version when this profile was migrated to another game version,
+    /// kept so that they can be added once they are updated
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub pending_mods: Vec<Mod>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub pending_external_mods: Vec<ExternalMod>,
+}
+
+impl Profile {
+    pub fn new(
+        name: String,
+        output_dir: PathBuf,
+        game_versions: Vec<String>,
+        mod_loader: ModLoader,
+    ) -> Self {
+        Self {
+            base: BaseProfile::new(name, output_dir, game_versions, mod_loader),
+            external_mods: Vec::new(),
+            parents: Vec::new(),
+            exclude: Vec::new(),
+            disabled: Vec::new(),
+            groups: Vec::new(),
+            channel_fallback: None,
+            mod_channel_fallbacks: Vec::new(),
+            untracked_files: None,
+            keep_old: None,
+            pending_mods: Vec::new(),
+            pending_external_mods: Vec::new(),
+        }
+    }
+
+    /// The number of mods in this profile, from all sources
+    pub fn mod_count(&self) -> usize {
+        self.mods.len() + self.external_mods.len()
+    }
+
+    /// The number of mods waiting to be added once they support this profile's game version
+    pub fn pending_count(&self) -> usize {
+        self.pending_mods.len() + self.pending_external_mods.len()
+    }
+
+    /// Whether the platform mod `mod_` has been disabled
+    pub fn is_mod_disabled(&self, mod_: &Mod) -> bool {
+        self.is_disabled(&platform_id(&mod_.identifier))
+    }
+
+    /// Whether the external mod `mod_` has been disabled
+    pub fn is_external_mod_disabled(&self, mod_: &ExternalMod) -> bool {
+        self.is_disabled(&mod_.identifier.display_id())
+    }
+
+    /// Whether the mod with the identifier `id` has been disabled
+    pub fn is_disabled(&self, id: &str) -> bool {
+        self.disabled
+            .iter()
+            .any(|disabled| disabled.eq_ignore_ascii_case(id))
+    }
+
+    /// Forget the mod with the identifier `id` in the disabled mods, the groups,
+    /// and the release channel fallbacks, after it was removed
+    pub fn forget_mod(&mut self, id: &str) {
+        self.disabled
+            .retain(|disabled| !disabled.eq_ignore_ascii_case(id));
+        for group in &mut self.groups {
+            group.mods.retain(|mod_| !mod_.eq_ignore_ascii_case(id));
+        }
+        self.mod_channel_fallbacks
+            .retain(|fallback| !fallback.id.eq_ignore_ascii_case(id));
+    }
+
+    /// The group that the mod with the identifier `id` belongs to
+    pub fn group_of(&self, id: &str) -> Option<&ModGroup> {
+        self.groups.iter().find(|group| group.contains(id))
+    }
+
+    /// Whether the mod with the identifier `id` should be installed,
+    /// i.e. it has not been disabled and is not in a disabled group
+    pub fn is_installed(&self, id: &str) -> bool {
+        !self.is_disabled(id) && self.group_of(id).map_or(true, |group| group.enabled)
+    }
+
+    /// The least stable release channel that the mod with the identifier `id` can fall back to
+    pub fn channel_fallback_of(&self, id: &str) -> Option<&ReleaseChannel> {
+        self.mod_channel_fallbacks
+            .iter()
+            .find(|fallback| fallback.id.eq_ignore_ascii_case(id))
+            .map_or(self.channel_fallback.as_ref(), |fallback| {
+                Some(&fallback.channel)
+            })
+    }
+
+    pub fn is_mod_installed(&self, mod_: &Mod) -> bool {
+        self.is_installed(&platform_id(&mod_.identifier))
+    }
+
+    pub fn is_external_mod_installed(&self, mod_: &ExternalMod) -> bool {
+        self.is_installed(&mod_.identifier.display_id())
+    }
+
+    /// Whether this profile itself has a mod called `name`
+    fn has_mod(&self, name: &str) -> bool {
+        self.mods
+            .iter()
+            .map(|mod_| &mod_.name)
+            .chain(self.external_mods.iter().map(|mod_| &mod_.name))
+            .any(|mod_name| mod_name.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the inherited mod called `name` with the identifier `id` has been excluded
+    fn is_excluded(&self, name: &str, id: &str) -> bool {
+        self.exclude.iter().any(|excluded| {
+            excluded.eq_ignore_ascii_case(name) || excluded.eq_ignore_ascii_case(id)
+        })
+    }
+}
+
+/// A named set of optional mods in a profile
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ModGroup {
+    pub name: String,
+    /// Whether the mods in this group are installed
+    pub enabled: bool,
+    /// The identifiers of the mods in this group
+    pub mods: Vec<String>,
+}
+
+impl ModGroup {
+    pub fn contains(&self, id: &str) -> bool {
+        self.mods.iter().any(|mod_| mod_.eq_ignore_ascii_case(id))
+    }
+}
+
+/// The release channel fallback of a mod, replacing the profile's
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct ChannelFallback {
+    /// The identifier of the mod
+    pub id: String,
+    /// The least stable release channel to fall back to, where `release` disables falling back
+    pub channel: ReleaseChannel,
+}
+
+/// What to do with the files in an output directory that ferium did not install
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum UntrackedFiles {
+    /// Move them to the `.old` directory
+    #[default]
+    Move,
+    /// Leave them in the output directory
+    Keep,
+    /// Add the mods they belong to to the profile, using the same lookup as `ferium scan`
+    Adopt,
+}
+
+/// How long to keep the backups in the `.old` directory of an output directory
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OldRetention {
+    /// Keep the files replaced by this many of the latest upgrades, where `0` keeps all of them
+    Upgrades(usize),
+    /// Keep the files replaced in this many days
+    Days(u64),
+}
+
+impl Default for OldRetention {
+    fn default() -> Self {
+        Self::Upgrades(5)
+    }
+}
+
+impl Display for OldRetention {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Upgrades(1) => write!(f, "the last upgrade"),
+            Self::Upgrades(count) => write!(f, "the last {count} upgrades"),
+            Self::Days(1) => write!(f, "the last day"),
+            Self::Days(days) => write!(f, "the last {days} days"),
+        }
+    }
+}
+
+impl Deref for Profile {
+    type Target = BaseProfile;
+
+    fn deref(&self) -> &Self::Target {
+        &self.base
+    }
+}
+
+impl DerefMut for Profile {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.base
+    }
+}
+
+/// The identifier of a platform mod as the user would type it
+pub fn platform_id(identifier: &ModIdentifier) -> String {
+    match identifier {
+        ModIdentifier::CurseForgeProject(id) => id.to_string(),
+        ModIdentifier::ModrinthProject(id) => id.clone(),
+        ModIdentifier::GitHubRepository((owner, repo)) => format!("{owner}/{repo}"),
+    }
+}
+
+#[expect(clippy::trivially_copy_pass_by_ref, reason = "Required by serde")]
+const fn is_zero(n: &usize) -> bool {
+    *n == 0
+}
+
+/// Deserialise the given `input` into a config, migrating it to the current version first
+pub fn deserialise(input: &str) -> Result<(Config, migrate::Report)> {
+    if input.trim().is_empty() {
+        let config = Config {
+            version: migrate::CURRENT_VERSION,
+            ..Config::default()
+        };
+        return Ok((
+            config,
+            migrate::Report {
+                from: migrate::CURRENT_VERSION,
+                changes: Vec::new(),
+            },
+        ));
+    }
+    from_value(serde_json::from_str(input).context("The config file is not valid JSON")?)
+}
+
+/// Deserialise the config in `value`, migrating it to the current version first
+pub fn from_value(mut value: Value) -> Result<(Config, migrate::Report)> {
+    let report = migrate::migrate(&mut value)?;
+    let config = serde_path_to_error::deserialize(&value).map_err(|err| {
+        let location = describe_path(&value, err.path());
+        anyhow!("Invalid config {location}: {}", err.into_inner())
+    })?;
+    Ok((config, report))
+}
+
+/// Describe where `path` points to in `config`, naming the profile or modpack it is in
+fn describe_path(config: &Value, path: &serde_path_to_error::Path) -> String {
+    let segments = path.iter().collect_vec();
+    let field = format!("at `{path}`");
+    match segments.as_slice() {
+        [Segment::Map { key }, Segment::Seq { index }, ..]
+            if key == "profiles" || key == "modpacks" =>
+        {
+            let kind = if key == "profiles" {
+                "profile"
+            } else {
+                "modpack"
+            };
+            match config[key.as_str()][*index]["name"].as_str() {
+                Some(name) => format!("in {kind} `{name}` {field}"),
+                None => format!("in {kind} {} {field}", index + 1),
+            }
+        }
+        _ => field,
+    }
+}
+
+/// Serialise `config` and overwrite `config_file` with it
+pub fn serialise(config: &Config, config_file: &mut File) -> Result<()> {
+    config_file.set_len(0)?;
+    config_file.rewind()?;
+    serde_json::to_writer_pretty(&mut *config_file, config)?;
+    config_file.flush()?;
+    Ok(())
+}
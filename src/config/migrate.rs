@@ -0,0 +1,257 @@
+//! Upgrading config files written by older versions of ferium
+//!
+//! Each migration upgrades the config by one version, so an old config is upgraded step by step.
+//! Migrations work on the raw JSON, since older configs may not deserialise into the current structures.
+
+use anyhow::{ensure, Context as _, Result};
+use libium::config::{filters::Filter, structs::ModLoader};
+use serde_json::{Map, Value};
+use std::{
+    ffi::OsString,
+    fs::write,
+    mem::discriminant,
+    path::{Path, PathBuf},
+};
+
+/// The version of the config written by this version of ferium
+pub const CURRENT_VERSION: u64 = 2;
+
+struct Migration {
+    /// The version this migration upgrades the config to, from the one before it
+    to: u64,
+    /// Apply the migration, and describe each change made
+    apply: fn(&mut Map<String, Value>) -> Result<Vec<String>>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        to: 1,
+        apply: profile_filters,
+    },
+    Migration {
+        to: 2,
+        apply: replacing_mod_filters,
+    },
+];
+
+/// What was changed when migrating a config
+#[derive(Debug, Clone, Default)]
+pub struct Report {
+    /// The version the config was at before migrating
+    pub from: u64,
+    pub changes: Vec<String>,
+}
+
+impl Report {
+    /// Whether the config was written by an older version of ferium
+    pub const fn is_needed(&self) -> bool {
+        self.from < CURRENT_VERSION
+    }
+}
+
+/// Upgrade `config` to the current version
+pub fn migrate(config: &mut Value) -> Result<Report> {
+    let config = config
+        .as_object_mut()
+        .context("The config file does not contain a JSON object")?;
+    let from = match config.get("version") {
+        Some(version) => version
+            .as_u64()
+            .context("The config's `version` is not a whole number")?,
+        None => 0,
+    };
+    ensure!(
+        from <= CURRENT_VERSION,
+        "The config file is version {from}, but this version of ferium only supports up to version {CURRENT_VERSION}. Please update ferium"
+    );
+
+    let mut changes = Vec::new();
+    for migration in MIGRATIONS.iter().filter(|migration| migration.to > from) {
+        changes.extend((migration.apply)(config)?);
+        config.insert("version".to_owned(), migration.to.into());
+    }
+    Ok(Report { from, changes })
+}
+
+/// Write `contents`, the config before migrating from version `from`, next to `config_path`
+///
+/// An existing backup from the same version is kept, since the config is migrated again
+/// until it is saved, and the first backup is the one written before any migration.
+/// Returns the path of the backup.
+pub fn backup(config_path: &Path, contents: &str, from: u64) -> Result<PathBuf> {
+    let mut path = OsString::from(config_path);
+    path.push(format!(".v{from}.bak"));
+    let path = PathBuf::from(path);
+    if !path.exists() {
+        write(&path, contents)
+            .with_context(|| format!("Could not back up the config to {}", path.display()))?;
+    }
+    Ok(path)
+}
+
+/// Version 1 replaced each profile's `game_version` and `mod_loader` with `filters`,
+/// and each mod's `check_game_version` and `check_mod_loader` with its own `filters`
+fn profile_filters(config: &mut Map<String, Value>) -> Result<Vec<String>> {
+    let mut changes = Vec::new();
+    let Some(profiles) = config.get_mut("profiles").and_then(Value::as_array_mut) else {
+        return Ok(changes);
+    };
+
+    for profile in profiles.iter_mut().filter_map(Value::as_object_mut) {
+        let name = profile
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+
+        let mut filters = Vec::new();
+        if let Some(mod_loader) = profile.remove("mod_loader") {
+            let mod_loader: ModLoader = serde_json::from_value(mod_loader)
+                .with_context(|| format!("Invalid `mod_loader` in profile `{name}`"))?;
+            filters.push(Filter::ModLoaderPrefer(match mod_loader {
+                // Quilt can load most Fabric mods
+                ModLoader::Quilt => vec![ModLoader::Quilt, ModLoader::Fabric],
+                mod_loader => vec![mod_loader],
+            }));
+        }
+        if let Some(game_version) = profile.remove("game_version") {
+            let game_version: String = serde_json::from_value(game_version)
+                .with_context(|| format!("Invalid `game_version` in profile `{name}`"))?;
+            filters.push(Filter::GameVersionStrict(vec![game_version]));
+        }
+        if !filters.is_empty() {
+            let has_filters = profile
+                .get("filters")
+                .and_then(Value::as_array)
+                .is_some_and(|filters| !filters.is_empty());
+            if has_filters {
+                changes.push(format!(
+                    "Profile `{name}`: removed `game_version` and `mod_loader`, which were superseded by its filters"
+                ));
+            } else {
+                profile.insert("filters".to_owned(), serde_json::to_value(&filters)?);
+                changes.push(format!(
+                    "Profile `{name}`: replaced `game_version` and `mod_loader` with filters"
+                ));
+            }
+        }
+        let profile_filters: Vec<Filter> = match profile.get("filters") {
+            Some(filters) => serde_json::from_value(filters.clone())
+                .with_context(|| format!("Invalid `filters` in profile `{name}`"))?,
+            None => Vec::new(),
+        };
+
+        let Some(mods) = profile.get_mut("mods").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for mod_ in mods.iter_mut().filter_map(Value::as_object_mut) {
+            let check_game_version = mod_.remove("check_game_version");
+            let check_mod_loader = mod_.remove("check_mod_loader");
+            if check_game_version.is_none() && check_mod_loader.is_none() {
+                continue;
+            }
+            let mod_name = mod_
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            let check_game_version =
+                check_game_version.as_ref().and_then(Value::as_bool) != Some(false);
+            let check_mod_loader =
+                check_mod_loader.as_ref().and_then(Value::as_bool) != Some(false);
+            if check_game_version && check_mod_loader {
+                changes.push(format!(
+                    "Profile `{name}`, mod `{mod_name}`: removed redundant version and loader checks"
+                ));
+                continue;
+            }
+
+            // The mod overrides the profile's filters with all of them except the unchecked ones
+            let filters = profile_filters
+                .iter()
+                .filter(|filter| match filter {
+                    Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_) => {
+                        check_game_version
+                    }
+                    Filter::ModLoaderPrefer(_) | Filter::ModLoaderAny(_) => check_mod_loader,
+                    _ => true,
+                })
+                .collect::<Vec<_>>();
+            mod_.insert("filters".to_owned(), serde_json::to_value(filters)?);
+            mod_.insert("override_filters".to_owned(), true.into());
+            changes.push(format!(
+                "Profile `{name}`, mod `{mod_name}`: replaced disabled {} checks with filters",
+                match (check_game_version, check_mod_loader) {
+                    (false, false) => "version and loader",
+                    (false, true) => "version",
+                    _ => "loader",
+                }
+            ));
+        }
+    }
+    Ok(changes)
+}
+
+/// Version 2 made a mod's own filters replace the profile's filters of the same kind,
+/// instead of both being used
+///
+/// Mods with a filter of the same kind as one of the profile's keep both,
+/// by overriding the profile's filters with all of them.
+fn replacing_mod_filters(config: &mut Map<String, Value>) -> Result<Vec<String>> {
+    let mut changes = Vec::new();
+    let Some(profiles) = config.get_mut("profiles").and_then(Value::as_array_mut) else {
+        return Ok(changes);
+    };
+
+    for profile in profiles.iter_mut().filter_map(Value::as_object_mut) {
+        let name = profile
+            .get("name")
+            .and_then(Value::as_str)
+            .unwrap_or_default()
+            .to_owned();
+        let profile_filters: Vec<Filter> = match profile.get("filters") {
+            Some(filters) => serde_json::from_value(filters.clone())
+                .with_context(|| format!("Invalid `filters` in profile `{name}`"))?,
+            None => Vec::new(),
+        };
+
+        let Some(mods) = profile.get_mut("mods").and_then(Value::as_array_mut) else {
+            continue;
+        };
+        for mod_ in mods.iter_mut().filter_map(Value::as_object_mut) {
+            if mod_.get("override_filters").and_then(Value::as_bool) == Some(true) {
+                continue;
+            }
+            let mod_name = mod_
+                .get("name")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_owned();
+            let own_filters: Vec<Filter> = match mod_.get("filters") {
+                Some(filters) => serde_json::from_value(filters.clone()).with_context(|| {
+                    format!("Invalid `filters` of mod `{mod_name}` in profile `{name}`")
+                })?,
+                None => Vec::new(),
+            };
+            let replaced = own_filters.iter().any(|own| {
+                profile_filters
+                    .iter()
+                    .any(|filter| discriminant(filter) == discriminant(own))
+            });
+            if !replaced {
+                continue;
+            }
+
+            let filters = profile_filters
+                .iter()
+                .chain(&own_filters)
+                .collect::<Vec<_>>();
+            mod_.insert("filters".to_owned(), serde_json::to_value(filters)?);
+            mod_.insert("override_filters".to_owned(), true.into());
+            changes.push(format!(
+                "Profile `{name}`, mod `{mod_name}`: kept the profile's filters alongside the mod's own, which would now replace them"
+            ));
+        }
+    }
+    Ok(changes)
+}
@@ -0,0 +1,152 @@
+//! Project-local `ferium.toml` files, which describe a single profile
+//!
+//! When a `ferium.toml` is found in the current directory or one of its parents,
+//! it is used instead of the global config, so that a pack can be version controlled with its project.
+//! The output directory and local files are relative to the file.
+//! The file can also have `[api]` and `[network]` tables, which work like the global config's.
+
+use super::{migrate, Config, Profile};
+use crate::{
+    api::ApiUrls,
+    cli::{ProfileSubCommands, SubCommands},
+    network::NetworkSettings,
+    sources::SourceIdentifier,
+};
+use anyhow::{bail, ensure, Context as _, Result};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{
+    fs::write,
+    path::{Path, PathBuf},
+};
+
+pub const FILE_NAME: &str = "ferium.toml";
+
+#[derive(Serialize)]
+struct WorkspaceFile<'a> {
+    version: u64,
+    #[serde(flatten)]
+    profile: &'a Profile,
+    #[serde(skip_serializing_if = "ApiUrls::is_empty")]
+    api: &'a ApiUrls,
+    #[serde(skip_serializing_if = "NetworkSettings::is_empty")]
+    network: &'a NetworkSettings,
+}
+
+/// Find the workspace file in `directory` or its closest parent
+pub fn find(directory: &Path) -> Option<PathBuf> {
+    directory
+        .ancestors()
+        .map(|directory| directory.join(FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Check that `command` can be run on the workspace file at `path`,
+/// before it does anything that could not be written back to the file
+pub fn check_command(command: &SubCommands, path: &Path) -> Result<()> {
+    let unsupported = match command {
+        SubCommands::Modpack { .. } | SubCommands::Modpacks => Some("modpacks"),
+        SubCommands::Profile {
+            subcommand:
+                Some(
+                    ProfileSubCommands::Create { .. }
+                    | ProfileSubCommands::Delete { .. }
+                    | ProfileSubCommands::Migrate {
+                        create: Some(_), ..
+                    },
+                ),
+        } => Some("other profiles"),
+        _ => None,
+    };
+    if let Some(unsupported) = unsupported {
+        bail!(
+            "{} can only contain a single profile, use `--config-file` to manage {unsupported}",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+/// Deserialise the workspace file at `path`, with the given `contents`,
+/// into a config containing only its profile
+///
+/// `path` should be absolute, so that the paths in the file can be resolved relative to it.
+pub fn deserialise(contents: &str, path: &Path) -> Result<(Config, migrate::Report)> {
+    let table: toml::Table =
+        toml::from_str(contents).with_context(|| format!("Could not parse {}", path.display()))?;
+    let mut profile = serde_json::to_value(table)?;
+    let mut config = json!({});
+    if let Some(fields) = profile.as_object_mut() {
+        for field in ["version", "api", "network"] {
+            if let Some(value) = fields.remove(field) {
+                config[field] = value;
+            }
+        }
+    }
+    for field in ["mods", "filters"] {
+        if profile.get(field).is_none() {
+            profile[field] = json!([]);
+        }
+    }
+    config["profiles"] = json!([profile]);
+    let (mut config, report) = super::from_value(config)?;
+
+    let directory = directory(path);
+    for profile in &mut config.profiles {
+        if profile.output_dir.is_relative() {
+            profile.output_dir = directory.join(&profile.output_dir);
+        }
+        for mod_ in &mut profile.external_mods {
+            if let SourceIdentifier::LocalFile { path, .. } = &mut mod_.identifier {
+                if path.is_relative() {
+                    *path = directory.join(&*path);
+                }
+            }
+        }
+    }
+    Ok((config, report))
+}
+
+/// Write the profile in `config` back to the workspace file at `path`
+pub fn serialise(config: &Config, path: &Path) -> Result<()> {
+    ensure!(
+        config.profiles.len() == 1 && config.modpacks.is_empty(),
+        "{} can only contain a single profile, use `--config-file` to manage other profiles and modpacks",
+        path.display()
+    );
+    let directory = directory(path);
+    let mut profile = config.profiles[0].clone();
+    profile.output_dir = relative_to(&profile.output_dir, directory);
+    for mod_ in &mut profile.external_mods {
+        if let SourceIdentifier::LocalFile { path, .. } = &mut mod_.identifier {
+            *path = relative_to(path, directory);
+        }
+    }
+
+    let contents = toml::to_string_pretty(&WorkspaceFile {
+        version: migrate::CURRENT_VERSION,
+        profile: &profile,
+        api: &config.api,
+        network: &config.network,
+    })?;
+    write(path, contents).with_context(|| format!("Could not write {}", path.display()))?;
+    Ok(())
+}
+
+/// Whether `config` has changed since it was deserialised as `original`
+pub fn has_changed(config: &Config, original: &Value) -> Result<bool> {
+    Ok(serde_json::to_value(config)? != *original)
+}
+
+fn directory(path: &Path) -> &Path {
+    path.parent().unwrap_or(Path::new("."))
+}
+
+/// `path` relative to `directory` if it is inside it, otherwise `path` itself
+fn relative_to(path: &Path, directory: &Path) -> PathBuf {
+    match path.strip_prefix(directory) {
+        Ok(relative) if relative.as_os_str().is_empty() => PathBuf::from("."),
+        Ok(relative) => relative.to_owned(),
+        Err(_) => path.to_owned(),
+    }
+}
@@ -1,38 +1,152 @@
 #![expect(clippy::expect_used, reason = "Для ядовитых мьютексов")]
 
-use crate::{DEFAULT_PARALLEL_NETWORK, PARALLEL_NETWORK, STYLE_BYTE, TICK};
-use anyhow::{anyhow, bail, Error, Result};
-use colored::Colorize as _;
-use fs_extra::{
-    dir::{copy as copy_dir, CopyOptions as DirCopyOptions},
-    file::{move_file, CopyOptions as FileCopyOptions},
+use crate::{
+    api,
+    backup::{self, Backup},
+    config::{OldRetention, UntrackedFiles},
+    network::CLIENT,
+    CROSS, DEFAULT_DOWNLOAD_RETRIES, DOWNLOAD_RETRIES, NETWORK_PERMITS, STYLE_BYTE, TICK,
 };
-use futures::{stream::FuturesUnordered, StreamExt as _};
+use anyhow::{bail, Error, Result};
+use colored::Colorize as _;
+use fs_extra::dir::{copy as copy_dir, CopyOptions as DirCopyOptions};
+use futures::{channel::mpsc::UnboundedSender, stream::FuturesUnordered, StreamExt as _};
 use indicatif::ProgressBar;
+use inquire::Select;
 use libium::{iter_ext::IterExt as _, upgrade::DownloadData};
+use reqwest::{header::RANGE, Client, StatusCode};
+use serde::{Deserialize, Serialize};
 use std::{
+    collections::BTreeMap,
     ffi::OsString,
-    fs::{copy, create_dir_all, read_dir, remove_file},
+    fmt::Display,
+    fs::{
+        copy, create_dir_all, read_dir, read_to_string, remove_file, rename, write, File,
+        OpenOptions,
+    },
+    future::Future,
+    io::Write as _,
     path::{Path, PathBuf},
     sync::{Arc, Mutex},
-    time::Duration,
+    time::{Duration, SystemTime, UNIX_EPOCH},
 };
-use tokio::sync::Semaphore;
+use tokio::time::sleep;
+
+/// Файл в папке вывода, в котором записано, какие файлы установил ferium
+const INSTALLED_LIST: &str = ".ferium-installed.json";
+
+/// Что происходит во время обновления, когда его прогресс показывает `ferium tui`, а не терминал
+pub enum Event {
+    /// Сообщение, которое иначе было бы напечатано
+    Message(String),
+    /// Файл поставлен в очередь на загрузку, с его размером в байтах
+    Queued(String, u64),
+    /// Для файла загружено столько байтов
+    Downloaded(String, u64),
+    /// Файл загружен, или не загружен из-за ошибки
+    Finished(String, Option<String>),
+}
+
+tokio::task_local! {
+    static EVENTS: UnboundedSender<Event>;
+}
+
+/// Выполняет `future` так, что события обновления отправляются в `events`, а не печатаются
+pub async fn with_events<F: Future>(events: UnboundedSender<Event>, future: F) -> F::Output {
+    EVENTS.scope(events, future).await
+}
+
+/// Отправляет `event`, если прогресс показывает интерфейс
+///
+/// Возвращает, было ли событие отправлено.
+fn send(event: Event) -> bool {
+    EVENTS
+        .try_with(|events| {
+            let _ = events.unbounded_send(event);
+        })
+        .is_ok()
+}
+
+/// Печатает `message` в stderr, как и индикаторы прогресса, или отправляет его, если прогресс показывает интерфейс
+pub fn report(message: impl Display) {
+    if !send(Event::Message(message.to_string())) {
+        eprintln!("{message}");
+    }
+}
+
+/// Печатает `message` над `progress_bar`, или отправляет его, если прогресс показывает интерфейс
+pub fn report_above(progress_bar: &ProgressBar, message: impl Display) {
+    if !send(Event::Message(message.to_string())) {
+        progress_bar.println(message.to_string());
+    }
+}
+
+/// Индикатор прогресса до `length`, который скрыт, если прогресс показывает интерфейс
+pub fn progress_bar(length: u64) -> ProgressBar {
+    if EVENTS.try_with(|_| ()).is_ok() {
+        ProgressBar::hidden()
+    } else {
+        ProgressBar::new(length)
+    }
+}
+
+/// Файлы, которые ferium установил в папку вывода, и посторонние файлы, которые решено оставить
+#[derive(Deserialize, Serialize, Default)]
+struct InstalledList {
+    #[serde(default)]
+    installed: Vec<String>,
+    #[serde(default)]
+    kept: Vec<String>,
+    /// Имя файла, под которым установлен каждый мод, по ID мода
+    #[serde(default)]
+    mods: BTreeMap<String, String>,
+}
+
+impl InstalledList {
+    /// Читает список из папки вывода `directory`, или возвращает `None`, если его там нет
+    fn read(directory: &Path) -> Option<Self> {
+        read_to_string(directory.join(INSTALLED_LIST))
+            .ok()
+            .and_then(|list| serde_json::from_str(&list).ok())
+    }
+
+    fn write(&self, directory: &Path) -> Result<()> {
+        Ok(write(
+            directory.join(INSTALLED_LIST),
+            serde_json::to_string_pretty(self)?,
+        )?)
+    }
+}
 
 /// Проверяет данную `directory`
 ///
-/// - Если там есть файлы, которых нет в `to_download` или `to_install`, они будут перемещены в `directory`/.old
+/// - Если там есть файлы, которые ferium установил раньше, но которых нет в `to_download` или `to_install`,
+///   они будут перемещены в новую резервную копию в `directory`/.old. Если перемещение не удалось,
+///   файл остаётся на месте и будет перемещён при следующем обновлении
+/// - Если списка установленных файлов ещё нет, установленными считаются файлы из `to_download` и `to_install`
+/// - С посторонними файлами, которые ferium не устанавливал, поступают согласно `untracked`,
+///   или как выберет пользователь, если `ask`. Они никогда не удаляются
 /// - Если файл в `to_download` или `to_install` уже там, он будет удалён из соответствующего вектора
-/// - Если файл является `.part` файлом или если перемещение не удалось, файл будет удалён
+/// - Если файл является `.part` файлом файла из `to_download`, он остаётся, чтобы загрузку можно было продолжить
+/// - Если файл является другим `.part` файлом, он будет удалён
+/// - Файлы `.disabled` модов с ID из `disabled` остаются на месте, другие файлы `.disabled` считаются
+///   файлами, которые ferium установил раньше, если он установил файл с таким же именем без `.disabled`,
+///   или посторонними файлами
+/// - Старые резервные копии удаляются согласно `retention`
+///
+/// Возвращает имена посторонних файлов, которые нужно добавить в профиль. Они остаются на месте.
 pub async fn clean(
     directory: &Path,
     to_download: &mut Vec<DownloadData>,
     to_install: &mut Vec<(OsString, PathBuf)>,
-) -> Result<()> {
+    untracked: UntrackedFiles,
+    ask: bool,
+    retention: OldRetention,
+    disabled: &[String],
+) -> Result<Vec<String>> {
     let dupes = find_dupes_by_key(to_download, DownloadData::filename);
     if !dupes.is_empty() {
-        println!(
-            "{}",
+        report(
             format!(
                 "Предупреждение: {} дублирующих файла(ов) найдено {}. Удалите мод, к которому он принадлежит",
                 dupes.len(),
@@ -45,7 +159,37 @@ pub async fn clean(
             .bold()
         );
     }
-    create_dir_all(directory.join(".old"))?;
+    let backup = Backup::new(directory);
+    let mut list = InstalledList {
+        installed: to_download
+            .iter()
+            .map(DownloadData::filename)
+            .chain(
+                to_install
+                    .iter()
+                    .map(|thing| thing.0.to_string_lossy().into_owned()),
+            )
+            .collect(),
+        ..Default::default()
+    };
+    let previous = InstalledList::read(directory).unwrap_or_else(|| InstalledList {
+        installed: list.installed.clone(),
+        ..Default::default()
+    });
+    list.mods.clone_from(&previous.mods);
+    // Файлы, в которые были переименованы файлы отключённых модов
+    let disabled_files = previous
+        .mods
+        .iter()
+        .filter(|(id, _)| {
+            disabled
+                .iter()
+                .any(|disabled| disabled.eq_ignore_ascii_case(id))
+        })
+        .map(|(_, filename)| format!("{filename}.disabled"))
+        .collect_vec();
+
+    let mut unknown = Vec::new();
     for file in read_dir(directory)? {
         let file = file?;
         // Если это файл
@@ -53,6 +197,14 @@ pub async fn clean(
             let filename = file.file_name();
             let filename = filename.to_string_lossy();
             let filename = filename.as_ref();
+            // Отключённые моды не трогаем, лаунчеры их не загружают
+            if disabled_files.iter().any(|f| f == filename) || filename == INSTALLED_LIST {
+                continue;
+            }
+            // Частично загруженный файл остаётся, чтобы продолжить его загрузку
+            let resumable = filename.strip_suffix(".part").is_some_and(|filename| {
+                to_download.iter().any(|thing| filename == thing.filename())
+            });
             // Если он уже загружен
             if let Some(index) = to_download
                 .iter()
@@ -64,21 +216,124 @@ pub async fn clean(
             } else if let Some(index) = to_install.iter().position(|thing| filename == thing.0) {
                 // Не устанавливать его
                 to_install.swap_remove(index);
-            // В противном случае, переместить файл в `directory`/.old
-            // Если файл является `.part` файлом или если перемещение не удалось, удалить файл
-            } else if filename.ends_with("part")
-                || move_file(
-                    file.path(),
-                    directory.join(".old").join(filename),
-                    &FileCopyOptions::new(),
-                )
-                .is_err()
+            // Другие `.part` файлы удаляются
+            } else if filename.ends_with(".part") {
+                if !resumable {
+                    remove_file(file.path())?;
+                }
+            // Файлы, которые ferium установил раньше, перемещаются в резервную копию
+            } else if previous
+                .installed
+                .iter()
+                .any(|f| f == filename.strip_suffix(".disabled").unwrap_or(filename))
             {
-                remove_file(file.path())?;
+                if let Err(err) = backup.store(&file.path()) {
+                    report(
+                        format!("Не удалось переместить {filename} в .old, файл оставлен на месте: {err}")
+                            .yellow(),
+                    );
+                    // Попробовать снова при следующем обновлении
+                    list.installed.push(filename.to_owned());
+                }
+            } else if previous.kept.iter().any(|f| f == filename) {
+                list.kept.push(filename.to_owned());
+            } else {
+                unknown.push(filename.to_owned());
             }
         }
     }
-    Ok(())
+
+    let mut adopted = Vec::new();
+    if !unknown.is_empty() {
+        report("Эти файлы в папке вывода установлены не ferium:".yellow());
+        for filename in &unknown {
+            report(format!("  {}", filename.dimmed()));
+        }
+        let action = if ask {
+            pick_untracked_action(untracked)
+        } else {
+            untracked
+        };
+        match action {
+            UntrackedFiles::Move => {
+                for filename in &unknown {
+                    if backup.store(&directory.join(filename)).is_err() {
+                        report(
+                            format!(
+                                "Не удалось переместить {filename} в .old, файл оставлен на месте"
+                            )
+                            .yellow(),
+                        );
+                    }
+                }
+            }
+            UntrackedFiles::Keep => list.kept.extend(unknown),
+            // После добавления в профиль файлы будут заменены при следующем обновлении
+            UntrackedFiles::Adopt => {
+                list.installed.extend(unknown.iter().cloned());
+                adopted = unknown;
+            }
+        }
+    }
+    list.write(directory)?;
+    backup::prune(directory, retention)?;
+    Ok(adopted)
+}
+
+/// Записывает, что файл `filename` в папке вывода `directory` установил ferium,
+/// чтобы при следующем обновлении он был перемещён в резервную копию без вопросов
+pub fn mark_installed(directory: &Path, filename: &str) -> Result<()> {
+    let mut list = InstalledList::read(directory).unwrap_or_default();
+    list.kept.retain(|f| f != filename);
+    if !list.installed.iter().any(|f| f == filename) {
+        list.installed.push(filename.to_owned());
+    }
+    list.write(directory)
+}
+
+/// Записывает имена `files`, под которыми установлены моды в папке вывода `directory`, по ID модов
+///
+/// Записи остальных модов, например отключённых, сохраняются.
+pub fn record_mod_files(
+    directory: &Path,
+    files: impl IntoIterator<Item = (String, String)>,
+) -> Result<()> {
+    let mut list = InstalledList::read(directory).unwrap_or_default();
+    list.mods.extend(files);
+    list.write(directory)
+}
+
+/// Имя файла, под которым установлен мод с ID `id` в папке вывода `directory`, если оно записано
+pub fn installed_file(directory: &Path, id: &str) -> Option<String> {
+    InstalledList::read(directory)?.mods.remove(id)
+}
+
+/// Спрашивает, что сделать с посторонними файлами, по умолчанию `default`
+///
+/// Если спросить не удалось, файлы остаются на месте.
+fn pick_untracked_action(default: UntrackedFiles) -> UntrackedFiles {
+    let actions = [
+        UntrackedFiles::Adopt,
+        UntrackedFiles::Keep,
+        UntrackedFiles::Move,
+    ];
+    Select::new(
+        "Что с ними сделать?",
+        vec![
+            "Добавить их моды в профиль",
+            "Оставить их и больше не спрашивать",
+            "Переместить их в .old",
+        ],
+    )
+    .with_starting_cursor(
+        actions
+            .iter()
+            .position(|action| *action == default)
+            .unwrap_or_default(),
+    )
+    .without_filtering()
+    .raw_prompt()
+    .map_or(UntrackedFiles::Keep, |selection| actions[selection.index])
 }
 
 /// Конструирует вектор `to_install` из `directory`
@@ -93,16 +348,46 @@ pub fn read_overrides(directory: &Path) -> Result<Vec<(OsString, PathBuf)>> {
     Ok(to_install)
 }
 
+/// Файлы, которые нужно загрузить и установить в одну папку вывода
+pub struct Batch {
+    pub output_dir: PathBuf,
+    pub to_download: Vec<DownloadData>,
+    pub to_install: Vec<(OsString, PathBuf)>,
+}
+
+impl Batch {
+    pub fn is_empty(&self) -> bool {
+        self.to_download.is_empty() && self.to_install.is_empty()
+    }
+}
+
 /// Загружает и устанавливает файлы в `to_download` и `to_install` в `output_dir`
 pub async fn download(
     output_dir: PathBuf,
     to_download: Vec<DownloadData>,
     to_install: Vec<(OsString, PathBuf)>,
 ) -> Result<()> {
+    download_many(vec![Batch {
+        output_dir,
+        to_download,
+        to_install,
+    }])
+    .await
+    .into_iter()
+    .next()
+    .unwrap_or(Ok(()))
+}
+
+/// Загружает и устанавливает файлы всех `batches` одновременно, так что все загрузки
+/// делят одни и те же разрешения на сетевые запросы и один индикатор прогресса
+///
+/// Возвращает результат каждого пакета в том же порядке.
+pub async fn download_many(batches: Vec<Batch>) -> Vec<Result<()>> {
     let progress_bar = Arc::new(Mutex::new(
-        ProgressBar::new(
-            to_download
+        progress_bar(
+            batches
                 .iter()
+                .flat_map(|batch| &batch.to_download)
                 .map(|downloadable| downloadable.length as u64)
                 .sum(),
         )
@@ -113,70 +398,254 @@ pub async fn download(
         .expect("Мьютекс отравлен")
         .enable_steady_tick(Duration::from_millis(100));
     let mut tasks = FuturesUnordered::new();
-    let semaphore = Arc::new(Semaphore::new(
-        *PARALLEL_NETWORK.get_or_init(|| DEFAULT_PARALLEL_NETWORK),
-    ));
-    let client = reqwest::Client::new();
-
-    for downloadable in to_download {
-        let semaphore = Arc::clone(&semaphore);
-        let progress_bar = Arc::clone(&progress_bar);
-        let client = client.clone();
-        let output_dir = output_dir.clone();
-
-        tasks.push(async move {
-            let _permit = semaphore.acquire_owned().await?;
-
-            let (length, filename) = downloadable
-                .download(client, &output_dir, |additional| {
-                    progress_bar
-                        .lock()
-                        .expect("Мьютекс отравлен")
-                        .inc(additional as u64);
-                })
-                .await?;
-            progress_bar
-                .lock()
-                .expect("Мьютекс отравлен")
-                .println(format!(
-                    "{} Загружено  {:>7}  {}",
-                    &*TICK,
-                    size::Size::from_bytes(length)
-                        .format()
-                        .with_base(size::Base::Base10)
-                        .to_string(),
-                    filename.dimmed(),
-                ));
-            Ok::<(), Error>(())
-        });
+    let client = CLIENT.clone();
+
+    let mut pending = Vec::new();
+    for (index, batch) in batches.into_iter().enumerate() {
+        for downloadable in batch.to_download {
+            let progress_bar = Arc::clone(&progress_bar);
+            let client = client.clone();
+            let output_dir = batch.output_dir.clone();
+            send(Event::Queued(
+                downloadable.filename(),
+                downloadable.length as u64,
+            ));
+
+            tasks.push(async move {
+                let (length, filename) =
+                    match download_with_retries(&client, &downloadable, &output_dir, &progress_bar)
+                        .await
+                    {
+                        Ok(downloaded) => downloaded,
+                        Err(err) => {
+                            send(Event::Finished(
+                                downloadable.filename(),
+                                Some(err.to_string()),
+                            ));
+                            progress_bar
+                                .lock()
+                                .expect("Мьютекс отравлен")
+                                .println(format!(
+                                    "{}",
+                                    format!(
+                                        "{CROSS} Ошибка     {}  {err}",
+                                        downloadable.filename()
+                                    )
+                                    .red()
+                                ));
+                            return Err((index, downloadable.filename(), err));
+                        }
+                    };
+                send(Event::Finished(downloadable.filename(), None));
+                progress_bar
+                    .lock()
+                    .expect("Мьютекс отравлен")
+                    .println(format!(
+                        "{} Загружено  {:>7}  {}",
+                        &*TICK,
+                        size::Size::from_bytes(length)
+                            .format()
+                            .with_base(size::Base::Base10)
+                            .to_string(),
+                        filename.dimmed(),
+                    ));
+                Ok(())
+            });
+        }
+        pending.push((batch.output_dir, batch.to_install));
     }
+    let mut failures = pending.iter().map(|_| Vec::new()).collect_vec();
     while let Some(res) = tasks.next().await {
-        res?;
+        if let Err((index, filename, err)) = res {
+            failures[index].push((filename, err));
+        }
     }
-    Arc::try_unwrap(progress_bar)
-        .map_err(|_| anyhow!("Не удалось завершить выполнение потоков"))?
-        .into_inner()?
+    progress_bar
+        .lock()
+        .expect("Мьютекс отравлен")
         .finish_and_clear();
+
+    pending
+        .into_iter()
+        .zip(failures)
+        .map(|((output_dir, to_install), failures)| {
+            install(&output_dir, to_install)?;
+            if !failures.is_empty() {
+                bail!(
+                    "\nНе удалось загрузить {} файл(ов):\n{}",
+                    failures.len(),
+                    failures
+                        .iter()
+                        .map(|(filename, err)| format!("  {filename}: {err}"))
+                        .display("\n")
+                );
+            }
+            Ok(())
+        })
+        .collect()
+}
+
+/// Копирует файлы и папки в `to_install` в `output_dir`
+fn install(output_dir: &Path, to_install: Vec<(OsString, PathBuf)>) -> Result<()> {
     for (name, path) in to_install {
         if path.is_file() {
             copy(path, output_dir.join(&name))?;
         } else if path.is_dir() {
             let mut copy_options = DirCopyOptions::new();
             copy_options.overwrite = true;
-            copy_dir(path, &output_dir, &copy_options)?;
+            copy_dir(path, output_dir, &copy_options)?;
         } else {
             bail!("Не удалось определить, является ли устанавливаемое файл или папкой")
         }
-        println!(
+        report(format!(
             "{} Установлено          {}",
             &*TICK,
             name.to_string_lossy().dimmed()
-        );
+        ));
     }
-
     Ok(())
 }
 
+/// Загружает `downloadable`, повторяя попытку с экспоненциальной задержкой в случае ошибки
+///
+/// Количество повторов задаётся с помощью `--retries` или настройки `retries` в разделе `network`
+async fn download_with_retries(
+    client: &Client,
+    downloadable: &DownloadData,
+    output_dir: &Path,
+    progress_bar: &Mutex<ProgressBar>,
+) -> Result<(usize, String)> {
+    let retries = *DOWNLOAD_RETRIES.get_or_init(|| DEFAULT_DOWNLOAD_RETRIES);
+    // Количество байтов этого файла, уже учтённых индикатором прогресса
+    let mut counted = 0;
+    let mut attempt = 0;
+    loop {
+        let result = {
+            let _permit = Arc::clone(&NETWORK_PERMITS).acquire_owned().await?;
+            download_resumable(client, downloadable, output_dir, progress_bar, &mut counted).await
+        };
+        match result {
+            Err(err) if attempt < retries && is_retryable(&err) => {
+                attempt += 1;
+                let delay = backoff(attempt);
+                report_above(
+                    &progress_bar.lock().expect("Мьютекс отравлен"),
+                    format!(
+                        "Ошибка загрузки {}: {err}, повтор {attempt}/{retries} через {:.1} с",
+                        downloadable.filename(),
+                        delay.as_secs_f32()
+                    )
+                    .yellow(),
+                );
+                // Разрешение освобождено, поэтому другие файлы загружаются во время ожидания
+                sleep(delay).await;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// Загружает `downloadable` в `.part` файл, продолжая с того места, где закончилась предыдущая попытка,
+/// и переименовывает его после завершения
+///
+/// `counted` — количество байтов, уже учтённых индикатором прогресса
+async fn download_resumable(
+    client: &Client,
+    downloadable: &DownloadData,
+    output_dir: &Path,
+    progress_bar: &Mutex<ProgressBar>,
+    counted: &mut u64,
+) -> Result<(usize, String)> {
+    let out_file_path = output_dir.join(&downloadable.output);
+    let mut part_path = out_file_path.clone().into_os_string();
+    part_path.push(".part");
+    let part_path = PathBuf::from(part_path);
+    if let Some(parent) = out_file_path.parent() {
+        create_dir_all(parent)?;
+    }
+
+    let mut downloaded = part_path.metadata().map_or(0, |metadata| metadata.len());
+    update_progress(progress_bar, downloadable, counted, downloaded);
+
+    let mut request = client.get(api::download_url(&downloadable.download_url));
+    if downloaded > 0 {
+        request = request.header(RANGE, format!("bytes={downloaded}-"));
+    }
+    let mut response = request.send().await?;
+
+    if response.status() == StatusCode::RANGE_NOT_SATISFIABLE {
+        // `.part` файл уже содержит весь файл, или он повреждён
+        if downloaded != downloadable.length as u64 {
+            remove_file(&part_path)?;
+            update_progress(progress_bar, downloadable, counted, 0);
+            bail!("Частично загруженный файл повреждён");
+        }
+    } else {
+        response = response.error_for_status()?;
+        let mut file = if response.status() == StatusCode::PARTIAL_CONTENT {
+            OpenOptions::new().append(true).open(&part_path)?
+        } else {
+            // Сервер не поддерживает запросы диапазонов, поэтому загрузка начинается заново
+            downloaded = 0;
+            update_progress(progress_bar, downloadable, counted, 0);
+            File::create(&part_path)?
+        };
+        while let Some(chunk) = response.chunk().await? {
+            file.write_all(&chunk)?;
+            downloaded += chunk.len() as u64;
+            update_progress(progress_bar, downloadable, counted, downloaded);
+        }
+        file.flush()?;
+    }
+
+    rename(&part_path, &out_file_path)?;
+    Ok((usize::try_from(downloaded)?, downloadable.filename()))
+}
+
+/// Обновляет индикатор прогресса так, чтобы для `downloadable` было учтено `downloaded` байтов
+fn update_progress(
+    progress_bar: &Mutex<ProgressBar>,
+    downloadable: &DownloadData,
+    counted: &mut u64,
+    downloaded: u64,
+) {
+    send(Event::Downloaded(downloadable.filename(), downloaded));
+    let progress_bar = progress_bar.lock().expect("Мьютекс отравлен");
+    if downloaded > *counted {
+        progress_bar.inc(downloaded - *counted);
+    } else {
+        progress_bar.dec(*counted - downloaded);
+    }
+    *counted = downloaded;
+}
+
+/// Стоит ли повторять загрузку после ошибки `err`
+///
+/// Ошибки клиента, такие как 404, не исправятся при повторе, кроме таймаута и превышения лимита запросов
+fn is_retryable(err: &Error) -> bool {
+    !err.downcast_ref::<reqwest::Error>()
+        .and_then(reqwest::Error::status)
+        .is_some_and(|status| {
+            status.is_client_error()
+                && status != StatusCode::REQUEST_TIMEOUT
+                && status != StatusCode::TOO_MANY_REQUESTS
+        })
+}
+
+/// Задержка перед повтором `attempt`, которая удваивается с каждой попыткой (до 30 секунд),
+/// с разбросом до половины задержки, чтобы повторы разных файлов не совпадали
+///
+/// Разброс берётся из наносекунд текущего времени, которых для этого достаточно.
+fn backoff(attempt: u32) -> Duration {
+    let base = Duration::from_millis(500)
+        .saturating_mul(2_u32.saturating_pow(attempt - 1))
+        .min(Duration::from_secs(30));
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |time| time.subsec_nanos());
+    base + base.mul_f64(f64::from(nanos) / 2e9)
+}
+
 /// Находит дубликаты элементов в `slice`, используя значение, полученное с помощью замыкания `key`
 ///
 /// Возвращает индексы дублирующих элементов в обратном порядке для удобного удаления
@@ -16,42 +16,66 @@
 #![expect(clippy::multiple_crate_versions, clippy::too_many_lines)]
 
 mod add;
+mod api;
+mod backup;
 mod cli;
+mod config;
 mod download;
 mod file_picker;
+mod network;
+mod rate_limit;
+mod scan;
+mod sources;
 mod subcommands;
 
-use anyhow::{anyhow, bail, ensure, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use clap::{CommandFactory, Parser};
-use cli::{Ferium, ModpackSubCommands, ProfileSubCommands, SubCommands};
+use cli::{
+    ConfigSubCommands, Ferium, GroupSubCommands, ModpackSubCommands, OldSubCommands,
+    ProfileSubCommands, SubCommands,
+};
 use colored::{ColoredString, Colorize};
+use config::{Config, Inherited, Profile};
 use indicatif::ProgressStyle;
 use libium::{
     config::{
-        self,
         filters::ProfileParameters as _,
-        structs::{Config, ModIdentifier, Modpack, Profile},
+        structs::{ModIdentifier, Modpack},
         DEFAULT_CONFIG_PATH,
     },
     iter_ext::IterExt as _,
 };
 use std::{
-    env::{set_var, var_os},
+    env::{current_dir, set_var, var_os},
+    fs::read_to_string,
+    io::{stdin, IsTerminal as _},
     process::ExitCode,
-    sync::{LazyLock, OnceLock},
+    sync::{Arc, LazyLock, OnceLock},
 };
+use tokio::sync::Semaphore;
 
 const CROSS: &str = "×";
 static TICK: LazyLock<ColoredString> = LazyLock::new(|| "✓".green());
 
 pub static PARALLEL_NETWORK: OnceLock<usize> = OnceLock::new();
+/// Профиль и сборка, выбранные с помощью `--profile` и `--modpack` вместо активных
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+static SELECTED_MODPACK: OnceLock<String> = OnceLock::new();
 pub const DEFAULT_PARALLEL_NETWORK: usize = 10;
+pub static DOWNLOAD_RETRIES: OnceLock<u32> = OnceLock::new();
+pub const DEFAULT_DOWNLOAD_RETRIES: u32 = 3;
+/// Разрешения на сетевые запросы, общие для всех профилей и сборок в одном запуске
+pub static NETWORK_PERMITS: LazyLock<Arc<Semaphore>> = LazyLock::new(|| {
+    Arc::new(Semaphore::new(
+        *PARALLEL_NETWORK.get_or_init(|| DEFAULT_PARALLEL_NETWORK),
+    ))
+});
 
 /// Темы Indicatif
 #[expect(clippy::expect_used)]
 pub static STYLE_NO: LazyLock<ProgressStyle> = LazyLock::new(|| {
     ProgressStyle::default_bar()
-        .template("{spinner} {elapsed} [{wide_bar:.cyan/blue}] {pos:.cyan}/{len:.blue}")
+        .template("{spinner} {elapsed} [{wide_bar:.cyan/blue}] {pos:.cyan}/{len:.blue} {msg}")
         .expect("Ошибка разбора шаблона индикатора прогресса")
         .progress_chars("#>-")
 });
@@ -140,14 +164,75 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
     if let Some(n) = cli_app.parallel_network {
         let _ = PARALLEL_NETWORK.set(n);
     }
+    if let Some(name) = cli_app.profile {
+        let _ = SELECTED_PROFILE.set(name);
+    }
+    if let Some(name) = cli_app.modpack {
+        let _ = SELECTED_MODPACK.set(name);
+    }
 
-    let mut config_file = config::get_file(
-        &cli_app
-            .config_file
-            .or_else(|| var_os("FERIUM_CONFIG_FILE").map(Into::into))
-            .unwrap_or(DEFAULT_CONFIG_PATH.clone()),
-    )?;
-    let mut config = config::deserialise(&libium::read_wrapper(&mut config_file)?)?;
+    let explicit_path = cli_app
+        .config_file
+        .or_else(|| var_os("FERIUM_CONFIG_FILE").map(Into::into));
+    // Если путь не указан, используется ferium.toml из текущей папки или её родителей
+    let workspace = match &explicit_path {
+        Some(path) => path
+            .extension()
+            .is_some_and(|extension| extension == "toml")
+            .then(|| path.clone()),
+        None => config::workspace::find(&current_dir()?),
+    };
+    // Пути в ferium.toml задаются относительно него, поэтому его путь должен быть абсолютным
+    let workspace = workspace
+        .map(|path| {
+            path.canonicalize()
+                .with_context(|| format!("Не удалось найти {}", path.display()))
+        })
+        .transpose()?;
+    if let Some(path) = &workspace {
+        config::workspace::check_command(&cli_app.subcommand, path)?;
+    }
+    let config_path = workspace
+        .clone()
+        .or(explicit_path)
+        .unwrap_or(DEFAULT_CONFIG_PATH.clone());
+    let mut config_file = if workspace.is_none() {
+        Some(libium::config::get_file(&config_path)?)
+    } else {
+        None
+    };
+    let contents = match &mut config_file {
+        Some(config_file) => libium::read_wrapper(config_file)?,
+        None => read_to_string(&config_path)?,
+    };
+    let (mut config, migration) = if workspace.is_some() {
+        config::workspace::deserialise(&contents, &config_path)?
+    } else {
+        config::deserialise(&contents)?
+    };
+    let original_config = serde_json::to_value(&config)?;
+    if let SubCommands::Config {
+        subcommand: ConfigSubCommands::Migrate { check: true },
+    } = cli_app.subcommand
+    {
+        // Ничего не записывается при проверке
+        return subcommands::config::migrate(&migration, true);
+    }
+    if migration.is_needed() {
+        let backup = config::migrate::backup(&config_path, &contents, migration.from)?;
+        eprintln!(
+            "{}",
+            format!(
+                "Конфигурация обновлена с версии {} до {}, резервная копия сохранена в {}",
+                migration.from,
+                config::migrate::CURRENT_VERSION,
+                backup.display()
+            )
+            .yellow()
+        );
+    }
+    api::configure(&config.api)?;
+    network::configure(&config.network, cli_app.network)?;
 
     let mut did_add_fail = false;
 
@@ -155,53 +240,28 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
         SubCommands::Complete { .. } | SubCommands::Profiles | SubCommands::Modpacks => {
             unreachable!();
         }
+        SubCommands::Doctor { fix } => {
+            let active = get_active_profile(&mut config).ok().cloned();
+            subcommands::doctor(&config, active.as_ref(), fix).await?;
+        }
+        SubCommands::Config { subcommand } => match subcommand {
+            ConfigSubCommands::Migrate { check } => {
+                subcommands::config::migrate(&migration, check)?
+            }
+        },
         SubCommands::Scan {
             platform,
             directory,
             force,
         } => {
             let profile = get_active_profile(&mut config)?;
-
-            let spinner = indicatif::ProgressBar::new_spinner().with_message("Чтение файлов");
-            spinner.enable_steady_tick(std::time::Duration::from_millis(100));
-
-            let ids = libium::scan(directory.as_ref().unwrap_or(&profile.output_dir), || {
-                spinner.set_message("Запрос к серверам");
-            })
-            .await?;
-
-            spinner.set_message("Добавление модов");
-
-            let mut send_ids = Vec::new();
-            for id in ids {
-                use libium::config::structs::ModIdentifier;
-                match id {
-                    (filename, None, None) => {
-                        println!("{} {}", "Неизвестный файл:".yellow(), filename.dimmed());
-                    }
-                    (_, Some(mr_id), None) => send_ids.push(ModIdentifier::ModrinthProject(mr_id)),
-                    (_, None, Some(cf_id)) => {
-                        send_ids.push(ModIdentifier::CurseForgeProject(cf_id));
-                    }
-                    (_, Some(mr_id), Some(cf_id)) => match platform {
-                        cli::Platform::Modrinth => {
-                            send_ids.push(ModIdentifier::ModrinthProject(mr_id));
-                        }
-                        cli::Platform::Curseforge => {
-                            send_ids.push(ModIdentifier::CurseForgeProject(cf_id));
-                        }
-                    },
-                }
-            }
-
-            let (successes, failures) =
-                libium::add(profile, send_ids, !force, false, vec![]).await?;
-            spinner.finish_and_clear();
-
-            did_add_fail = add::display_successes_failures(&successes, failures);
+            let directory = directory.unwrap_or_else(|| profile.output_dir.clone());
+            did_add_fail = scan::scan(profile, &directory, platform, force, None).await?;
         }
         SubCommands::Add {
-            identifiers,
+            identifiers: mut requested,
+            collection,
+            from_file,
             force,
             filters,
         } => {
@@ -209,11 +269,37 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
             let override_profile = filters.override_profile;
             let filters: Vec<_> = filters.into();
 
+            if let Some(path) = from_file {
+                requested.extend(add::read_list(&path)?);
+            }
+            let mut identifiers = Vec::new();
+            if let Some(collection) = collection {
+                identifiers.extend(add::collection(&collection).await?);
+            }
+            // Ссылки на страницы проектов превращаются в идентификаторы
+            let mut failures = Vec::new();
+            for id in requested {
+                match add::resolve_url(&id).await {
+                    Ok(ids) => identifiers.extend(ids),
+                    Err(err) => failures.push((id, err)),
+                }
+            }
+            if identifiers.is_empty() && failures.is_empty() {
+                bail!("Не найдено ни одного мода для добавления");
+            }
+
             if identifiers.len() > 1 && !filters.is_empty() {
-                bail!("Фильтры можно настраивать только при добавлении одного мода!")
+                bail!(
+                    "Фильтры можно настраивать только при добавлении одного мода!\n(Чтобы изменить фильтры уже добавленного мода, используйте 'ferium mod configure')"
+                )
             }
 
-            let (successes, failures) = libium::add(
+            // Идентификаторы других источников добавляются отдельно
+            let (external_ids, identifiers): (Vec<_>, Vec<_>) = identifiers
+                .into_iter()
+                .partition(|id| sources::is_source_id(id));
+
+            let (mut successes, platform_failures) = add::add(
                 profile,
                 identifiers
                     .into_iter()
@@ -223,21 +309,50 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 override_profile,
                 filters,
             )
-            .await?;
+            .await;
+            failures.extend(platform_failures);
+
+            let (external_successes, external_failures) =
+                sources::add(profile, external_ids, !force).await;
+            successes.extend(external_successes);
+            failures.extend(external_failures);
 
             did_add_fail = add::display_successes_failures(&successes, failures);
         }
-        SubCommands::List { verbose, markdown } => {
+        SubCommands::List {
+            verbose,
+            markdown,
+            matrix,
+            versions,
+            loaders,
+            csv,
+            groups,
+        } => {
+            let active = get_active_profile(&mut config)?.clone();
+            let inherited = config.inherited_mods(&active)?;
             let profile = get_active_profile(&mut config)?;
-            check_empty_profile(profile)?;
+            if inherited.is_empty() {
+                check_empty_profile(profile)?;
+            }
 
-            if verbose {
+            if matrix {
+                let format = if csv {
+                    subcommands::list::matrix::Format::Csv
+                } else if markdown {
+                    subcommands::list::matrix::Format::Markdown
+                } else {
+                    subcommands::list::matrix::Format::Table
+                };
+                let merged = config.merged(&active)?;
+                subcommands::list::matrix::matrix(&merged, versions, loaders, format).await?;
+            } else if verbose {
                 subcommands::list::verbose(profile, markdown).await?;
+                subcommands::list::inherited(&inherited, markdown);
             } else {
                 println!(
                     "{} {} на {} {}\n",
                     profile.name.bold(),
-                    format!("({} модов)", profile.mods.len()).yellow(),
+                    format!("({} модов)", profile.mod_count() + inherited.len()).yellow(),
                     profile
                         .filters
                         .mod_loader()
@@ -252,26 +367,42 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                         .display(", ")
                         .green(),
                 );
-                for mod_ in &profile.mods {
-                    println!(
-                        "{:20}  {}",
-                        match &mod_.identifier {
-                            ModIdentifier::CurseForgeProject(id) =>
-                                format!("{} {:8}", "CF".red(), id.to_string().dimmed()),
-                            ModIdentifier::ModrinthProject(id) =>
-                                format!("{} {:8}", "MR".green(), id.dimmed()),
-                            ModIdentifier::GitHubRepository(_) => "GH".purple().to_string(),
-                        },
-                        match &mod_.identifier {
-                            ModIdentifier::ModrinthProject(_)
-                            | ModIdentifier::CurseForgeProject(_) => mod_.name.bold().to_string(),
-                            ModIdentifier::GitHubRepository(id) =>
-                                format!("{}/{}", id.0.dimmed(), id.1.bold()),
-                        },
-                    );
+                if groups {
+                    list_groups(profile, &inherited);
+                } else {
+                    list_mods(profile, &inherited, |_| true);
                 }
             }
         }
+        SubCommands::Remove { mod_names } => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            subcommands::remove(profile, mod_names)?;
+        }
+        SubCommands::Disable { mod_names } => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            subcommands::disable(profile, mod_names)?;
+        }
+        SubCommands::Enable { mod_names } => {
+            let profile = get_active_profile(&mut config)?;
+            check_empty_profile(profile)?;
+            subcommands::enable(profile, mod_names)?;
+        }
+        SubCommands::Mod { subcommand } => {
+            let profile = get_active_profile(&mut config)?;
+            subcommands::mod_::subcommand(subcommand, profile).await?;
+        }
+        SubCommands::Old { subcommand } => {
+            let profile = get_active_profile(&mut config)?;
+            subcommands::old::subcommand(subcommand.unwrap_or(OldSubCommands::List), profile)?;
+        }
+        SubCommands::Group { subcommand } => {
+            let profile = get_active_profile(&mut config)?;
+            subcommands::group::subcommand(subcommand.unwrap_or(GroupSubCommands::List), profile)?;
+        }
+        SubCommands::Status => subcommands::status().await?,
+        SubCommands::Tui => subcommands::tui::tui(&mut config).await?,
         SubCommands::Modpack { subcommand } => {
             let mut default_flag = false;
             let subcommand = subcommand.unwrap_or_else(|| {
@@ -281,7 +412,7 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 ModpackSubCommands::List
             });
 
-            subcommands::modpacks::subcommand(subcommand, &mut config, &mut config_file).await?;
+            subcommands::modpack::subcommand(subcommand, &mut config).await?;
 
             if default_flag {
                 println!("\n(Чтобы увидеть список профилей, используйте 'ferium modpack list')");
@@ -294,28 +425,55 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
                 ProfileSubCommands::List
             });
 
-            subcommands::profile::subcommand(subcommand, &mut config, &mut config_file).await?;
+            subcommands::profile::subcommand(subcommand, &mut config).await?;
 
             if default_flag {
                 println!("\n(Чтобы увидеть список профилей, используйте 'ferium profile list')");
             }
         }
-        SubCommands::Download {
-            directory,
-            no_retries,
-            parallel,
-        } => {
-            download::download(
-                &get_active_profile(&mut config)?,
-                directory.as_deref(),
-                parallel,
-                !no_retries,
-            )
-            .await?;
+        SubCommands::Upgrade { all, profiles, yes } => {
+            // Без терминала спросить о посторонних файлах нельзя
+            let ask = !yes && stdin().is_terminal();
+            if all || !profiles.is_empty() {
+                let selected = if all {
+                    config
+                        .profiles
+                        .iter()
+                        .map(|profile| profile.name.clone())
+                        .collect_vec()
+                } else {
+                    profiles
+                        .iter()
+                        .map(|name| {
+                            config
+                                .find_profile(name)
+                                .map(|profile| profile.name.clone())
+                                .with_context(|| format!("Профиль {name} не существует"))
+                        })
+                        .collect::<Result<Vec<_>>>()?
+                };
+                subcommands::upgrade_many(&mut config, &selected, ask).await?;
+            } else {
+                let active = get_active_profile(&mut config)?.clone();
+                let profile = config.merged(&active)?;
+                check_empty_profile(&profile)?;
+
+                let adopted = subcommands::upgrade(&profile, ask).await?;
+                subcommands::update_local_hashes(&mut config, &active.name);
+                subcommands::adopt(&mut config, &active.name, &adopted).await?;
+            }
         }
     }
 
-    config::serialise(&config, &mut config_file)?;
+    match &mut config_file {
+        Some(config_file) => config::serialise(&config, config_file)?,
+        // ferium.toml перезаписывается только при изменениях, чтобы сохранить форматирование
+        None => {
+            if migration.is_needed() || config::workspace::has_changed(&config, &original_config)? {
+                config::workspace::serialise(&config, &config_path)?;
+            }
+        }
+    }
 
     ensure!(
         !did_add_fail,
@@ -326,18 +484,146 @@ async fn actual_main(mut cli_app: Ferium) -> Result<()> {
     Ok(())
 }
 
-#[expect(clippy::unwrap_used)]
-fn get_active_profile<'a>(config: &'a mut Config) -> Result<&'a mut Profile> {
-    Ok(config
-        .profiles
-        .iter_mut()
-        .find(|p| p.active)
-        .ok_or_else(|| anyhow!("Нет активного профиля!\n(Вы можете создать профиль с помощью 'ferium profile create')"))?)
+/// Возвращает профиль, выбранный с помощью `--profile`, или активный профиль
+///
+/// Активный профиль в конфигурации при этом не меняется
+fn get_active_profile(config: &mut Config) -> Result<&mut Profile> {
+    if let Some(name) = SELECTED_PROFILE.get() {
+        return config
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name.eq_ignore_ascii_case(name))
+            .with_context(|| format!("Профиль {name} не существует"));
+    }
+    config.profiles.get_mut(config.active_profile).ok_or_else(|| {
+        anyhow!("Нет активного профиля!\n(Вы можете создать профиль с помощью 'ferium profile create')")
+    })
+}
+
+/// Возвращает сборку, выбранную с помощью `--modpack`, или активную сборку
+///
+/// Активная сборка в конфигурации при этом не меняется
+fn get_active_modpack(config: &mut Config) -> Result<&mut Modpack> {
+    if let Some(name) = SELECTED_MODPACK.get() {
+        return config
+            .modpacks
+            .iter_mut()
+            .find(|modpack| modpack.name.eq_ignore_ascii_case(name))
+            .with_context(|| format!("Сборка {name} не существует"));
+    }
+    config.modpacks.get_mut(config.active_modpack).ok_or_else(|| {
+        anyhow!("Нет активной сборки!\n(Вы можете добавить сборку с помощью 'ferium modpack add')")
+    })
+}
+
+/// Выводит моды профиля и унаследованные моды, идентификаторы которых подходят под `show`
+fn list_mods(profile: &Profile, inherited: &Inherited, show: impl Fn(&str) -> bool) {
+    let mods = profile
+        .mods
+        .iter()
+        .map(|mod_| (mod_, None))
+        .chain(
+            inherited
+                .mods
+                .iter()
+                .map(|(origin, mod_)| (mod_, Some(origin))),
+        )
+        .filter(|(mod_, _)| show(&config::platform_id(&mod_.identifier)));
+    for (mod_, origin) in mods {
+        // Отключённые моды показываются тусклыми
+        if origin.is_none() && profile.is_mod_disabled(mod_) {
+            let id = config::platform_id(&mod_.identifier);
+            let tag = match &mod_.identifier {
+                ModIdentifier::CurseForgeProject(_) => format!("CF {id}"),
+                ModIdentifier::ModrinthProject(_) => format!("MR {id}"),
+                ModIdentifier::GitHubRepository(_) => "GH".to_owned(),
+            };
+            println!("{}", format!("{tag:11}  {} (disabled)", mod_.name).dimmed());
+            continue;
+        }
+        println!(
+            "{:20}  {}{}",
+            match &mod_.identifier {
+                ModIdentifier::CurseForgeProject(id) =>
+                    format!("{} {:8}", "CF".red(), id.to_string().dimmed()),
+                ModIdentifier::ModrinthProject(id) => format!("{} {:8}", "MR".green(), id.dimmed()),
+                ModIdentifier::GitHubRepository(_) => "GH".purple().to_string(),
+            },
+            match &mod_.identifier {
+                ModIdentifier::ModrinthProject(_) | ModIdentifier::CurseForgeProject(_) =>
+                    mod_.name.bold().to_string(),
+                ModIdentifier::GitHubRepository(id) => format!("{}/{}", id.0.dimmed(), id.1.bold()),
+            },
+            inherited_from(origin),
+        );
+    }
+    let external_mods = profile
+        .external_mods
+        .iter()
+        .map(|mod_| (mod_, None))
+        .chain(
+            inherited
+                .external_mods
+                .iter()
+                .map(|(origin, mod_)| (mod_, Some(origin))),
+        )
+        .filter(|(mod_, _)| show(&mod_.identifier.display_id()));
+    for (mod_, origin) in external_mods {
+        let (namespace, name) = mod_.identifier.namespace_and_name();
+        if origin.is_none() && profile.is_external_mod_disabled(mod_) {
+            let tag = mod_.identifier.tag();
+            println!(
+                "{}",
+                format!("{tag:11}  {namespace}/{name} (disabled)").dimmed()
+            );
+            continue;
+        }
+        println!(
+            "{:20}  {}/{}{}",
+            match mod_.identifier {
+                sources::SourceIdentifier::GitLabProject { .. } => mod_.identifier.tag().yellow(),
+                sources::SourceIdentifier::ForgejoRepository { .. } => mod_.identifier.tag().blue(),
+                sources::SourceIdentifier::MavenArtifact { .. } => mod_.identifier.tag().cyan(),
+                sources::SourceIdentifier::DirectUrl { .. } => mod_.identifier.tag().white(),
+                sources::SourceIdentifier::LocalFile { .. } =>
+                    mod_.identifier.tag().bright_yellow(),
+            }
+            .to_string(),
+            namespace.dimmed(),
+            name.bold(),
+            inherited_from(origin),
+        );
+    }
+}
+
+/// Выводит моды профиля, разделённые по группам
+fn list_groups(profile: &Profile, inherited: &Inherited) {
+    println!("{}", "Ungrouped".bold());
+    list_mods(profile, inherited, |id| profile.group_of(id).is_none());
+    for group in &profile.groups {
+        println!(
+            "\n{} {}",
+            group.name.bold(),
+            if group.enabled {
+                "(enabled)".green()
+            } else {
+                "(disabled)".red()
+            }
+        );
+        list_mods(profile, inherited, |id| group.contains(id));
+    }
+}
+
+/// Подпись для модов, унаследованных от другого профиля
+fn inherited_from(origin: Option<&String>) -> String {
+    origin.map_or(String::new(), |origin| {
+        format!("  {}", format!("(from {origin})").dimmed())
+    })
 }
 
 fn check_empty_profile(profile: &Profile) -> Result<()> {
     ensure!(
-        !profile.mods.is_empty(),
+        profile.mod_count() != 0,
         "{}",
         "Профиль не содержит модов.".yellow()
     );
@@ -0,0 +1,158 @@
+//! Proxy, certificate, timeout, user agent, and retry settings for every HTTP client ferium creates
+//!
+//! These are read from the `network` section of the config, and can be overridden with global flags.
+
+use crate::{cli::NetworkArguments, DOWNLOAD_RETRIES};
+use anyhow::{Context as _, Result};
+use reqwest::{Certificate, Client, ClientBuilder, Proxy};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::read,
+    path::PathBuf,
+    sync::{LazyLock, OnceLock},
+    time::Duration,
+};
+
+#[derive(Deserialize, Serialize, Debug, Clone, Default, PartialEq, Eq)]
+pub struct NetworkSettings {
+    /// An HTTP, HTTPS, or SOCKS5 proxy to send all requests through
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub proxy: Option<String>,
+
+    /// PEM files of root certificates to trust in addition to the built in ones
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[serde(default)]
+    pub ca_certificates: Vec<PathBuf>,
+
+    /// How long to wait for a connection to be established, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub connect_timeout: Option<u64>,
+
+    /// How long to wait for a response to send more data, in seconds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub read_timeout: Option<u64>,
+
+    /// Appended to ferium's user agent, e.g. to identify your organisation to a mirror
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub user_agent_suffix: Option<String>,
+
+    /// How many times to retry a failed download before giving up on the file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default)]
+    pub retries: Option<u32>,
+}
+
+impl NetworkSettings {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// The settings after applying the flags, and the certificates they point to
+struct Resolved {
+    settings: NetworkSettings,
+    certificates: Vec<Certificate>,
+}
+
+static RESOLVED: OnceLock<Resolved> = OnceLock::new();
+
+/// Use the settings in `config`, overridden by the ones provided in `arguments`
+pub fn configure(config: &NetworkSettings, arguments: NetworkArguments) -> Result<()> {
+    let mut settings = config.clone();
+    if arguments.proxy.is_some() {
+        settings.proxy = arguments.proxy;
+    }
+    settings.ca_certificates.extend(arguments.ca_certificates);
+    if arguments.connect_timeout.is_some() {
+        settings.connect_timeout = arguments.connect_timeout;
+    }
+    if arguments.read_timeout.is_some() {
+        settings.read_timeout = arguments.read_timeout;
+    }
+    if arguments.user_agent_suffix.is_some() {
+        settings.user_agent_suffix = arguments.user_agent_suffix;
+    }
+    if arguments.retries.is_some() {
+        settings.retries = arguments.retries;
+    }
+    if let Some(retries) = settings.retries {
+        let _ = DOWNLOAD_RETRIES.set(retries);
+    }
+
+    if let Some(proxy) = &settings.proxy {
+        Proxy::all(proxy).with_context(|| format!("Invalid proxy `{proxy}`"))?;
+    }
+    let mut certificates = Vec::new();
+    for path in &settings.ca_certificates {
+        let pem = read(path)
+            .with_context(|| format!("Could not read CA certificates from {}", path.display()))?;
+        certificates.extend(
+            Certificate::from_pem_bundle(&pem)
+                .with_context(|| format!("Invalid CA certificates in {}", path.display()))?,
+        );
+    }
+
+    let _ = RESOLVED.set(Resolved {
+        settings,
+        certificates,
+    });
+    Ok(())
+}
+
+fn resolved() -> &'static Resolved {
+    RESOLVED.get_or_init(|| Resolved {
+        settings: NetworkSettings::default(),
+        certificates: Vec::new(),
+    })
+}
+
+/// Ferium's user agent, followed by the configured suffix
+pub fn user_agent() -> String {
+    let user_agent = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+    match &resolved().settings.user_agent_suffix {
+        Some(suffix) => format!("{user_agent} {suffix}"),
+        None => user_agent.to_owned(),
+    }
+}
+
+pub fn connect_timeout() -> Option<Duration> {
+    resolved().settings.connect_timeout.map(Duration::from_secs)
+}
+
+pub fn read_timeout() -> Option<Duration> {
+    resolved().settings.read_timeout.map(Duration::from_secs)
+}
+
+/// A client builder with all the network settings applied
+fn client_builder() -> Result<ClientBuilder> {
+    let resolved = resolved();
+    let mut builder = Client::builder().user_agent(user_agent());
+    if let Some(proxy) = &resolved.settings.proxy {
+        builder = builder.proxy(Proxy::all(proxy)?);
+    }
+    for certificate in &resolved.certificates {
+        builder = builder.add_root_certificate(certificate.clone());
+    }
+    if let Some(timeout) = connect_timeout() {
+        builder = builder.connect_timeout(timeout);
+    }
+    if let Some(timeout) = read_timeout() {
+        builder = builder.read_timeout(timeout);
+    }
+    Ok(builder)
+}
+
+/// The client shared by all of ferium's own requests
+#[expect(
+    clippy::expect_used,
+    reason = "The settings were validated when configuring"
+)]
+pub static CLIENT: LazyLock<Client> = LazyLock::new(|| {
+    client_builder()
+        .and_then(|builder| Ok(builder.build()?))
+        .expect("Could not build HTTP client")
+});
@@ -0,0 +1,171 @@
+//! Waiting for the platforms' rate limits to reset instead of failing
+//!
+//! When a platform reports that its quota has run out, requests to it are paused
+//! until the quota resets, while requests to other platforms continue.
+
+#![expect(clippy::expect_used, reason = "For mutex poisons")]
+
+use crate::download::report;
+use colored::Colorize as _;
+use indicatif::ProgressBar;
+use reqwest::{
+    header::{HeaderMap, RETRY_AFTER},
+    StatusCode,
+};
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::sleep;
+
+/// The longest ferium will wait for a single rate limit to reset
+pub const MAX_WAIT: Duration = Duration::from_secs(60 * 60);
+/// How long to wait when a platform does not say when its rate limit resets
+pub const DEFAULT_WAIT: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Platform {
+    Modrinth,
+    CurseForge,
+    GitHub,
+    GitLab,
+    Forgejo,
+}
+
+impl Display for Platform {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Modrinth => write!(f, "Modrinth"),
+            Self::CurseForge => write!(f, "CurseForge"),
+            Self::GitHub => write!(f, "GitHub"),
+            Self::GitLab => write!(f, "GitLab"),
+            Self::Forgejo => write!(f, "Forgejo"),
+        }
+    }
+}
+
+/// When each rate limited platform can be queried again
+static PAUSED_UNTIL: LazyLock<Mutex<HashMap<Platform, Instant>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Stop sending requests to `platform` for `duration`
+pub fn pause(platform: Platform, duration: Duration) {
+    let until = Instant::now() + duration.min(MAX_WAIT);
+    let mut paused = PAUSED_UNTIL.lock().expect("Mutex poisoned");
+    let entry = paused.entry(platform).or_insert(until);
+    *entry = (*entry).max(until);
+}
+
+/// Wait until `platform` can be queried again
+///
+/// The countdown is shown as the message of `progress_bar`, or printed once if there is none.
+pub async fn until_ready(platform: Platform, progress_bar: Option<&Mutex<ProgressBar>>) {
+    let mut waited = false;
+    loop {
+        let remaining = PAUSED_UNTIL
+            .lock()
+            .expect("Mutex poisoned")
+            .get(&platform)
+            .and_then(|until| until.checked_duration_since(Instant::now()))
+            .filter(|remaining| !remaining.is_zero());
+        let Some(remaining) = remaining else {
+            break;
+        };
+
+        let message = format!(
+            "Rate limited by {platform}, resuming in {}s",
+            remaining.as_secs() + 1
+        );
+        match progress_bar.map(|progress_bar| progress_bar.lock().expect("Mutex poisoned")) {
+            // The progress bar is hidden when `ferium tui` shows the progress
+            Some(progress_bar) if !progress_bar.is_hidden() => {
+                progress_bar.set_message(message.yellow().to_string());
+            }
+            _ if !waited => report(message.yellow()),
+            _ => (),
+        }
+        waited = true;
+        sleep(remaining.min(Duration::from_secs(1))).await;
+    }
+
+    if waited {
+        if let Some(progress_bar) = progress_bar {
+            progress_bar.lock().expect("Mutex poisoned").set_message("");
+        }
+    }
+}
+
+/// A platform's request quota, as reported by its rate limit headers
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Quota {
+    pub limit: Option<u64>,
+    pub remaining: Option<u64>,
+    pub reset: Option<Duration>,
+}
+
+impl Quota {
+    /// Read the `X-RateLimit-*` or `RateLimit-*` headers
+    pub fn from_headers(headers: &HeaderMap) -> Self {
+        let get = |name: &str| {
+            ["x-ratelimit-", "ratelimit-"].iter().find_map(|prefix| {
+                headers
+                    .get(format!("{prefix}{name}"))?
+                    .to_str()
+                    .ok()?
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            })
+        };
+        Self {
+            limit: get("limit"),
+            remaining: get("remaining"),
+            reset: get("reset").map(reset_duration),
+        }
+    }
+
+    /// Whether the platform reported anything about its rate limit
+    pub const fn is_reported(&self) -> bool {
+        self.limit.is_some() || self.remaining.is_some()
+    }
+}
+
+/// Pause `platform` if `quota` has run out
+pub fn observe(platform: Platform, quota: &Quota) {
+    if quota.remaining == Some(0) {
+        pause(platform, quota.reset.unwrap_or(DEFAULT_WAIT));
+    }
+}
+
+/// How long to wait before trying again, if a response with `status` and `headers` says that
+/// the platform's rate limit was exceeded
+///
+/// Besides `429 Too Many Requests`, GitHub responds with `403 Forbidden` when its quota has run out
+/// or a secondary rate limit was hit. The wait is taken from `Retry-After`, then the quota's reset.
+pub fn exceeded(status: StatusCode, headers: &HeaderMap, quota: &Quota) -> Option<Duration> {
+    let retry_after = headers
+        .get(RETRY_AFTER)
+        .and_then(|value| value.to_str().ok()?.trim().parse().ok())
+        .map(Duration::from_secs);
+    let limited = status == StatusCode::TOO_MANY_REQUESTS
+        || (status == StatusCode::FORBIDDEN
+            && (quota.remaining == Some(0) || retry_after.is_some()));
+    limited.then(|| retry_after.or(quota.reset).unwrap_or(DEFAULT_WAIT))
+}
+
+/// Convert a rate limit reset header, which is either the number of seconds until the reset
+/// or the Unix time of the reset, into the time left until the reset
+pub fn reset_duration(reset: u64) -> Duration {
+    // Nothing resets decades from now, so this has to be a timestamp
+    if reset > 1_000_000_000 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        Duration::from_secs(reset.saturating_sub(now))
+    } else {
+        Duration::from_secs(reset)
+    }
+}
@@ -0,0 +1,522 @@
+//! Опознание jar файлов, которые не удалось найти на Modrinth и CurseForge по хешу
+//!
+//! Такие файлы часто являются немного другими сборками известных модов или модами, которые есть только на GitHub.
+//! Их метаданные читаются из самого jar файла, и по ним ищутся подходящие проекты.
+
+use crate::{
+    add, api,
+    cli::Platform,
+    config::Profile,
+    sources::{self, hash_file, local},
+};
+use anyhow::{ensure, Context as _, Result};
+use colored::Colorize as _;
+use furse::structures::mod_structs::Mod;
+use indicatif::ProgressBar;
+use inquire::Select;
+use libium::{
+    config::{filters::ProfileParameters as _, structs::ModIdentifier},
+    iter_ext::IterExt as _,
+    HOME,
+};
+use reqwest::Url;
+use serde::Deserialize;
+use sha2::{Digest as _, Sha512};
+use std::{
+    cmp::Reverse,
+    collections::HashMap,
+    fs::{copy, create_dir_all, read, read_dir, File},
+    io::{read_to_string, stdin, IsTerminal as _},
+    path::{Path, PathBuf},
+    time::Duration,
+};
+use zip::ZipArchive;
+
+/// Сколько совпадений с каждой платформы предлагать
+const MAX_MATCHES: usize = 3;
+
+/// Добавляет в `profile` моды из файлов в `directory`, или только из файлов `only`, если они указаны
+///
+/// Файлы ищутся на Modrinth и CurseForge по хешу, если они есть на обеих, используется `platform`.
+/// Остальные файлы опознаются по их метаданным с помощью [`identify`].
+/// Возвращает, не удалось ли добавить какие-то моды.
+pub async fn scan(
+    profile: &mut Profile,
+    directory: &Path,
+    platform: Platform,
+    force: bool,
+    only: Option<&[String]>,
+) -> Result<bool> {
+    let spinner = ProgressBar::new_spinner().with_message("Чтение файлов");
+    spinner.enable_steady_tick(Duration::from_millis(100));
+
+    let ids = find_by_hash(directory, || {
+        spinner.set_message("Запрос к серверам");
+    })
+    .await?;
+
+    spinner.set_message("Добавление модов");
+
+    let mut send_ids = Vec::new();
+    let mut unknown = Vec::new();
+    for id in ids {
+        if only.is_some_and(|only| !only.contains(&id.0)) {
+            continue;
+        }
+        match id {
+            (filename, None, None) => unknown.push(filename),
+            (_, Some(mr_id), None) => send_ids.push(ModIdentifier::ModrinthProject(mr_id)),
+            (_, None, Some(cf_id)) => {
+                send_ids.push(ModIdentifier::CurseForgeProject(cf_id));
+            }
+            (_, Some(mr_id), Some(cf_id)) => match platform {
+                Platform::Modrinth => {
+                    send_ids.push(ModIdentifier::ModrinthProject(mr_id));
+                }
+                Platform::Curseforge => {
+                    send_ids.push(ModIdentifier::CurseForgeProject(cf_id));
+                }
+            },
+        }
+    }
+
+    let (mut successes, mut failures) = add::add(profile, send_ids, !force, false, vec![]).await;
+    spinner.finish_and_clear();
+
+    // Файлы, которые не нашлись по хешу, опознаются по их метаданным
+    let (identified, local) = identify(directory, &unknown, profile).await;
+    if !identified.is_empty() {
+        let (identified_successes, identified_failures) =
+            add::add(profile, identified, !force, false, vec![]).await;
+        successes.extend(identified_successes);
+        failures.extend(identified_failures);
+    }
+    let (local_successes, local_failures) = sources::add(profile, local, !force).await;
+    successes.extend(local_successes);
+    failures.extend(local_failures);
+
+    Ok(add::display_successes_failures(&successes, failures))
+}
+
+/// Ищет jar файлы в `directory` на Modrinth и CurseForge по их хешам, вызывая `on_query` перед запросами
+///
+/// Возвращает имя каждого файла вместе с ID проекта на Modrinth и ID мода на CurseForge, если они нашлись.
+async fn find_by_hash(
+    directory: &Path,
+    on_query: impl FnOnce(),
+) -> Result<Vec<(String, Option<String>, Option<i32>)>> {
+    let mut files = Vec::new();
+    for entry in read_dir(directory)? {
+        let path = entry?.path();
+        if !local::is_jar_file(&path) {
+            continue;
+        }
+        let Some(filename) = path.file_name() else {
+            continue;
+        };
+        let bytes = read(&path)?;
+        files.push((
+            filename.to_string_lossy().into_owned(),
+            format!("{:x}", Sha512::digest(&bytes)),
+            curseforge_fingerprint(&bytes),
+        ));
+    }
+
+    on_query();
+    let hashes = files.iter().map(|file| file.1.clone()).collect_vec();
+    let fingerprints = files.iter().map(|file| file.2).collect_vec();
+    let (versions, matches) = futures::join!(
+        api::versions_from_hashes(&hashes),
+        api::get_fingerprint_matches(&fingerprints)
+    );
+    let (versions, matches) = (versions?, matches?);
+
+    Ok(files
+        .into_iter()
+        .map(|(filename, hash, fingerprint)| {
+            let modrinth = versions
+                .get(&hash)
+                .map(|version| version.project_id.clone());
+            let curseforge = matches
+                .iter()
+                .find(|(matched, _)| *matched == fingerprint)
+                .map(|(_, id)| *id);
+            (filename, modrinth, curseforge)
+        })
+        .collect())
+}
+
+/// Отпечаток, по которому CurseForge ищет файлы: MurmurHash2 с зерном 1 от содержимого без пробельных символов
+#[expect(
+    clippy::cast_possible_truncation,
+    reason = "CurseForge also uses the length modulo 2³²"
+)]
+fn curseforge_fingerprint(bytes: &[u8]) -> u32 {
+    const M: u32 = 0x5bd1_e995;
+    let bytes = bytes
+        .iter()
+        .copied()
+        .filter(|byte| !matches!(byte, 9 | 10 | 13 | 32))
+        .collect_vec();
+
+    let mut hash = 1 ^ bytes.len() as u32;
+    let mut chunks = bytes.chunks_exact(4);
+    for chunk in &mut chunks {
+        let mut k = u32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]);
+        k = k.wrapping_mul(M);
+        k ^= k >> 24;
+        k = k.wrapping_mul(M);
+        hash = hash.wrapping_mul(M) ^ k;
+    }
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        for (i, byte) in rest.iter().enumerate() {
+            hash ^= u32::from(*byte) << (8 * i);
+        }
+        hash = hash.wrapping_mul(M);
+    }
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(M);
+    hash ^ (hash >> 15)
+}
+
+/// Метаданные мода, прочитанные из его jar файла
+#[derive(Debug, Clone)]
+pub struct JarMetadata {
+    pub id: String,
+    pub name: Option<String>,
+    pub version: Option<String>,
+    /// Репозиторий GitHub с исходным кодом мода, если он указан
+    pub github: Option<(String, String)>,
+}
+
+impl JarMetadata {
+    /// Название мода, или его ID, если название не указано
+    pub fn name(&self) -> &str {
+        self.name.as_deref().unwrap_or(&self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct FabricMod {
+    id: String,
+    name: Option<String>,
+    version: Option<String>,
+    #[serde(default)]
+    contact: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct QuiltMod {
+    quilt_loader: QuiltLoader,
+}
+
+#[derive(Deserialize)]
+struct QuiltLoader {
+    id: String,
+    version: Option<String>,
+    #[serde(default)]
+    metadata: QuiltMetadata,
+}
+
+#[derive(Deserialize, Default)]
+struct QuiltMetadata {
+    name: Option<String>,
+    #[serde(default)]
+    contact: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct ModsToml {
+    #[serde(rename = "issueTrackerURL")]
+    issue_tracker_url: Option<String>,
+    #[serde(default)]
+    mods: Vec<TomlMod>,
+}
+
+#[derive(Deserialize)]
+struct TomlMod {
+    #[serde(rename = "modId")]
+    mod_id: String,
+    #[serde(rename = "displayName")]
+    display_name: Option<String>,
+    version: Option<String>,
+    #[serde(rename = "displayURL")]
+    display_url: Option<String>,
+}
+
+/// Читает метаданные мода из jar файла по пути `path`
+///
+/// Поддерживаются `fabric.mod.json`, `quilt.mod.json`, `META-INF/mods.toml` и `META-INF/neoforge.mods.toml`.
+/// Возвращает `None`, если ни одного из них в файле нет.
+pub fn read_metadata(path: &Path) -> Result<Option<JarMetadata>> {
+    let mut archive = ZipArchive::new(File::open(path)?)
+        .with_context(|| format!("{} не является jar файлом", path.display()))?;
+    let mut read = |name: &str| archive.by_name(name).ok().map(read_to_string).transpose();
+
+    if let Some(json) = read("fabric.mod.json")? {
+        let fabric: FabricMod = serde_json::from_str(&json).with_context(|| {
+            format!("Не удалось прочитать fabric.mod.json из {}", path.display())
+        })?;
+        return Ok(Some(JarMetadata {
+            github: github_repo(fabric.contact.values()),
+            id: fabric.id,
+            name: fabric.name,
+            version: fabric.version,
+        }));
+    }
+    if let Some(json) = read("quilt.mod.json")? {
+        let quilt: QuiltMod = serde_json::from_str(&json).with_context(|| {
+            format!("Не удалось прочитать quilt.mod.json из {}", path.display())
+        })?;
+        let loader = quilt.quilt_loader;
+        return Ok(Some(JarMetadata {
+            github: github_repo(loader.metadata.contact.values()),
+            id: loader.id,
+            name: loader.metadata.name,
+            version: loader.version,
+        }));
+    }
+    for name in ["META-INF/neoforge.mods.toml", "META-INF/mods.toml"] {
+        if let Some(toml) = read(name)? {
+            let mods_toml: ModsToml = toml::from_str(&toml)
+                .with_context(|| format!("Не удалось прочитать {name} из {}", path.display()))?;
+            let Some(mod_) = mods_toml.mods.into_iter().next() else {
+                continue;
+            };
+            return Ok(Some(JarMetadata {
+                github: github_repo(mod_.display_url.iter().chain(&mods_toml.issue_tracker_url)),
+                id: mod_.mod_id,
+                name: mod_.display_name,
+                // Версия обычно подставляется при сборке, например `${file.jarVersion}`
+                version: mod_.version.filter(|version| !version.starts_with('$')),
+            }));
+        }
+    }
+    Ok(None)
+}
+
+/// Первый репозиторий GitHub среди ссылок `urls`
+fn github_repo<'a>(urls: impl IntoIterator<Item = &'a String>) -> Option<(String, String)> {
+    urls.into_iter().find_map(|url| {
+        let url = Url::parse(url).ok()?;
+        if url.host_str()?.trim_start_matches("www.") != "github.com" {
+            return None;
+        }
+        let mut segments = url.path_segments()?.filter(|s| !s.is_empty());
+        let owner = segments.next()?;
+        let repo = segments.next()?.trim_end_matches(".git");
+        Some((owner.to_owned(), repo.to_owned()))
+    })
+}
+
+/// Проект, которому может принадлежать неизвестный файл
+enum Candidate {
+    Modrinth(api::SearchHit),
+    CurseForge(Mod),
+    GitHub(String, String),
+}
+
+impl Candidate {
+    fn identifier(&self) -> ModIdentifier {
+        match self {
+            Self::Modrinth(hit) => ModIdentifier::ModrinthProject(hit.project_id.clone()),
+            Self::CurseForge(mod_) => ModIdentifier::CurseForgeProject(mod_.id),
+            Self::GitHub(owner, repo) => {
+                ModIdentifier::GitHubRepository((owner.clone(), repo.clone()))
+            }
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self {
+            Self::Modrinth(hit) => {
+                format!("Modrinth:   {} ({}) от {}", hit.title, hit.slug, hit.author)
+            }
+            Self::CurseForge(mod_) => format!("CurseForge: {} ({})", mod_.name, mod_.slug),
+            Self::GitHub(owner, repo) => format!("GitHub:     {owner}/{repo}"),
+        }
+    }
+}
+
+/// Насколько проект с `slug` и `title` похож на мод с `metadata`, 0 если не похож вообще
+fn similarity(metadata: &JarMetadata, slug: &str, title: &str) -> u8 {
+    let id = metadata.id.replace('_', "-");
+    if slug.eq_ignore_ascii_case(&id) || title.eq_ignore_ascii_case(metadata.name()) {
+        2
+    } else if slug.to_lowercase().contains(&id.to_lowercase())
+        || title
+            .to_lowercase()
+            .contains(&metadata.name().to_lowercase())
+    {
+        1
+    } else {
+        0
+    }
+}
+
+/// Ищет на платформах проекты, которым может принадлежать мод с `metadata`, лучшие совпадения первыми
+///
+/// Ошибки поиска на Modrinth пропускаются, а о пропуске CurseForge выводится предупреждение.
+async fn find_candidates(metadata: &JarMetadata, profile: &Profile) -> Vec<Candidate> {
+    let loader = profile.filters.mod_loader().map(ToString::to_string);
+    let mut candidates = Vec::new();
+
+    let mut queries = vec![metadata.name()];
+    if metadata.name() != metadata.id {
+        queries.push(&metadata.id);
+    }
+    let mut hits: Vec<api::SearchHit> = Vec::new();
+    for query in queries {
+        for hit in api::search_mods(query, &[], loader.as_deref())
+            .await
+            .unwrap_or_default()
+        {
+            if !hits.iter().any(|h| h.project_id == hit.project_id) {
+                hits.push(hit);
+            }
+        }
+    }
+    hits.retain(|hit| similarity(metadata, &hit.slug, &hit.title) > 0);
+    hits.sort_by_key(|hit| Reverse(similarity(metadata, &hit.slug, &hit.title)));
+    candidates.extend(hits.into_iter().take(MAX_MATCHES).map(Candidate::Modrinth));
+
+    let mut mods = match api::search_curseforge(metadata.name()).await {
+        Ok(mods) => mods,
+        Err(err) => {
+            println!(
+                "{}",
+                format!(
+                    "CurseForge пропущен при поиске {}: {err:#}",
+                    metadata.name()
+                )
+                .yellow()
+            );
+            Vec::new()
+        }
+    };
+    mods.retain(|mod_| similarity(metadata, &mod_.slug, &mod_.name) > 0);
+    mods.sort_by_key(|mod_| Reverse(similarity(metadata, &mod_.slug, &mod_.name)));
+    candidates.extend(
+        mods.into_iter()
+            .take(MAX_MATCHES)
+            .map(Candidate::CurseForge),
+    );
+
+    if let Some((owner, repo)) = &metadata.github {
+        if let Ok(repository) = api::get_repository(owner, repo).await {
+            candidates.push(Candidate::GitHub(
+                repository
+                    .owner
+                    .map_or_else(|| owner.clone(), |owner| owner.login),
+                repository.name,
+            ));
+        }
+    }
+    candidates
+}
+
+/// Пытается опознать неизвестные файлы `filenames` в `directory` по их метаданным
+///
+/// Если ferium запущен в терминале, для каждого файла предлагаются найденные проекты
+/// или добавление копии файла как локального мода.
+/// Возвращает идентификаторы выбранных проектов и пути файлов, которые нужно добавить как локальные моды.
+pub async fn identify(
+    directory: &Path,
+    filenames: &[String],
+    profile: &Profile,
+) -> (Vec<ModIdentifier>, Vec<String>) {
+    let interactive = stdin().is_terminal();
+    let mut identifiers = Vec::new();
+    let mut local = Vec::new();
+
+    for filename in filenames {
+        println!("{} {}", "Неизвестный файл:".yellow(), filename.dimmed());
+        let path = directory.join(filename);
+        let metadata = match read_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                println!("  {}", err.to_string().red());
+                None
+            }
+        };
+        match &metadata {
+            Some(metadata) => println!(
+                "  Похоже на {} {}(ID {})",
+                metadata.name().bold(),
+                metadata
+                    .version
+                    .as_ref()
+                    .map(|version| format!("{version} "))
+                    .unwrap_or_default(),
+                metadata.id
+            ),
+            None => println!("  {}", "Метаданные мода не найдены".dimmed()),
+        }
+        if !interactive {
+            continue;
+        }
+
+        let candidates = match &metadata {
+            Some(metadata) => find_candidates(metadata, profile).await,
+            None => Vec::new(),
+        };
+        if candidates.is_empty() && metadata.is_some() {
+            println!("  {}", "Подходящих проектов не найдено".dimmed());
+        }
+        let options = candidates
+            .iter()
+            .map(Candidate::describe)
+            .chain([
+                "Добавить как локальный файл".to_owned(),
+                "Пропустить".to_owned(),
+            ])
+            .collect_vec();
+        let Ok(selection) = Select::new("Что сделать с этим файлом?", options)
+            .without_filtering()
+            .raw_prompt()
+        else {
+            continue;
+        };
+        match candidates.get(selection.index) {
+            Some(candidate) => identifiers.push(candidate.identifier()),
+            None if selection.index == candidates.len() => match keep_local_copy(&path) {
+                Ok(kept) => {
+                    println!(
+                        "  Файл скопирован в {}",
+                        kept.display().to_string().dimmed()
+                    );
+                    local.push(kept.to_string_lossy().into_owned());
+                }
+                Err(err) => println!("  {}", err.to_string().red()),
+            },
+            None => {}
+        }
+        println!();
+    }
+    (identifiers, local)
+}
+
+/// Копирует `path` в папку локальных модов ferium и возвращает путь копии
+///
+/// Локальный мод должен лежать вне папки вывода, иначе при обновлении его источник
+/// совпадает с установленным файлом. Другой файл с тем же именем не перезаписывается.
+fn keep_local_copy(path: &Path) -> Result<PathBuf> {
+    let directory = HOME.join(".config").join("ferium").join("local");
+    create_dir_all(&directory)?;
+    let filename = path
+        .file_name()
+        .with_context(|| format!("У {} нет имени файла", path.display()))?;
+    let kept = directory.join(filename);
+    if kept.exists() {
+        ensure!(
+            hash_file(&kept)? == hash_file(path)?,
+            "В {} уже есть другой файл с именем {}",
+            directory.display(),
+            filename.to_string_lossy()
+        );
+    } else {
+        copy(path, &kept)?;
+    }
+    Ok(kept)
+}
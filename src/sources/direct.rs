@@ -0,0 +1,57 @@
+//! Files at a fixed URL, verified using their SHA-256 hash
+
+use super::{content_length, hash_file, ExternalMod, SourceIdentifier, CLIENT};
+use anyhow::{Context as _, Result};
+use libium::upgrade::DownloadData;
+use reqwest::Url;
+use sha2::{Digest as _, Sha256};
+use std::{fs::remove_file, path::Path};
+
+/// The name of the file the URL points to
+pub fn filename(url: &Url) -> Result<String> {
+    url.path_segments()
+        .and_then(Iterator::last)
+        .filter(|segment| !segment.is_empty())
+        .map(ToOwned::to_owned)
+        .context("The URL does not point to a file")
+}
+
+/// Download the file at `url` and calculate its SHA-256 hash
+pub async fn fetch_hash(url: &Url) -> Result<String> {
+    let bytes = CLIENT
+        .get(url.clone())
+        .send()
+        .await?
+        .error_for_status()?
+        .bytes()
+        .await?;
+    Ok(format!("{:x}", Sha256::digest(&bytes)))
+}
+
+pub async fn fetch_download_file(url: &str) -> Result<DownloadData> {
+    let url = Url::parse(url)?;
+    Ok(DownloadData {
+        output: filename(&url)?.into(),
+        length: content_length(&url).await.unwrap_or_default(),
+        download_url: url,
+        dependencies: Vec::new(),
+        conflicts: Vec::new(),
+    })
+}
+
+/// Check the hashes of the downloaded direct URL mods in `external_mods`
+///
+/// Files that do not match their expected hash are deleted, and their names are returned.
+pub fn verify_hashes(external_mods: &[ExternalMod], output_dir: &Path) -> Result<Vec<String>> {
+    let mut mismatched = Vec::new();
+    for mod_ in external_mods {
+        if let SourceIdentifier::DirectUrl { url, sha256 } = &mod_.identifier {
+            let path = output_dir.join(filename(&Url::parse(url)?)?);
+            if path.is_file() && !hash_file(&path)?.eq_ignore_ascii_case(sha256) {
+                remove_file(&path)?;
+                mismatched.push(mod_.name.clone());
+            }
+        }
+    }
+    Ok(mismatched)
+}
@@ -0,0 +1,84 @@
+//! Releases of repositories on Codeberg or another Forgejo or Gitea instance
+
+use super::{get_json, Asset};
+use crate::rate_limit::Platform;
+use anyhow::Result;
+use reqwest::Url;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Repository {
+    pub name: String,
+    pub full_name: String,
+    #[serde(default)]
+    pub description: String,
+    pub html_url: String,
+    pub stars_count: usize,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub owner: User,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct User {
+    pub login: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub name: String,
+    pub tag_name: String,
+    pub draft: bool,
+    pub prerelease: bool,
+    pub assets: Vec<Attachment>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Attachment {
+    pub name: String,
+    pub size: usize,
+    #[serde(default)]
+    pub download_count: usize,
+    pub browser_download_url: Url,
+}
+
+fn repo_url(instance: &str, owner: &str, repo: &str) -> String {
+    format!("{instance}/api/v1/repos/{owner}/{repo}")
+}
+
+pub async fn get_repository(instance: &str, owner: &str, repo: &str) -> Result<Repository> {
+    get_json(&repo_url(instance, owner, repo), Platform::Forgejo).await
+}
+
+/// List the releases of the repository, newest first
+pub async fn list_releases(instance: &str, owner: &str, repo: &str) -> Result<Vec<Release>> {
+    get_json(
+        &format!("{}/releases", repo_url(instance, owner, repo)),
+        Platform::Forgejo,
+    )
+    .await
+}
+
+/// List the files attached to the repository's releases, newest first
+pub async fn list_assets(instance: &str, owner: &str, repo: &str) -> Result<Vec<Asset>> {
+    Ok(list_releases(instance, owner, repo)
+        .await?
+        .into_iter()
+        .filter(|release| !release.draft)
+        .flat_map(|release| {
+            let release_title = if release.name.is_empty() {
+                release.tag_name
+            } else {
+                release.name
+            };
+            let prerelease = release.prerelease;
+            release.assets.into_iter().map(move |attachment| Asset {
+                filename: attachment.name,
+                url: attachment.browser_download_url,
+                length: Some(attachment.size),
+                release_title: release_title.clone(),
+                prerelease,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,92 @@
+//! Releases of projects on gitlab.com or a self-hosted GitLab instance
+
+use super::{get_json, Asset};
+use crate::rate_limit::Platform;
+use anyhow::Result;
+use reqwest::Url;
+use serde::Deserialize;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Project {
+    pub name: String,
+    pub path_with_namespace: String,
+    pub description: Option<String>,
+    pub web_url: String,
+    pub star_count: usize,
+    #[serde(default)]
+    pub topics: Vec<String>,
+    pub namespace: Namespace,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Namespace {
+    pub name: String,
+    pub web_url: String,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Release {
+    pub name: String,
+    pub tag_name: String,
+    #[serde(default)]
+    pub upcoming_release: bool,
+    pub assets: ReleaseAssets,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct ReleaseAssets {
+    pub links: Vec<Link>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Link {
+    pub name: String,
+    pub url: Url,
+}
+
+/// The API endpoint for the project at `path`, which has to be URL encoded
+fn project_url(instance: &str, path: &str) -> String {
+    format!("{instance}/api/v4/projects/{}", path.replace('/', "%2F"))
+}
+
+pub async fn get_project(instance: &str, path: &str) -> Result<Project> {
+    get_json(&project_url(instance, path), Platform::GitLab).await
+}
+
+/// List the releases of the project at `path`, newest first
+pub async fn list_releases(instance: &str, path: &str) -> Result<Vec<Release>> {
+    get_json(
+        &format!("{}/releases", project_url(instance, path)),
+        Platform::GitLab,
+    )
+    .await
+}
+
+/// List the files attached to the project's releases, newest first
+pub async fn list_assets(instance: &str, path: &str) -> Result<Vec<Asset>> {
+    Ok(list_releases(instance, path)
+        .await?
+        .into_iter()
+        .filter(|release| !release.upcoming_release)
+        .flat_map(|release| {
+            let release_title = if release.name.is_empty() {
+                release.tag_name
+            } else {
+                release.name
+            };
+            release.assets.links.into_iter().map(move |link| Asset {
+                // The link's name is only a label, the URL has the actual filename
+                filename: link
+                    .url
+                    .path_segments()
+                    .and_then(Iterator::last)
+                    .filter(|segment| !segment.is_empty())
+                    .map_or(link.name, ToOwned::to_owned),
+                url: link.url,
+                length: None,
+                release_title: release_title.clone(),
+                prerelease: false,
+            })
+        })
+        .collect())
+}
@@ -0,0 +1,99 @@
+//! Jar files on this computer, which are copied to the output directory when upgrading
+
+use super::{hash_file, ExternalMod, SourceIdentifier};
+use crate::{download::report, CROSS, TICK};
+use anyhow::{ensure, Result};
+use colored::Colorize as _;
+use std::{
+    ffi::OsString,
+    fs::remove_file,
+    path::{Path, PathBuf},
+};
+
+pub fn is_jar_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jar"))
+        && path.is_file()
+}
+
+pub fn parse(path: &Path) -> Result<SourceIdentifier> {
+    ensure!(is_jar_file(path), "{} is not a jar file", path.display());
+    let path = path.canonicalize()?;
+    Ok(SourceIdentifier::LocalFile {
+        sha256: hash_file(&path)?,
+        path,
+    })
+}
+
+/// Store the current hash of the local file of the mod in `external_mods` identified by `id`,
+/// so that a file that changed after being added is not reported as changed once it is installed
+pub fn update_hash(external_mods: &mut [ExternalMod], id: &str) {
+    for mod_ in external_mods {
+        if mod_.identifier.display_id() != id {
+            continue;
+        }
+        if let SourceIdentifier::LocalFile { path, sha256 } = &mut mod_.identifier {
+            if let Ok(current_hash) = hash_file(path) {
+                *sha256 = current_hash;
+            }
+        }
+    }
+}
+
+/// Get the files to install for the local mods in `external_mods`
+///
+/// Copies in `output_dir` that differ from their source file are deleted so that they are installed again.
+/// If a source file is missing, resolution will continue and the error return flag is set to true.
+pub fn to_install(
+    external_mods: &[ExternalMod],
+    output_dir: &Path,
+) -> Result<(Vec<(OsString, PathBuf)>, bool)> {
+    let mut to_install = Vec::new();
+    let mut error = false;
+    let local_mods = external_mods
+        .iter()
+        .filter(|mod_| mod_.identifier.is_local())
+        .collect::<Vec<_>>();
+    let pad_len = local_mods
+        .iter()
+        .map(|mod_| mod_.name.len())
+        .max()
+        .unwrap_or(20)
+        .clamp(20, 50);
+
+    for mod_ in local_mods {
+        let SourceIdentifier::LocalFile { path, sha256 } = &mod_.identifier else {
+            continue;
+        };
+        let Some(filename) = path.file_name().filter(|_| path.is_file()) else {
+            report(format!("{CROSS} {:pad_len$}  File not found", mod_.name).red());
+            error = true;
+            continue;
+        };
+
+        let current_hash = hash_file(path)?;
+        if !current_hash.eq_ignore_ascii_case(sha256) {
+            report(
+                format!(
+                    "Warning: {} has changed since it was added to the profile, the new file will be installed",
+                    path.display()
+                )
+                .yellow(),
+            );
+        }
+        let installed = output_dir.join(filename);
+        if installed.is_file() && hash_file(&installed)? != current_hash {
+            remove_file(installed)?;
+        }
+
+        report(format!(
+            "{} {:pad_len$}  {}",
+            TICK.clone(),
+            mod_.name,
+            filename.to_string_lossy().dimmed()
+        ));
+        to_install.push((filename.to_owned(), path.clone()));
+    }
+
+    Ok((to_install, error))
+}
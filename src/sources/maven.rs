@@ -0,0 +1,121 @@
+//! Artifacts published to a Maven repository
+
+use super::{content_length, CLIENT};
+use anyhow::{Context as _, Result};
+use libium::upgrade::DownloadData;
+use regex::Regex;
+use reqwest::Url;
+use std::sync::LazyLock;
+
+#[expect(clippy::expect_used)]
+static COMMENT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("(?s)<!--.*?-->").expect("Invalid regex"));
+
+/// Get the trimmed contents of every `tag` element in `xml`
+fn elements<'a>(xml: &'a str, tag: &str) -> Vec<&'a str> {
+    let mut contents = Vec::new();
+    let (open, close) = (format!("<{tag}>"), format!("</{tag}>"));
+    let mut rest = xml;
+    while let Some((_, after)) = rest.split_once(&open) {
+        let Some((content, after)) = after.split_once(&close) else {
+            break;
+        };
+        contents.push(content.trim());
+        rest = after;
+    }
+    contents
+}
+
+/// Get the versions listed in the `<versioning>` section of `metadata`, oldest first
+///
+/// If the repository does not list every version, the `<release>` or `<latest>` version is used.
+fn parse_versions(metadata: &str) -> Vec<String> {
+    let metadata = COMMENT.replace_all(metadata, "");
+    let Some(versioning) = elements(&metadata, "versioning").into_iter().next() else {
+        return Vec::new();
+    };
+    let versions = elements(versioning, "versions")
+        .into_iter()
+        .flat_map(|versions| elements(versions, "version"))
+        .filter(|version| !version.is_empty())
+        .map(ToOwned::to_owned)
+        .collect::<Vec<_>>();
+    if !versions.is_empty() {
+        return versions;
+    }
+    ["release", "latest"]
+        .into_iter()
+        .find_map(|tag| {
+            elements(versioning, tag)
+                .into_iter()
+                .find(|version| !version.is_empty())
+        })
+        .map(ToOwned::to_owned)
+        .into_iter()
+        .collect()
+}
+
+/// The URL of the directory containing every version of `group:artifact`
+fn artifact_url(repository: &str, group: &str, artifact: &str) -> String {
+    format!(
+        "{}/{}/{artifact}",
+        repository.trim_end_matches('/'),
+        group.replace('.', "/")
+    )
+}
+
+/// List the versions of `group:artifact` in `repository`, oldest first
+pub async fn list_versions(repository: &str, group: &str, artifact: &str) -> Result<Vec<String>> {
+    let metadata = CLIENT
+        .get(format!(
+            "{}/maven-metadata.xml",
+            artifact_url(repository, group, artifact)
+        ))
+        .send()
+        .await?
+        .error_for_status()?
+        .text()
+        .await?;
+
+    Ok(parse_versions(&metadata))
+}
+
+/// Get the newest version of `group:artifact`, optionally only considering versions that entirely match `version_pattern`
+pub async fn latest_version(
+    repository: &str,
+    group: &str,
+    artifact: &str,
+    version_pattern: Option<&str>,
+) -> Result<String> {
+    let pattern = version_pattern
+        .map(|pattern| Regex::new(&format!("^(?:{pattern})$")))
+        .transpose()?;
+    list_versions(repository, group, artifact)
+        .await?
+        .into_iter()
+        .rev()
+        .find(|version| pattern.as_ref().map_or(true, |p| p.is_match(version)))
+        .context("No version matching the pattern was found")
+}
+
+pub async fn fetch_download_file(
+    repository: &str,
+    group: &str,
+    artifact: &str,
+    version_pattern: Option<&str>,
+) -> Result<DownloadData> {
+    let version = latest_version(repository, group, artifact, version_pattern).await?;
+    let filename = format!("{artifact}-{version}.jar");
+    let url = Url::parse(&format!(
+        "{}/{version}/{filename}",
+        artifact_url(repository, group, artifact)
+    ))?;
+
+    Ok(DownloadData {
+        length: content_length(&url).await.unwrap_or_default(),
+        download_url: url,
+        output: filename.into(),
+        dependencies: Vec::new(),
+        conflicts: Vec::new(),
+    })
+}
@@ -0,0 +1,707 @@
+//! Mod sources that ferium resolves itself, without going through libium
+//!
+//! Mods from these sources are stored in [`Profile::external_mods`](crate::config::Profile::external_mods),
+//! while the files of Modrinth, CurseForge, and GitHub mods are resolved in [`platform`].
+
+pub mod direct;
+pub mod forgejo;
+pub mod gitlab;
+pub mod local;
+pub mod maven;
+pub mod platform;
+
+use crate::{
+    config::Profile,
+    network::CLIENT,
+    rate_limit::{self, Platform, Quota},
+    subcommands::filters::describe,
+};
+use anyhow::{bail, ensure, Context as _, Result};
+use colored::Colorize as _;
+use libium::{
+    config::{
+        filters::{Filter, ReleaseChannel},
+        structs::ModLoader,
+    },
+    upgrade::DownloadData,
+};
+use regex::Regex;
+use reqwest::{header::CONTENT_LENGTH, Client, RequestBuilder, Url};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use sha2::{Digest as _, Sha256};
+use std::{
+    env::var,
+    fmt::{self, Display, Formatter},
+    fs::File,
+    io::copy,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+/// The GitLab instance used when none is specified, can be overridden with `FERIUM_GITLAB_URL`
+pub static DEFAULT_GITLAB_URL: LazyLock<String> =
+    LazyLock::new(|| var("FERIUM_GITLAB_URL").unwrap_or_else(|_| "https://gitlab.com".to_owned()));
+/// The Forgejo instance used when none is specified, can be overridden with `FERIUM_FORGEJO_URL`
+pub static DEFAULT_FORGEJO_URL: LazyLock<String> = LazyLock::new(|| {
+    var("FERIUM_FORGEJO_URL").unwrap_or_else(|_| "https://codeberg.org".to_owned())
+});
+/// The Maven repository used when none is specified, can be overridden with `FERIUM_MAVEN_URL`
+pub static DEFAULT_MAVEN_URL: LazyLock<String> = LazyLock::new(|| {
+    var("FERIUM_MAVEN_URL").unwrap_or_else(|_| "https://repo.maven.apache.org/maven2".to_owned())
+});
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct ExternalMod {
+    pub name: String,
+    pub identifier: SourceIdentifier,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq, Eq)]
+pub enum SourceIdentifier {
+    /// A project on a GitLab instance, identified by its full path (e.g. `group/subgroup/project`)
+    GitLabProject { instance: String, path: String },
+    /// A repository on a Forgejo or Gitea instance, such as Codeberg
+    ForgejoRepository {
+        instance: String,
+        owner: String,
+        repo: String,
+    },
+    /// The newest version of an artifact in a Maven repository,
+    /// optionally only considering versions that match `version_pattern`
+    MavenArtifact {
+        repository: String,
+        group: String,
+        artifact: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        #[serde(default)]
+        version_pattern: Option<String>,
+    },
+    /// A file at a fixed URL, which has to match the SHA-256 hash
+    DirectUrl { url: String, sha256: String },
+    /// A jar file on this computer, along with its SHA-256 hash when it was added
+    LocalFile { path: PathBuf, sha256: String },
+}
+
+impl SourceIdentifier {
+    /// The two letter tag used when listing mods
+    pub const fn tag(&self) -> &'static str {
+        match self {
+            Self::GitLabProject { .. } => "GL",
+            Self::ForgejoRepository { .. } => "FJ",
+            Self::MavenArtifact { .. } => "MV",
+            Self::DirectUrl { .. } => "DL",
+            Self::LocalFile { .. } => "LF",
+        }
+    }
+
+    /// The namespace and name of the project, e.g. `("group/subgroup", "project")`
+    pub fn namespace_and_name(&self) -> (&str, &str) {
+        match self {
+            Self::GitLabProject { path, .. } => path.rsplit_once('/').unwrap_or(("", path)),
+            Self::ForgejoRepository { owner, repo, .. } => (owner, repo),
+            Self::MavenArtifact {
+                group, artifact, ..
+            } => (group, artifact),
+            Self::DirectUrl { url, .. } => url.rsplit_once('/').unwrap_or(("", url)),
+            Self::LocalFile { path, .. } => (
+                path.parent().and_then(Path::to_str).unwrap_or_default(),
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+
+    /// The identifier as the user would type it, without the instance
+    pub fn display_id(&self) -> String {
+        match self {
+            Self::GitLabProject { path, .. } => path.clone(),
+            Self::ForgejoRepository { owner, repo, .. } => format!("{owner}/{repo}"),
+            Self::MavenArtifact {
+                group, artifact, ..
+            } => format!("{group}:{artifact}"),
+            Self::DirectUrl { url, .. } => url.clone(),
+            Self::LocalFile { path, .. } => path.display().to_string(),
+        }
+    }
+
+    /// Whether this mod is installed from a local file instead of being downloaded
+    pub const fn is_local(&self) -> bool {
+        matches!(self, Self::LocalFile { .. })
+    }
+}
+
+impl ExternalMod {
+    /// Whether `query` is this mod's name or identifier, case-insensitively
+    pub fn matches(&self, query: &str) -> bool {
+        self.name.eq_ignore_ascii_case(query)
+            || self.identifier.display_id().eq_ignore_ascii_case(query)
+    }
+}
+
+/// Whether `id` should be parsed by [`parse_id`] instead of libium
+pub fn is_source_id(id: &str) -> bool {
+    local::is_jar_file(Path::new(id))
+        || id.split_once(':').is_some_and(|(scheme, _)| {
+            [
+                "gitlab", "forgejo", "gitea", "codeberg", "maven", "url", "file",
+            ]
+            .iter()
+            .any(|s| scheme.eq_ignore_ascii_case(s))
+        })
+}
+
+/// Parse an identifier of the form `<source>:<path>` or `<source>:<url>`
+///
+/// - `gitlab:group/project` or `gitlab:https://gitlab.example.com/group/project`
+/// - `forgejo:owner/repo` or `forgejo:https://git.example.com/owner/repo` (`gitea:` works too)
+/// - `codeberg:owner/repo`
+/// - `maven:group:artifact` or `maven:group:artifact:version_pattern`, optionally followed by `@repository_url`
+/// - `url:https://example.com/mod.jar`, optionally followed by `#sha256=<hash>`
+/// - `./path/to/mod.jar` or `file:./path/to/mod.jar`
+pub fn parse_id(id: &str) -> Result<SourceIdentifier> {
+    if local::is_jar_file(Path::new(id)) {
+        return local::parse(Path::new(id));
+    }
+    let (scheme, rest) = id.split_once(':').context("Missing source prefix")?;
+    match scheme.to_lowercase().as_str() {
+        "gitlab" => {
+            let (instance, path) = instance_and_path(rest, &DEFAULT_GITLAB_URL)?;
+            Ok(SourceIdentifier::GitLabProject { instance, path })
+        }
+        "forgejo" | "gitea" | "codeberg" => {
+            let default = if scheme.eq_ignore_ascii_case("codeberg") {
+                "https://codeberg.org"
+            } else {
+                DEFAULT_FORGEJO_URL.as_str()
+            };
+            let (instance, path) = instance_and_path(rest, default)?;
+            let Some((owner, repo)) = path.split_once('/').filter(|(_, r)| !r.contains('/')) else {
+                bail!("Forgejo repositories should be of the form `owner/repo`");
+            };
+            Ok(SourceIdentifier::ForgejoRepository {
+                instance,
+                owner: owner.to_owned(),
+                repo: repo.to_owned(),
+            })
+        }
+        "maven" => {
+            let (coordinates, repository) = rest
+                .rsplit_once('@')
+                .unwrap_or((rest, DEFAULT_MAVEN_URL.as_str()));
+            let mut coordinates = coordinates.splitn(3, ':');
+            let (Some(group), Some(artifact)) = (coordinates.next(), coordinates.next()) else {
+                bail!("Maven artifacts should be of the form `group:artifact`");
+            };
+            ensure!(
+                !group.is_empty() && !artifact.is_empty(),
+                "Maven artifacts should be of the form `group:artifact`"
+            );
+            Ok(SourceIdentifier::MavenArtifact {
+                repository: repository.trim_end_matches('/').to_owned(),
+                group: group.to_owned(),
+                artifact: artifact.to_owned(),
+                version_pattern: coordinates.next().map(ToOwned::to_owned),
+            })
+        }
+        "url" => {
+            let mut url = Url::parse(rest)?;
+            // The hash is filled in when adding if it is not provided
+            let sha256 = url
+                .fragment()
+                .and_then(|fragment| fragment.strip_prefix("sha256="))
+                .unwrap_or_default()
+                .to_lowercase();
+            url.set_fragment(None);
+            direct::filename(&url)?;
+            Ok(SourceIdentifier::DirectUrl {
+                url: url.to_string(),
+                sha256,
+            })
+        }
+        "file" => local::parse(Path::new(rest)),
+        _ => bail!("Unknown source `{scheme}`"),
+    }
+}
+
+/// Split `input` into the instance's base URL and the project path, using `default` if no URL is given
+fn instance_and_path(input: &str, default: &str) -> Result<(String, String)> {
+    let (instance, path) = if input.starts_with("http://") || input.starts_with("https://") {
+        let url = Url::parse(input)?;
+        (
+            url.origin().ascii_serialization(),
+            url.path().trim_matches('/').to_owned(),
+        )
+    } else {
+        (
+            default.trim_end_matches('/').to_owned(),
+            input.trim_matches('/').to_owned(),
+        )
+    };
+    ensure!(
+        path.contains('/'),
+        "The project path should include its namespace, e.g. `owner/name`"
+    );
+    Ok((instance, path))
+}
+
+/// Add the mods with the given `ids` to `profile`
+///
+/// Returns the names of the mods that were added, and the IDs that could not be added along with the reason.
+pub async fn add(
+    profile: &mut Profile,
+    ids: Vec<String>,
+    perform_checks: bool,
+) -> (Vec<String>, Vec<(String, anyhow::Error)>) {
+    let mut successes = Vec::new();
+    let mut failures = Vec::new();
+    for id in ids {
+        match add_one(profile, &id, perform_checks).await {
+            Ok(name) => successes.push(name),
+            Err(err) => failures.push((id, err)),
+        }
+    }
+    (successes, failures)
+}
+
+async fn add_one(profile: &mut Profile, id: &str, perform_checks: bool) -> Result<String> {
+    // Use the canonical path from the server so that duplicates can be detected
+    let (name, identifier) = match parse_id(id)? {
+        SourceIdentifier::GitLabProject { instance, path } => {
+            let project = gitlab::get_project(&instance, &path).await?;
+            (
+                project.name,
+                SourceIdentifier::GitLabProject {
+                    instance,
+                    path: project.path_with_namespace,
+                },
+            )
+        }
+        SourceIdentifier::ForgejoRepository {
+            instance,
+            owner,
+            repo,
+        } => {
+            let repository = forgejo::get_repository(&instance, &owner, &repo).await?;
+            (
+                repository.name.clone(),
+                SourceIdentifier::ForgejoRepository {
+                    instance,
+                    owner: repository.owner.login,
+                    repo: repository.name,
+                },
+            )
+        }
+        SourceIdentifier::MavenArtifact {
+            repository,
+            group,
+            artifact,
+            version_pattern,
+        } => {
+            // Check that the artifact exists and has a matching version
+            maven::latest_version(&repository, &group, &artifact, version_pattern.as_deref())
+                .await?;
+            (
+                artifact.clone(),
+                SourceIdentifier::MavenArtifact {
+                    repository,
+                    group,
+                    artifact,
+                    version_pattern,
+                },
+            )
+        }
+        SourceIdentifier::DirectUrl { url, sha256 } => {
+            let parsed = Url::parse(&url)?;
+            let sha256 = if sha256.is_empty() {
+                let sha256 = direct::fetch_hash(&parsed).await?;
+                println!("Recorded the SHA-256 hash of {url} as {sha256}");
+                sha256
+            } else {
+                sha256
+            };
+            (
+                direct::filename(&parsed)?,
+                SourceIdentifier::DirectUrl { url, sha256 },
+            )
+        }
+        identifier @ SourceIdentifier::LocalFile { .. } => {
+            (identifier.namespace_and_name().1.to_owned(), identifier)
+        }
+    };
+
+    if profile
+        .external_mods
+        .iter()
+        .any(|mod_| mod_.identifier == identifier || mod_.name.eq_ignore_ascii_case(&name))
+        || profile
+            .mods
+            .iter()
+            .any(|mod_| mod_.name.eq_ignore_ascii_case(&name))
+    {
+        bail!(libium::add::Error::AlreadyAdded);
+    }
+
+    // Maven repositories and URLs do not have any metadata to filter by
+    if matches!(
+        identifier,
+        SourceIdentifier::MavenArtifact { .. } | SourceIdentifier::DirectUrl { .. }
+    ) && profile.filters.iter().any(|filter| {
+        matches!(
+            filter,
+            Filter::GameVersionStrict(_)
+                | Filter::GameVersionMinor(_)
+                | Filter::ModLoaderPrefer(_)
+                | Filter::ModLoaderAny(_)
+        )
+    }) {
+        println!(
+            "{}",
+            format!(
+                "{name} is not checked against the profile's game version or mod loader, make sure it is compatible"
+            )
+            .yellow()
+        );
+    }
+
+    // Maven artifacts and URLs were already checked above, and local files do not need to be
+    if perform_checks
+        && matches!(
+            identifier,
+            SourceIdentifier::GitLabProject { .. } | SourceIdentifier::ForgejoRepository { .. }
+        )
+    {
+        compatible_asset(&identifier, &profile.filters).await?;
+    }
+
+    profile.external_mods.push(ExternalMod {
+        name: name.clone(),
+        identifier,
+    });
+    Ok(name)
+}
+
+/// A downloadable file attached to a release
+pub struct Asset {
+    pub filename: String,
+    pub url: Url,
+    pub length: Option<usize>,
+    pub release_title: String,
+    pub prerelease: bool,
+}
+
+/// Get the latest file of the mod with `identifier` that is compatible with `filters`
+pub async fn fetch_download_file(
+    identifier: &SourceIdentifier,
+    filters: &[Filter],
+) -> Result<DownloadData> {
+    match identifier {
+        // Maven repositories and URLs do not have any metadata to filter by,
+        // so the profile's filters are not applied to them
+        SourceIdentifier::MavenArtifact {
+            repository,
+            group,
+            artifact,
+            version_pattern,
+        } => {
+            return maven::fetch_download_file(
+                repository,
+                group,
+                artifact,
+                version_pattern.as_deref(),
+            )
+            .await;
+        }
+        SourceIdentifier::DirectUrl { url, .. } => {
+            return direct::fetch_download_file(url).await;
+        }
+        SourceIdentifier::LocalFile { path, .. } => {
+            bail!("{} is installed from a local file", path.display())
+        }
+        SourceIdentifier::GitLabProject { .. } | SourceIdentifier::ForgejoRepository { .. } => {}
+    }
+
+    let asset = compatible_asset(identifier, filters).await?;
+    let length = match asset.length {
+        Some(length) => length,
+        None => content_length(&asset.url).await.unwrap_or_default(),
+    };
+    Ok(DownloadData {
+        download_url: asset.url,
+        output: asset.filename.into(),
+        length,
+        dependencies: Vec::new(),
+        conflicts: Vec::new(),
+    })
+}
+
+/// The latest release asset of the GitLab or Forgejo mod with `identifier` that is compatible with `filters`
+///
+/// Only the releases are listed, so checking a mod when adding it does not request any of its files.
+async fn compatible_asset(identifier: &SourceIdentifier, filters: &[Filter]) -> Result<Asset> {
+    let assets = match identifier {
+        SourceIdentifier::GitLabProject { instance, path } => {
+            gitlab::list_assets(instance, path).await?
+        }
+        SourceIdentifier::ForgejoRepository {
+            instance,
+            owner,
+            repo,
+        } => forgejo::list_assets(instance, owner, repo).await?,
+        _ => bail!("{} does not have releases", identifier.display_id()),
+    };
+
+    for asset in assets {
+        if is_compatible(filters, &asset)? {
+            return Ok(asset);
+        }
+    }
+    Err(NoCompatibleFile(None).into())
+}
+
+/// The error returned when none of a mod's files satisfy its filters,
+/// along with the description of the filter that no file satisfied if there is one
+#[derive(Debug)]
+pub struct NoCompatibleFile(pub Option<String>);
+
+impl Display for NoCompatibleFile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match &self.0 {
+            Some(filter) => write!(f, "No compatible file was found for {filter}"),
+            None => f.write_str("No compatible file was found"),
+        }
+    }
+}
+
+impl std::error::Error for NoCompatibleFile {}
+
+/// What the filters of a mod are checked against for each of its files
+pub struct FileInfo {
+    pub filename: String,
+    pub title: String,
+    pub description: String,
+    pub game_versions: Vec<String>,
+    /// The lowercase names of the mod loaders
+    pub loaders: Vec<String>,
+    pub channel: ReleaseChannel,
+}
+
+impl FileInfo {
+    fn has_loader(&self, loader: &ModLoader) -> bool {
+        let loader = loader.to_string().to_lowercase();
+        self.loaders.iter().any(|name| *name == loader)
+    }
+
+    fn satisfies(&self, filter: &Filter) -> Result<bool> {
+        Ok(match filter {
+            Filter::GameVersionStrict(versions) => {
+                self.game_versions.iter().any(|v| versions.contains(v))
+            }
+            Filter::GameVersionMinor(versions) => self.game_versions.iter().any(|v| {
+                versions
+                    .iter()
+                    .any(|version| minor_group(v) == minor_group(version))
+            }),
+            Filter::ModLoaderPrefer(loaders) | Filter::ModLoaderAny(loaders) => {
+                loaders.iter().any(|loader| self.has_loader(loader))
+            }
+            Filter::ReleaseChannel(channel) => match channel {
+                ReleaseChannel::Release => matches!(self.channel, ReleaseChannel::Release),
+                ReleaseChannel::Beta => !matches!(self.channel, ReleaseChannel::Alpha),
+                ReleaseChannel::Alpha => true,
+            },
+            Filter::Filename(regex) => Regex::new(regex)?.is_match(&self.filename),
+            Filter::Title(regex) => Regex::new(regex)?.is_match(&self.title),
+            Filter::Description(regex) => Regex::new(regex)?.is_match(&self.description),
+        })
+    }
+}
+
+/// The release a game version belongs to, e.g. `1.20` for `1.20.4`,
+/// or the version itself if it is not a release like snapshots
+fn minor_group(version: &str) -> &str {
+    if !version.starts_with(|c: char| c.is_ascii_digit()) {
+        return version;
+    }
+    match version.match_indices('.').nth(1) {
+        Some((index, _)) => &version[..index],
+        None => version,
+    }
+}
+
+/// Pick the first of `files`, which are sorted newest first, that satisfies `filters`
+///
+/// Mod loader preferences are applied after the other filters,
+/// so that the most preferred loader is picked among the files that satisfy them.
+pub fn select<T>(mut files: Vec<(FileInfo, T)>, filters: &[Filter]) -> Result<T> {
+    for filter in filters {
+        if matches!(filter, Filter::ModLoaderPrefer(_)) {
+            continue;
+        }
+        let mut satisfying = Vec::new();
+        for (info, file) in files {
+            if info.satisfies(filter)? {
+                satisfying.push((info, file));
+            }
+        }
+        ensure!(
+            !satisfying.is_empty(),
+            NoCompatibleFile(Some(describe(filter)))
+        );
+        files = satisfying;
+    }
+    for filter in filters {
+        if let Filter::ModLoaderPrefer(loaders) = filter {
+            let loader = loaders
+                .iter()
+                .find(|loader| files.iter().any(|(info, _)| info.has_loader(loader)))
+                .ok_or_else(|| NoCompatibleFile(Some(describe(filter))))?;
+            files.retain(|(info, _)| info.has_loader(loader));
+        }
+    }
+    Ok(files.into_iter().next().ok_or(NoCompatibleFile(None))?.1)
+}
+
+/// The alphanumeric words of `text`
+pub fn words(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect()
+}
+
+/// The version numbers mentioned in `text`, such as `1.20.1`
+pub fn mentioned_versions(text: &str) -> Vec<&str> {
+    text.split(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|token| token.trim_matches('.'))
+        .filter(|token| token.contains('.'))
+        .collect()
+}
+
+/// Whether the lowercase `text` mentions the game `version`
+///
+/// Version numbers are compared whole, so that `1.20` is not found in `1.20.1`.
+pub fn mentions_version(text: &str, version: &str) -> bool {
+    let version = version.to_lowercase();
+    // Snapshots like `24w14a` are single words
+    mentioned_versions(text).contains(&version.as_str()) || words(text).contains(&version.as_str())
+}
+
+/// Check whether `asset` satisfies `filters`
+///
+/// Release assets have no structured metadata, so the game versions and mod loaders
+/// are determined from the release title and the filename.
+/// Game versions have to be mentioned exactly, or within the same minor version for minor filters.
+fn is_compatible(filters: &[Filter], asset: &Asset) -> Result<bool> {
+    if !Path::new(&asset.filename)
+        .extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("jar"))
+        || asset.filename.to_lowercase().ends_with("-sources.jar")
+    {
+        return Ok(false);
+    }
+
+    let text = format!("{} {}", asset.release_title, asset.filename).to_lowercase();
+    let words = words(&text);
+    let mentioned_versions = mentioned_versions(&text);
+    let mentioned_loaders = [
+        ModLoader::Fabric,
+        ModLoader::Quilt,
+        ModLoader::NeoForge,
+        ModLoader::Forge,
+    ]
+    .into_iter()
+    .filter(|loader| words.contains(&loader.to_string().to_lowercase().as_str()))
+    .collect::<Vec<_>>();
+
+    for filter in filters {
+        let satisfied = match filter {
+            Filter::GameVersionStrict(versions) => versions
+                .iter()
+                .any(|version| mentions_version(&text, version)),
+            Filter::GameVersionMinor(versions) => versions.iter().any(|version| {
+                mentions_version(&text, version)
+                    || mentioned_versions
+                        .iter()
+                        .any(|mentioned| minor_group(mentioned) == minor_group(version))
+            }),
+            // Files which do not mention any mod loader are assumed to be compatible
+            Filter::ModLoaderPrefer(loaders) | Filter::ModLoaderAny(loaders) => {
+                mentioned_loaders.is_empty()
+                    || mentioned_loaders.iter().any(|l| loaders.contains(l))
+            }
+            Filter::ReleaseChannel(channel) => {
+                !asset.prerelease || !matches!(channel, ReleaseChannel::Release)
+            }
+            Filter::Filename(regex) => Regex::new(regex)?.is_match(&asset.filename),
+            Filter::Title(regex) => Regex::new(regex)?.is_match(&asset.release_title),
+            // Releases do not have descriptions
+            Filter::Description(_) => true,
+        };
+        if !satisfied {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Calculate the SHA-256 hash of the file at `path`
+pub fn hash_file(path: &Path) -> Result<String> {
+    let mut hasher = Sha256::new();
+    copy(&mut File::open(path)?, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Get and deserialise the JSON at `url`, waiting for `platform`'s rate limit to reset if it has been exceeded
+pub async fn get_json<T: DeserializeOwned>(url: &str, platform: Platform) -> Result<T> {
+    send_json(|client| client.get(url), platform).await
+}
+
+/// How many times a request is sent while the platform's rate limit is exceeded before giving up
+const RATE_LIMITED_ATTEMPTS: usize = 5;
+
+/// Send the request built by `request` and deserialise the JSON response,
+/// waiting for `platform`'s rate limit to reset if it is exceeded
+///
+/// The request is sent up to [`RATE_LIMITED_ATTEMPTS`] times, and fails straight away
+/// if the rate limit resets later than ferium is willing to wait.
+pub async fn send_json<T: DeserializeOwned>(
+    request: impl Fn(&Client) -> RequestBuilder,
+    platform: Platform,
+) -> Result<T> {
+    for _ in 0..RATE_LIMITED_ATTEMPTS {
+        rate_limit::until_ready(platform, None).await;
+        let response = request(&CLIENT).send().await?;
+        let quota = Quota::from_headers(response.headers());
+        let Some(wait) = rate_limit::exceeded(response.status(), response.headers(), &quota) else {
+            rate_limit::observe(platform, &quota);
+            return Ok(response.error_for_status()?.json().await?);
+        };
+        ensure!(
+            wait <= rate_limit::MAX_WAIT,
+            "{platform}'s rate limit was exceeded and resets in {} minutes",
+            wait.as_secs().div_ceil(60)
+        );
+        rate_limit::pause(platform, wait);
+    }
+    bail!("{platform}'s rate limit was exceeded too many times")
+}
+
+/// Request `url` only to read the rate limit headers of the response
+pub async fn fetch_quota(url: &str) -> Result<Quota> {
+    let response = CLIENT.get(url).send().await?;
+    Ok(Quota::from_headers(response.headers()))
+}
+
+async fn content_length(url: &Url) -> Option<usize> {
+    CLIENT
+        .head(url.clone())
+        .send()
+        .await
+        .ok()?
+        .headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
@@ -0,0 +1,175 @@
+//! The files of Modrinth, CurseForge, and GitHub mods
+//!
+//! These are listed through [`api`], so that the configured API URLs are used,
+//! and picked using [`select`] like the files of the other sources.
+
+use super::{select, Asset, FileInfo, NoCompatibleFile};
+use crate::api;
+use anyhow::{Context as _, Result};
+use ferinth::structures::version::{Version, VersionType};
+use furse::structures::file_structs::{File, FileReleaseType};
+use libium::{
+    config::{
+        filters::{Filter, ReleaseChannel},
+        structs::{ModIdentifier, ModpackIdentifier},
+    },
+    upgrade::{try_from_cf_file, DownloadData},
+};
+use octocrab::models::repos::Release;
+
+/// Get the latest file of the mod with `identifier` that is compatible with `filters`
+pub async fn fetch_download_file(
+    identifier: &ModIdentifier,
+    filters: &[Filter],
+) -> Result<DownloadData> {
+    match identifier {
+        ModIdentifier::ModrinthProject(id) => {
+            let mut versions = api::list_versions(id).await?;
+            versions.sort_by(|a, b| b.date_published.cmp(&a.date_published));
+            select(
+                versions.into_iter().filter_map(modrinth_file).collect(),
+                filters,
+            )
+        }
+        ModIdentifier::CurseForgeProject(id) => {
+            let mut files = api::get_mod_files(*id).await?;
+            files.sort_by(|a, b| b.file_date.cmp(&a.file_date));
+            let file = select(
+                files
+                    .into_iter()
+                    .map(|file| (curseforge_info(&file), file))
+                    .collect(),
+                filters,
+            )?;
+            Ok(try_from_cf_file(file)?.1)
+        }
+        ModIdentifier::GitHubRepository((owner, repo)) => {
+            // Stop at the first page with a compatible asset, instead of reading every release
+            for page in 1.. {
+                let releases = api::list_releases_page(owner, repo, page).await?;
+                let last = releases.len() < api::GITHUB_PAGE_SIZE;
+                for asset in releases
+                    .into_iter()
+                    .filter(|release| !release.draft)
+                    .flat_map(github_assets)
+                {
+                    if super::is_compatible(filters, &asset)? {
+                        return Ok(DownloadData {
+                            download_url: asset.url,
+                            output: asset.filename.into(),
+                            length: asset.length.unwrap_or_default(),
+                            dependencies: Vec::new(),
+                            conflicts: Vec::new(),
+                        });
+                    }
+                }
+                if last {
+                    break;
+                }
+            }
+            Err(NoCompatibleFile(None).into())
+        }
+    }
+}
+
+/// The assets of `release`, along with its title and whether it is a pre-release
+fn github_assets(release: Release) -> impl Iterator<Item = Asset> {
+    let release_title = release
+        .name
+        .filter(|name| !name.is_empty())
+        .unwrap_or(release.tag_name);
+    let prerelease = release.prerelease;
+    release.assets.into_iter().map(move |asset| Asset {
+        filename: asset.name,
+        url: asset.browser_download_url,
+        length: usize::try_from(asset.size).ok(),
+        release_title: release_title.clone(),
+        prerelease,
+    })
+}
+
+/// Get the latest file of the modpack with `identifier`
+pub async fn fetch_modpack_file(identifier: &ModpackIdentifier) -> Result<DownloadData> {
+    match identifier {
+        ModpackIdentifier::ModrinthModpack(id) => {
+            let mut versions = api::list_versions(id).await?;
+            versions.sort_by(|a, b| b.date_published.cmp(&a.date_published));
+            versions
+                .into_iter()
+                .find_map(modrinth_file)
+                .map(|(_, file)| file)
+                .context("The modpack has no files")
+        }
+        ModpackIdentifier::CurseForgeModpack(id) => {
+            let file = api::get_mod_files(*id)
+                .await?
+                .into_iter()
+                .max_by(|a, b| a.file_date.cmp(&b.file_date))
+                .context("The modpack has no files")?;
+            Ok(try_from_cf_file(file)?.1)
+        }
+    }
+}
+
+/// The primary file of `version`, or its first file if none are marked as primary
+fn modrinth_file(version: Version) -> Option<(FileInfo, DownloadData)> {
+    let index = version
+        .files
+        .iter()
+        .position(|file| file.primary)
+        .unwrap_or_default();
+    let file = version.files.into_iter().nth(index)?;
+    Some((
+        FileInfo {
+            filename: file.filename.clone(),
+            title: version.name,
+            description: version.changelog.unwrap_or_default(),
+            game_versions: version.game_versions,
+            loaders: version
+                .loaders
+                .into_iter()
+                .map(|loader| loader.to_lowercase())
+                .collect(),
+            channel: match version.version_type {
+                VersionType::Release => ReleaseChannel::Release,
+                VersionType::Beta => ReleaseChannel::Beta,
+                VersionType::Alpha => ReleaseChannel::Alpha,
+            },
+        },
+        DownloadData {
+            download_url: file.url,
+            output: file.filename.into(),
+            length: file.size,
+            dependencies: Vec::new(),
+            conflicts: Vec::new(),
+        },
+    ))
+}
+
+/// Split the game versions of a CurseForge file into game versions and mod loaders,
+/// since CurseForge lists them together
+pub fn curseforge_listing(game_versions: Vec<String>) -> (Vec<String>, Vec<String>) {
+    game_versions.into_iter().partition(|version| {
+        version.starts_with(|c: char| c.is_ascii_digit()) || version.contains("Snapshot")
+    })
+}
+
+fn curseforge_info(file: &File) -> FileInfo {
+    let (game_versions, loaders) = curseforge_listing(file.game_versions.clone());
+    FileInfo {
+        filename: file.file_name.clone(),
+        title: file.display_name.clone(),
+        // CurseForge files do not have descriptions, only their mods do
+        description: String::new(),
+        game_versions,
+        loaders: loaders
+            .into_iter()
+            .map(|loader| loader.to_lowercase())
+            .collect(),
+        channel: match file.release_type {
+            FileReleaseType::Release => ReleaseChannel::Release,
+            FileReleaseType::Beta => ReleaseChannel::Beta,
+            FileReleaseType::Alpha => ReleaseChannel::Alpha,
+        },
+    }
+}
@@ -0,0 +1,39 @@
+use crate::config::migrate::{Report, CURRENT_VERSION};
+use anyhow::{bail, Result};
+use colored::Colorize as _;
+
+/// Report the changes made by migrating the config
+///
+/// The migration itself happens when the config is read, so when `check` is set this only fails
+/// if a migration is needed, and the caller does not write the config.
+pub fn migrate(report: &Report, check: bool) -> Result<()> {
+    if !report.is_needed() {
+        println!(
+            "{}",
+            format!("The config is up to date (version {CURRENT_VERSION})").green()
+        );
+        return Ok(());
+    }
+
+    println!(
+        "{}",
+        format!(
+            "{} the config from version {} to {CURRENT_VERSION}",
+            if check {
+                "Migrating would upgrade"
+            } else {
+                "Upgraded"
+            },
+            report.from
+        )
+        .bold()
+    );
+    for change in &report.changes {
+        println!("  - {change}");
+    }
+
+    if check {
+        bail!("The config needs to be migrated, run `ferium config migrate` to do so");
+    }
+    Ok(())
+}
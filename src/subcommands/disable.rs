@@ -0,0 +1,173 @@
+use crate::{
+    config::{platform_id, Profile},
+    download::installed_file,
+    sources::SourceIdentifier,
+};
+use anyhow::{bail, Result};
+use colored::Colorize as _;
+use inquire::MultiSelect;
+use libium::iter_ext::IterExt as _;
+use std::{ffi::OsString, fs::rename};
+
+/// A mod of the profile, with the identifier it is disabled by
+struct Entry {
+    id: String,
+    name: String,
+}
+
+fn entries(profile: &Profile) -> Vec<Entry> {
+    profile
+        .mods
+        .iter()
+        .map(|mod_| Entry {
+            id: platform_id(&mod_.identifier),
+            name: mod_.name.clone(),
+        })
+        .chain(profile.external_mods.iter().map(|mod_| Entry {
+            id: mod_.identifier.display_id(),
+            name: mod_.name.clone(),
+        }))
+        .collect()
+}
+
+/// Disable the mods in `profile` called or identified by `to_disable`, or picked from a list if it is empty
+///
+/// The mods stay in the profile with their filters, but are not installed,
+/// and their installed files are renamed to `.jar.disabled` so that launchers skip them.
+pub fn disable(profile: &mut Profile, to_disable: Vec<String>) -> Result<()> {
+    set_disabled(profile, to_disable, true)
+}
+
+/// Enable the mods in `profile` called or identified by `to_enable`, or picked from a list if it is empty
+pub fn enable(profile: &mut Profile, to_enable: Vec<String>) -> Result<()> {
+    set_disabled(profile, to_enable, false)
+}
+
+fn set_disabled(profile: &mut Profile, queries: Vec<String>, disable: bool) -> Result<()> {
+    let candidates = entries(profile)
+        .into_iter()
+        .filter(|entry| profile.is_disabled(&entry.id) != disable)
+        .collect_vec();
+
+    let selected = if queries.is_empty() {
+        if candidates.is_empty() {
+            println!(
+                "{}",
+                if disable {
+                    "Every mod is already disabled"
+                } else {
+                    "No mods are disabled"
+                }
+                .yellow()
+            );
+            return Ok(());
+        }
+        let options = candidates
+            .iter()
+            .map(|entry| format!("{}  {}", entry.name, entry.id.dimmed()))
+            .collect_vec();
+        MultiSelect::new(
+            if disable {
+                "Select mods to disable"
+            } else {
+                "Select mods to enable"
+            },
+            options,
+        )
+        .raw_prompt_skippable()?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|option| &candidates[option.index])
+        .collect_vec()
+    } else {
+        let mut selected = Vec::new();
+        for query in &queries {
+            let Some(entry) = candidates.iter().find(|entry| {
+                entry.name.eq_ignore_ascii_case(query) || entry.id.eq_ignore_ascii_case(query)
+            }) else {
+                if entries(profile).iter().any(|entry| {
+                    entry.name.eq_ignore_ascii_case(query) || entry.id.eq_ignore_ascii_case(query)
+                }) {
+                    bail!(
+                        "{query} is already {}",
+                        if disable { "disabled" } else { "enabled" }
+                    );
+                }
+                bail!("A mod with ID or name {query} is not present in this profile");
+            };
+            selected.push(entry);
+        }
+        selected
+    };
+
+    for entry in &selected {
+        if let Err(err) = set_mod_disabled(profile, &entry.id, disable) {
+            println!(
+                "{}",
+                format!(
+                    "Could not rename the installed file of {}: {err}",
+                    entry.name
+                )
+                .yellow()
+            );
+        }
+    }
+
+    if !selected.is_empty() {
+        println!(
+            "{} {}",
+            if disable { "Disabled" } else { "Enabled" },
+            selected.iter().map(|entry| entry.name.bold()).display(", ")
+        );
+    }
+    Ok(())
+}
+
+/// Disable the mod identified by `id`, or enable it if not `disable`, and rename its installed file
+///
+/// The mod is disabled even if renaming its file fails.
+pub fn set_mod_disabled(profile: &mut Profile, id: &str, disable: bool) -> Result<()> {
+    if disable {
+        profile.disabled.push(id.to_owned());
+    } else {
+        profile
+            .disabled
+            .retain(|disabled| !disabled.eq_ignore_ascii_case(id));
+    }
+    rename_installed_file(profile, id, disable)
+}
+
+/// The name of the file the mod identified by `id` is installed as
+///
+/// This is the file recorded by the last upgrade, or the file itself for local mods.
+fn installed_filename(profile: &Profile, id: &str) -> Option<OsString> {
+    let local = profile
+        .external_mods
+        .iter()
+        .find(|mod_| mod_.identifier.display_id() == id)
+        .and_then(|mod_| match &mod_.identifier {
+            SourceIdentifier::LocalFile { path, .. } => path.file_name().map(ToOwned::to_owned),
+            _ => None,
+        });
+    local.or_else(|| installed_file(&profile.output_dir, id).map(OsString::from))
+}
+
+/// Rename the installed file of the mod identified by `id` to end with `.disabled`, or back if not `disable`
+fn rename_installed_file(profile: &Profile, id: &str, disable: bool) -> Result<()> {
+    let Some(filename) = installed_filename(profile, id) else {
+        return Ok(());
+    };
+    let mut disabled_name = filename.clone();
+    disabled_name.push(".disabled");
+    let (from, to) = if disable {
+        (filename, disabled_name)
+    } else {
+        (disabled_name, filename)
+    };
+
+    let from = profile.output_dir.join(from);
+    if from.is_file() {
+        rename(&from, profile.output_dir.join(to))?;
+    }
+    Ok(())
+}
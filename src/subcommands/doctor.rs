@@ -0,0 +1,393 @@
+use super::upgrade::get_platform_downloadables;
+use crate::{
+    api,
+    backup::{self, old_dir, Backup},
+    config::{Config, OldRetention, Profile},
+    sources::{NoCompatibleFile, SourceIdentifier},
+    CROSS, TICK,
+};
+use anyhow::{bail, Result};
+use chrono::Local;
+use colored::Colorize as _;
+use libium::{config::structs::ModIdentifier, iter_ext::IterExt as _, HOME};
+use std::{
+    collections::{HashMap, HashSet},
+    env::var,
+    fs::{create_dir_all, read_dir, remove_dir_all},
+    path::{Path, PathBuf},
+};
+
+/// Something that is wrong with the config or the filesystem
+struct Problem {
+    description: String,
+    /// What the user can do about it
+    advice: String,
+    fix: Option<Fix>,
+}
+
+enum Fix {
+    CreateDirectory(PathBuf),
+    MoveToOld(PathBuf),
+    RemoveBackups(Vec<Backup>),
+    Delete(PathBuf),
+}
+
+impl Fix {
+    const fn describe(&self) -> &'static str {
+        match self {
+            Self::CreateDirectory(_) => "create it",
+            Self::MoveToOld(_) => "move it to `.old`",
+            Self::RemoveBackups(_) => "delete them",
+            Self::Delete(_) => "delete it",
+        }
+    }
+
+    fn apply(&self) -> Result<()> {
+        match self {
+            Self::CreateDirectory(path) => create_dir_all(path)?,
+            Self::MoveToOld(path) => {
+                if let Some(parent) = path.parent() {
+                    Backup::other(parent, "doctor").store(path)?;
+                }
+            }
+            Self::RemoveBackups(backups) => {
+                for backup in backups {
+                    backup.remove()?;
+                }
+            }
+            Self::Delete(path) => remove_dir_all(path)?,
+        }
+        Ok(())
+    }
+}
+
+/// Check the config and the filesystem for common problems, and fix the ones that can be if `fix` is set
+///
+/// The mods of `active` are resolved to check its filters and output directory,
+/// the other checks are run for every profile.
+pub async fn doctor(config: &Config, active: Option<&Profile>, fix: bool) -> Result<()> {
+    let mut problems = Vec::new();
+    println!("{}", "Checking the config".bold());
+    check_output_dirs(config, &mut problems);
+    check_duplicate_mods(config, &mut problems);
+    check_old_folders(config, &mut problems);
+
+    println!("{}", "Checking credentials".bold());
+    check_credentials(&mut problems).await;
+
+    if let Some(active) = active {
+        let profile = config.merged(active)?;
+        if profile.mod_count() > 0 && profile.output_dir.is_dir() {
+            println!();
+            check_active_profile(&profile, &mut problems).await?;
+        }
+    }
+
+    println!();
+    if problems.is_empty() {
+        println!("{}", "No problems found!".green().bold());
+        return Ok(());
+    }
+
+    let mut remaining = 0;
+    for problem in &problems {
+        println!("{}", format!("{CROSS} {}", problem.description).red());
+        match (&problem.fix, fix) {
+            (Some(fix), true) => match fix.apply() {
+                Ok(()) => println!("  {} Fixed", &*TICK),
+                Err(err) => {
+                    remaining += 1;
+                    println!("  {}", format!("Could not fix this: {err}").red());
+                }
+            },
+            (Some(fix), false) => {
+                remaining += 1;
+                println!(
+                    "  {}, or run `ferium doctor --fix` to {}",
+                    problem.advice,
+                    fix.describe()
+                );
+            }
+            (None, _) => {
+                remaining += 1;
+                println!("  {}", problem.advice);
+            }
+        }
+    }
+    if remaining > 0 {
+        bail!("\n{remaining} problem(s) need your attention");
+    }
+    Ok(())
+}
+
+fn check_output_dirs(config: &Config, problems: &mut Vec<Problem>) {
+    let dirs = config
+        .profiles
+        .iter()
+        .map(|profile| (format!("Profile `{}`", profile.name), &profile.output_dir))
+        .chain(
+            config
+                .modpacks
+                .iter()
+                .map(|modpack| (format!("Modpack `{}`", modpack.name), &modpack.output_dir)),
+        );
+
+    let mut seen = HashMap::new();
+    for (owner, dir) in dirs {
+        if !dir.is_dir() {
+            problems.push(Problem {
+                description: format!(
+                    "{owner}'s output directory {} does not exist",
+                    dir.display()
+                ),
+                advice: "Create it, or change it using `configure`".to_owned(),
+                fix: Some(Fix::CreateDirectory(dir.clone())),
+            });
+        }
+        let key = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+        if let Some(other) = seen.insert(key, owner.clone()) {
+            problems.push(Problem {
+                description: format!(
+                    "{other} and {} both use the output directory {}, so upgrading one removes the other's mods",
+                    owner.to_lowercase(),
+                    dir.display()
+                ),
+                advice: "Give one of them a different output directory using `configure`"
+                    .to_owned(),
+                fix: None,
+            });
+        }
+    }
+}
+
+/// A mod's name without its loader or formatting, used to find mods added from multiple platforms
+fn normalise_name(name: &str) -> String {
+    let name = name.split('(').next().unwrap_or(name);
+    name.chars()
+        .filter(char::is_ascii_alphanumeric)
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn platform_name(identifier: &ModIdentifier) -> &'static str {
+    match identifier {
+        ModIdentifier::CurseForgeProject(_) => "CurseForge",
+        ModIdentifier::ModrinthProject(_) => "Modrinth",
+        ModIdentifier::GitHubRepository(_) => "GitHub",
+    }
+}
+
+fn check_duplicate_mods(config: &Config, problems: &mut Vec<Problem>) {
+    for profile in &config.profiles {
+        let mut seen: HashMap<String, (&str, &str)> = HashMap::new();
+        for mod_ in &profile.mods {
+            let platform = platform_name(&mod_.identifier);
+            let key = normalise_name(&mod_.name);
+            if key.is_empty() {
+                continue;
+            }
+            match seen.get(&key) {
+                Some((other_name, other_platform)) if *other_platform != platform => {
+                    problems.push(Problem {
+                        description: format!(
+                            "Profile `{}` has {} from {} and {} from {platform}",
+                            profile.name, other_name, other_platform, mod_.name
+                        ),
+                        advice: "Remove one of them using `ferium remove`".to_owned(),
+                        fix: None,
+                    });
+                }
+                Some(_) => (),
+                None => {
+                    seen.insert(key, (mod_.name.as_str(), platform));
+                }
+            }
+        }
+    }
+}
+
+/// The total size of the files in `dir`, in bytes
+fn dir_size(dir: &Path) -> u64 {
+    read_dir(dir).map_or(0, |entries| {
+        entries
+            .flatten()
+            .map(|entry| match entry.file_type() {
+                Ok(file_type) if file_type.is_dir() => dir_size(&entry.path()),
+                Ok(_) => entry.metadata().map_or(0, |metadata| metadata.len()),
+                Err(_) => 0,
+            })
+            .sum()
+    })
+}
+
+/// Find the backups in `.old` directories that their retention policy does not keep,
+/// which are left behind when the policy was changed or upgrading was interrupted,
+/// and the files from before upgrades were backed up separately
+fn check_old_folders(config: &Config, problems: &mut Vec<Problem>) {
+    let mut directories: Vec<(PathBuf, OldRetention)> = Vec::new();
+    for (directory, retention) in config
+        .profiles
+        .iter()
+        .map(|profile| {
+            (
+                profile.output_dir.clone(),
+                profile.keep_old.unwrap_or_default(),
+            )
+        })
+        .chain(config.modpacks.iter().flat_map(|modpack| {
+            [
+                (modpack.output_dir.join("mods"), OldRetention::default()),
+                (
+                    modpack.output_dir.join("resourcepacks"),
+                    OldRetention::default(),
+                ),
+            ]
+        }))
+    {
+        if !directories.iter().any(|(other, _)| *other == directory) {
+            directories.push((directory, retention));
+        }
+    }
+
+    let now = Local::now().naive_local();
+    for (directory, retention) in directories {
+        let Ok(backups) = backup::list(&directory) else {
+            continue;
+        };
+        let (undated, dated): (Vec<_>, Vec<_>) = backups
+            .into_iter()
+            .partition(|backup| backup.time.is_none());
+
+        let expired = backup::expired(dated, retention, now);
+        if !expired.is_empty() {
+            let size = expired.iter().map(|backup| dir_size(&backup.path)).sum();
+            problems.push(Problem {
+                description: format!(
+                    "{} backup(s) in {} are older than {retention} and take up {}",
+                    expired.len(),
+                    old_dir(&directory).display(),
+                    format_size(size)
+                ),
+                advice: "The next upgrade will delete them".to_owned(),
+                fix: Some(Fix::RemoveBackups(expired)),
+            });
+        }
+
+        for backup in undated {
+            let size = backup.files().map_or(0, |files| {
+                files
+                    .iter()
+                    .filter_map(|file| backup.path.join(file).metadata().ok())
+                    .map(|metadata| metadata.len())
+                    .sum()
+            });
+            problems.push(Problem {
+                description: format!(
+                    "Files from before upgrades were backed up separately are in {} and take up {}",
+                    backup.path.display(),
+                    format_size(size)
+                ),
+                advice: "Delete them if you do not need these files anymore".to_owned(),
+                fix: Some(Fix::RemoveBackups(vec![backup])),
+            });
+        }
+    }
+
+    let temporary = HOME.join(".config").join("ferium").join(".tmp");
+    let size = dir_size(&temporary);
+    if size > 0 {
+        problems.push(Problem {
+            description: format!("{} takes up {}", temporary.display(), format_size(size)),
+            advice: "Delete it if you do not need these files anymore".to_owned(),
+            fix: Some(Fix::Delete(temporary)),
+        });
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    size::Size::from_bytes(bytes)
+        .format()
+        .with_base(size::Base::Base10)
+        .to_string()
+}
+
+async fn check_credentials(problems: &mut Vec<Problem>) {
+    if var("GITHUB_TOKEN").is_ok() {
+        if let Err(err) = api::check_github_token().await {
+            problems.push(Problem {
+                description: format!("The GitHub token was rejected: {err}"),
+                advice: "Create a new personal access token and provide it using `--github-token` or `GITHUB_TOKEN`".to_owned(),
+                fix: None,
+            });
+        }
+    }
+    if var("CURSEFORGE_API_KEY").is_ok() {
+        // Fabric API, which will always exist
+        if let Err(err) = api::get_mod(306_612).await {
+            problems.push(Problem {
+                description: format!("The CurseForge API key was rejected: {err}"),
+                advice:
+                    "Check the key provided using `--curseforge-api-key` or `CURSEFORGE_API_KEY`"
+                        .to_owned(),
+                fix: None,
+            });
+        }
+    }
+}
+
+/// Resolve the mods of `profile` to find the ones that no file matches,
+/// and the jars in its output directory that ferium does not track
+async fn check_active_profile(profile: &Profile, problems: &mut Vec<Problem>) -> Result<()> {
+    let (to_download, failed) = get_platform_downloadables(profile).await?;
+    for (name, err) in failed {
+        let advice = if err.is::<NoCompatibleFile>() {
+            "Change the profile's game versions or mod loaders, or remove the mod"
+        } else {
+            "Check your connection and the mod's project page, then run `ferium doctor` again"
+        };
+        problems.push(Problem {
+            description: format!(
+                "{name} could not be resolved in profile `{}`: {err}",
+                profile.name
+            ),
+            advice: advice.to_owned(),
+            fix: None,
+        });
+    }
+
+    let mut tracked = to_download
+        .iter()
+        .map(|(_, downloadable)| downloadable.filename())
+        .collect::<HashSet<_>>();
+    for mod_ in &profile.external_mods {
+        if let SourceIdentifier::LocalFile { path, .. } = &mod_.identifier {
+            if let Some(name) = path.file_name() {
+                tracked.insert(name.to_string_lossy().into_owned());
+            }
+        }
+    }
+    for entry in read_dir(&profile.output_dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        if path.is_file()
+            && path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("jar"))
+            && !tracked.contains(&name)
+        {
+            problems.push(Problem {
+                description: format!(
+                    "{name} in the output directory is not the current file of any mod in profile `{}`",
+                    profile.name
+                ),
+                advice: "Add it to the profile or move it to the `user` folder to keep it"
+                    .to_owned(),
+                fix: Some(Fix::MoveToOld(path)),
+            });
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,408 @@
+//! Describing, validating and editing the filters of profiles and mods
+
+use super::profile::{pick_minecraft_versions, pick_mod_loader};
+use crate::cli::{FilterKind, ProfileFilterArguments};
+use anyhow::{Context as _, Result};
+use colored::Colorize as _;
+use inquire::{validator::Validation, MultiSelect, Select, Text};
+use libium::{
+    config::{
+        filters::{Filter, ReleaseChannel},
+        structs::{Mod, ModLoader},
+    },
+    iter_ext::IterExt as _,
+};
+use regex::Regex;
+
+impl FilterKind {
+    pub const ALL: [Self; 6] = [
+        Self::ModLoader,
+        Self::GameVersion,
+        Self::ReleaseChannel,
+        Self::Filename,
+        Self::Title,
+        Self::Description,
+    ];
+
+    /// The kind of `filter`, where preferred and accepted mod loaders, and strict
+    /// and minor game versions, are of the same kind since only one of each is used
+    pub const fn of(filter: &Filter) -> Self {
+        match filter {
+            Filter::ModLoaderPrefer(_) | Filter::ModLoaderAny(_) => Self::ModLoader,
+            Filter::GameVersionStrict(_) | Filter::GameVersionMinor(_) => Self::GameVersion,
+            Filter::ReleaseChannel(_) => Self::ReleaseChannel,
+            Filter::Filename(_) => Self::Filename,
+            Filter::Title(_) => Self::Title,
+            Filter::Description(_) => Self::Description,
+        }
+    }
+
+    const fn name(self) -> &'static str {
+        match self {
+            Self::ModLoader => "Mod loader",
+            Self::GameVersion => "Minecraft version",
+            Self::ReleaseChannel => "Release channel",
+            Self::Filename => "Filename pattern",
+            Self::Title => "Title pattern",
+            Self::Description => "Description pattern",
+        }
+    }
+}
+
+/// The release channels, from the most to the least stable
+pub const CHANNELS: [ReleaseChannel; 3] = [
+    ReleaseChannel::Release,
+    ReleaseChannel::Beta,
+    ReleaseChannel::Alpha,
+];
+
+pub const fn channel_name(channel: &ReleaseChannel) -> &'static str {
+    match channel {
+        ReleaseChannel::Release => "release",
+        ReleaseChannel::Beta => "beta",
+        ReleaseChannel::Alpha => "alpha",
+    }
+}
+
+/// The position of `channel` in [`CHANNELS`], higher being less stable
+const fn instability(channel: &ReleaseChannel) -> usize {
+    match channel {
+        ReleaseChannel::Release => 0,
+        ReleaseChannel::Beta => 1,
+        ReleaseChannel::Alpha => 2,
+    }
+}
+
+/// The release channels to try in turn when no files satisfy `filters`,
+/// which are those less stable than the one `filters` allow, up to `fallback`
+pub fn fallback_channels(
+    filters: &[Filter],
+    fallback: Option<&ReleaseChannel>,
+) -> Vec<ReleaseChannel> {
+    // The most stable channel filtered by is the one that applies
+    let current = filters
+        .iter()
+        .filter_map(|filter| match filter {
+            Filter::ReleaseChannel(channel) => Some(instability(channel)),
+            _ => None,
+        })
+        .min();
+    let (Some(current), Some(fallback)) = (current, fallback) else {
+        return Vec::new();
+    };
+    CHANNELS
+        .into_iter()
+        .filter(|channel| (current + 1..=instability(fallback)).contains(&instability(channel)))
+        .collect()
+}
+
+/// Replace the release channel of `filters`, if they have one, with `channel`
+pub fn set_release_channel(filters: &mut [Filter], channel: &ReleaseChannel) {
+    for filter in filters {
+        if let Filter::ReleaseChannel(existing) = filter {
+            *existing = channel.clone();
+        }
+    }
+}
+
+/// A short description of what `filter` allows
+pub fn describe(filter: &Filter) -> String {
+    match filter {
+        Filter::ModLoaderPrefer(loaders) => {
+            format!("mod loader {}", loaders.iter().display(" then "))
+        }
+        Filter::ModLoaderAny(loaders) => {
+            format!("any mod loader of {}", loaders.iter().display(", "))
+        }
+        Filter::GameVersionStrict(versions) => {
+            format!("game version {}", versions.iter().display(", "))
+        }
+        Filter::GameVersionMinor(versions) => {
+            format!("minor versions of {}", versions.iter().display(", "))
+        }
+        Filter::ReleaseChannel(channel) => format!("{} channel", channel_name(channel)),
+        Filter::Filename(pattern) => format!("filename matching `{pattern}`"),
+        Filter::Title(pattern) => format!("title matching `{pattern}`"),
+        Filter::Description(pattern) => format!("description matching `{pattern}`"),
+    }
+}
+
+/// Add `filter` to `filters`, replacing the filter of the same kind if there is one
+pub fn set_filter(filters: &mut Vec<Filter>, filter: Filter) {
+    let kind = FilterKind::of(&filter);
+    match filters
+        .iter_mut()
+        .find(|existing| FilterKind::of(existing) == kind)
+    {
+        Some(existing) => *existing = filter,
+        None => filters.push(filter),
+    }
+}
+
+/// The filters used to pick the files of `mod_`
+///
+/// A mod's own filters replace the profile's filters of the same kind,
+/// and are the only ones used if it overrides the profile's.
+pub fn effective_filters(profile_filters: &[Filter], mod_: &Mod) -> Vec<Filter> {
+    if mod_.override_filters {
+        return mod_.filters.clone();
+    }
+    let own_kinds = mod_.filters.iter().map(FilterKind::of).collect_vec();
+    profile_filters
+        .iter()
+        .filter(|filter| !own_kinds.contains(&FilterKind::of(filter)))
+        .chain(&mod_.filters)
+        .cloned()
+        .collect()
+}
+
+/// Check that the patterns of `filters` are valid regular expressions
+pub fn check_patterns(filters: &[Filter]) -> Result<()> {
+    for filter in filters {
+        if let Filter::Filename(pattern) | Filter::Title(pattern) | Filter::Description(pattern) =
+            filter
+        {
+            Regex::new(pattern)
+                .with_context(|| format!("`{pattern}` is not a valid regular expression"))?;
+        }
+    }
+    Ok(())
+}
+
+/// Apply the changes in `arguments` to `filters`, returning whether any were given
+///
+/// Filters are removed first, then added or replaced, and reordered last.
+pub fn apply(filters: &mut Vec<Filter>, arguments: ProfileFilterArguments) -> Result<bool> {
+    let ProfileFilterArguments {
+        release_channel,
+        filename,
+        title,
+        description,
+        minor_versions,
+        strict_versions,
+        remove_filter,
+        filter_order,
+    } = arguments;
+    let mut changed = false;
+
+    for kind in remove_filter {
+        filters.retain(|filter| FilterKind::of(filter) != kind);
+        changed = true;
+    }
+
+    let added = release_channel
+        .map(Filter::ReleaseChannel)
+        .into_iter()
+        .chain(filename.map(Filter::Filename))
+        .chain(title.map(Filter::Title))
+        .chain(description.map(Filter::Description))
+        .collect_vec();
+    check_patterns(&added)?;
+    for filter in added {
+        set_filter(filters, filter);
+        changed = true;
+    }
+
+    if minor_versions || strict_versions {
+        let versions = filters
+            .iter_mut()
+            .find(|filter| FilterKind::of(filter) == FilterKind::GameVersion)
+            .context("This profile does not filter by game version")?;
+        *versions = match versions {
+            Filter::GameVersionStrict(v) | Filter::GameVersionMinor(v) if minor_versions => {
+                Filter::GameVersionMinor(v.clone())
+            }
+            Filter::GameVersionStrict(v) | Filter::GameVersionMinor(v) => {
+                Filter::GameVersionStrict(v.clone())
+            }
+            _ => unreachable!(),
+        };
+        changed = true;
+    }
+
+    if !filter_order.is_empty() {
+        // The sort is stable, so the kinds not listed keep their order
+        filters.sort_by_key(|filter| {
+            filter_order
+                .iter()
+                .position(|kind| *kind == FilterKind::of(filter))
+                .unwrap_or(filter_order.len())
+        });
+        changed = true;
+    }
+    Ok(changed)
+}
+
+/// Ask for a regular expression, which is checked as it is typed
+fn pick_pattern(message: &str, current: Option<&String>) -> Result<String> {
+    let mut text = Text::new(message).with_validator(|pattern: &str| {
+        Ok(match Regex::new(pattern) {
+            Ok(_) => Validation::Valid,
+            Err(err) => Validation::Invalid(err.to_string().into()),
+        })
+    });
+    if let Some(current) = current {
+        text = text.with_initial_value(current);
+    }
+    Ok(text.prompt()?)
+}
+
+/// Ask for a release channel
+pub fn pick_channel(message: &str) -> Result<ReleaseChannel> {
+    let selection = Select::new(message, CHANNELS.iter().map(channel_name).collect_vec())
+        .without_filtering()
+        .raw_prompt()?;
+    Ok(CHANNELS[selection.index].clone())
+}
+
+/// Ask for a filter of `kind`, starting from the one in `filters` if there is one
+///
+/// Returns `None` if an empty pattern is given, which removes the filter.
+async fn pick_filter(kind: FilterKind, filters: &[Filter]) -> Result<Option<Filter>> {
+    let current = filters.iter().find(|filter| FilterKind::of(filter) == kind);
+    let current_pattern = match current {
+        Some(Filter::Filename(pattern) | Filter::Title(pattern) | Filter::Description(pattern)) => {
+            Some(pattern)
+        }
+        _ => None,
+    };
+
+    Ok(match kind {
+        FilterKind::ModLoader => {
+            let default = match current {
+                Some(Filter::ModLoaderPrefer(loaders) | Filter::ModLoaderAny(loaders)) => {
+                    loaders.first()
+                }
+                _ => None,
+            };
+            Some(Filter::ModLoaderPrefer(match pick_mod_loader(default)? {
+                ModLoader::Quilt => vec![ModLoader::Quilt, ModLoader::Fabric],
+                loader => vec![loader],
+            }))
+        }
+        FilterKind::GameVersion => {
+            let default = match current {
+                Some(Filter::GameVersionStrict(versions) | Filter::GameVersionMinor(versions)) => {
+                    versions.as_slice()
+                }
+                _ => &[],
+            };
+            let versions = pick_minecraft_versions(default).await?;
+            let minor = Select::new(
+                "Which files should be accepted?",
+                vec![
+                    "Only files for exactly these versions",
+                    "Files for any minor version of these",
+                ],
+            )
+            .without_filtering()
+            .raw_prompt()?
+            .index
+                == 1;
+            Some(if minor {
+                Filter::GameVersionMinor(versions)
+            } else {
+                Filter::GameVersionStrict(versions)
+            })
+        }
+        FilterKind::ReleaseChannel => Some(Filter::ReleaseChannel(pick_channel(
+            "Which is the least stable release channel to use?",
+        )?)),
+        FilterKind::Filename | FilterKind::Title | FilterKind::Description => {
+            let pattern = pick_pattern(
+                "Enter a regular expression, or nothing to remove the filter",
+                current_pattern,
+            )?;
+            if pattern.is_empty() {
+                None
+            } else {
+                Some(match kind {
+                    FilterKind::Filename => Filter::Filename(pattern),
+                    FilterKind::Title => Filter::Title(pattern),
+                    _ => Filter::Description(pattern),
+                })
+            }
+        }
+    })
+}
+
+/// Let the user add, change, remove and reorder `filters` until they are done
+pub async fn edit(filters: &mut Vec<Filter>) -> Result<()> {
+    let items = vec![
+        "Add or change a filter",
+        "Remove filters",
+        "Move a filter",
+        "Done",
+    ];
+    loop {
+        if filters.is_empty() {
+            println!("{}", "There are no filters".yellow());
+        } else {
+            for (i, filter) in filters.iter().enumerate() {
+                println!("  {}. {}", i + 1, describe(filter));
+            }
+        }
+        let Ok(selection) = Select::new("What would you like to do?", items.clone())
+            .without_filtering()
+            .raw_prompt()
+        else {
+            break;
+        };
+
+        match selection.index {
+            0 => {
+                let Ok(kind) = Select::new(
+                    "Which filter?",
+                    FilterKind::ALL.iter().map(|kind| kind.name()).collect_vec(),
+                )
+                .without_filtering()
+                .raw_prompt() else {
+                    continue;
+                };
+                let kind = FilterKind::ALL[kind.index];
+                match pick_filter(kind, filters).await {
+                    Ok(Some(filter)) => set_filter(filters, filter),
+                    Ok(None) => filters.retain(|filter| FilterKind::of(filter) != kind),
+                    // The prompt was cancelled
+                    Err(_) => continue,
+                }
+            }
+            1 => {
+                let options = filters.iter().map(describe).collect_vec();
+                let selected = MultiSelect::new("Select filters to remove", options)
+                    .raw_prompt_skippable()?
+                    .unwrap_or_default();
+                // Remove from the end so that the other indices stay correct
+                for option in selected.into_iter().rev() {
+                    filters.remove(option.index);
+                }
+            }
+            2 => {
+                if filters.len() < 2 {
+                    println!("{}", "There is nothing to reorder".yellow());
+                    continue;
+                }
+                let options = filters.iter().map(describe).collect_vec();
+                let Ok(from) = Select::new("Which filter should be moved?", options)
+                    .without_filtering()
+                    .raw_prompt()
+                else {
+                    continue;
+                };
+                let positions = (1..=filters.len()).collect_vec();
+                let Ok(to) = Select::new("Move it to which position?", positions)
+                    .without_filtering()
+                    .with_starting_cursor(from.index)
+                    .raw_prompt()
+                else {
+                    continue;
+                };
+                let filter = filters.remove(from.index);
+                filters.insert(to.index, filter);
+            }
+            _ => break,
+        }
+        println!();
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use crate::{
+    cli::GroupSubCommands,
+    config::{platform_id, ModGroup, Profile},
+};
+use anyhow::{bail, Context as _, Result};
+use colored::Colorize as _;
+use libium::iter_ext::IterExt as _;
+
+pub fn subcommand(subcommand: GroupSubCommands, profile: &mut Profile) -> Result<()> {
+    match subcommand {
+        GroupSubCommands::Add { group, mod_names } => add(profile, &group, &mod_names)?,
+        GroupSubCommands::Remove { group, mod_names } => remove(profile, &group, &mod_names)?,
+        GroupSubCommands::Enable { group } => set_enabled(profile, &group, true)?,
+        GroupSubCommands::Disable { group } => set_enabled(profile, &group, false)?,
+        GroupSubCommands::List => list(profile),
+    }
+    Ok(())
+}
+
+/// The identifier and name of the mod in `profile` called or identified by `query`
+fn find_mod(profile: &Profile, query: &str) -> Result<(String, String)> {
+    profile
+        .mods
+        .iter()
+        .map(|mod_| (platform_id(&mod_.identifier), &mod_.name))
+        .chain(
+            profile
+                .external_mods
+                .iter()
+                .map(|mod_| (mod_.identifier.display_id(), &mod_.name)),
+        )
+        .find(|(id, name)| id.eq_ignore_ascii_case(query) || name.eq_ignore_ascii_case(query))
+        .map(|(id, name)| (id, name.clone()))
+        .with_context(|| format!("A mod with ID or name {query} is not present in this profile"))
+}
+
+fn find_group<'a>(profile: &'a mut Profile, name: &str) -> Result<&'a mut ModGroup> {
+    profile
+        .groups
+        .iter_mut()
+        .find(|group| group.name.eq_ignore_ascii_case(name))
+        .with_context(|| format!("The group {name} does not exist"))
+}
+
+/// The name of the mod with the identifier `id`, or the identifier if it is no longer in `profile`
+fn mod_name(profile: &Profile, id: &str) -> String {
+    find_mod(profile, id).map_or_else(|_| id.to_owned(), |(_, name)| name)
+}
+
+/// Add the mods called or identified by `mod_names` to `group`, creating it if it does not exist
+///
+/// A mod can only be in one group, so it is moved out of any other group.
+fn add(profile: &mut Profile, group: &str, mod_names: &[String]) -> Result<()> {
+    let mods = mod_names
+        .iter()
+        .map(|query| find_mod(profile, query))
+        .collect::<Result<Vec<_>>>()?;
+    for other in &mut profile.groups {
+        other
+            .mods
+            .retain(|id| !mods.iter().any(|(m, _)| m.eq_ignore_ascii_case(id)));
+    }
+
+    if !profile
+        .groups
+        .iter()
+        .any(|existing| existing.name.eq_ignore_ascii_case(group))
+    {
+        profile.groups.push(ModGroup {
+            name: group.to_owned(),
+            enabled: true,
+            mods: Vec::new(),
+        });
+    }
+    let group = find_group(profile, group)?;
+    group.mods.extend(mods.iter().map(|(id, _)| id.clone()));
+    println!(
+        "Added {} to {}",
+        mods.iter().map(|(_, name)| name.bold()).display(", "),
+        group.name.bold()
+    );
+    Ok(())
+}
+
+/// Remove the mods called or identified by `mod_names` from `group`, or delete it if none are given
+///
+/// The mods stay in the profile, and are installed like any other mod.
+fn remove(profile: &mut Profile, group: &str, mod_names: &[String]) -> Result<()> {
+    if mod_names.is_empty() {
+        let name = find_group(profile, group)?.name.clone();
+        profile.groups.retain(|existing| existing.name != name);
+        println!("Deleted {}", name.bold());
+        return Ok(());
+    }
+
+    let mods = mod_names
+        .iter()
+        .map(|query| find_mod(profile, query))
+        .collect::<Result<Vec<_>>>()?;
+    let group = find_group(profile, group)?;
+    for (id, name) in &mods {
+        if !group.contains(id) {
+            bail!("{name} is not in {}", group.name);
+        }
+    }
+    group
+        .mods
+        .retain(|id| !mods.iter().any(|(m, _)| m.eq_ignore_ascii_case(id)));
+    println!(
+        "Removed {} from {}",
+        mods.iter().map(|(_, name)| name.bold()).display(", "),
+        group.name.bold()
+    );
+    Ok(())
+}
+
+fn set_enabled(profile: &mut Profile, group: &str, enabled: bool) -> Result<()> {
+    let group = find_group(profile, group)?;
+    group.enabled = enabled;
+    println!(
+        "{} {}, run `ferium upgrade` to {} its mods",
+        if enabled { "Enabled" } else { "Disabled" },
+        group.name.bold(),
+        if enabled { "install" } else { "remove" }
+    );
+    Ok(())
+}
+
+fn list(profile: &Profile) {
+    if profile.groups.is_empty() {
+        println!(
+            "{}",
+            "This profile has no groups, create one using `ferium group add <group> <mods>`"
+                .yellow()
+        );
+        return;
+    }
+    for group in &profile.groups {
+        println!(
+            "{} {}",
+            group.name.bold(),
+            if group.enabled {
+                "(enabled)".green()
+            } else {
+                "(disabled)".red()
+            }
+        );
+        for id in &group.mods {
+            println!("  {}", mod_name(profile, id));
+        }
+    }
+}
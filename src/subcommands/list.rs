@@ -1,47 +1,260 @@
-use crate::TICK;
+pub mod matrix;
+
+use super::filters::{describe, effective_filters};
+use crate::{
+    api,
+    config::{platform_id, Inherited, Profile},
+    sources::{forgejo, gitlab, hash_file, maven, SourceIdentifier},
+    TICK,
+};
 use anyhow::{Context as _, Result};
 use colored::Colorize as _;
 use ferinth::structures::{project::Project, user::TeamMember};
 use furse::structures::mod_structs::Mod;
 use futures::{stream::FuturesUnordered, StreamExt as _};
 use libium::{
-    config::structs::{ModIdentifier, Profile},
+    config::{filters::Filter, structs::ModIdentifier},
     iter_ext::IterExt as _,
-    CURSEFORGE_API, GITHUB_API, MODRINTH_API,
 };
 use octocrab::models::{repos::Release, Repository};
+use std::path::Path;
 
-enum Metadata {
+/// The details of a mod as listed by its source
+pub enum Metadata {
     CF(Mod),
     MD(Project, Vec<TeamMember>),
     GH(Repository, Vec<Release>),
+    GL(SourceIdentifier, gitlab::Project, Vec<gitlab::Release>),
+    FJ(SourceIdentifier, forgejo::Repository, Vec<forgejo::Release>),
+    MV(SourceIdentifier, Vec<String>),
+    DL(SourceIdentifier),
+    LF(SourceIdentifier),
 }
 impl Metadata {
-    fn name(&self) -> &str {
+    pub fn name(&self) -> &str {
         match self {
             Metadata::CF(p) => &p.name,
             Metadata::MD(p, _) => &p.title,
             Metadata::GH(p, _) => &p.name,
+            Metadata::GL(_, p, _) => &p.name,
+            Metadata::FJ(_, p, _) => &p.name,
+            Metadata::MV(id, _) | Metadata::DL(id) | Metadata::LF(id) => id.namespace_and_name().1,
+        }
+    }
+
+    /// The identifier of the mod this metadata belongs to, as [`crate::config::platform_id`] or
+    /// [`SourceIdentifier::display_id`] would show it
+    #[expect(clippy::unwrap_used)]
+    pub fn id(&self) -> String {
+        match self {
+            Metadata::CF(p) => p.id.to_string(),
+            Metadata::MD(p, _) => p.id.clone(),
+            Metadata::GH(p, _) => format!("{}/{}", p.owner.as_ref().unwrap().login, p.name),
+            Metadata::GL(id, ..)
+            | Metadata::FJ(id, ..)
+            | Metadata::MV(id, _)
+            | Metadata::DL(id)
+            | Metadata::LF(id) => id.display_id(),
+        }
+    }
+
+    /// The summary of the mod, if its source provides one
+    pub fn description(&self) -> Option<&str> {
+        match self {
+            Metadata::CF(p) => Some(p.summary.trim()),
+            Metadata::MD(p, _) => Some(p.description.trim()),
+            Metadata::GH(p, _) => p.description.as_deref(),
+            Metadata::GL(_, p, _) => p.description.as_deref().filter(|d| !d.is_empty()),
+            Metadata::FJ(_, p, _) => Some(p.description.as_str()).filter(|d| !d.is_empty()),
+            Metadata::MV(..) | Metadata::DL(_) | Metadata::LF(_) => None,
         }
     }
 
+    /// The fields shown by `ferium list --verbose`, as plain text
     #[expect(clippy::unwrap_used)]
-    fn id(&self) -> ModIdentifier {
+    pub fn details(&self) -> Vec<(&'static str, String)> {
         match self {
+            Metadata::CF(p) => vec![
+                ("Link", p.links.website_url.to_string()),
+                ("Source", "CurseForge Mod".into()),
+                ("Project ID", p.id.to_string()),
+                ("Open Source", yes_or_no(p.links.source_url.as_ref())),
+                ("Downloads", p.download_count.to_string()),
+                (
+                    "Authors",
+                    p.authors.iter().map(|a| &a.name).display(", ").to_string(),
+                ),
+                (
+                    "Categories",
+                    p.categories
+                        .iter()
+                        .map(|c| &c.name)
+                        .display(", ")
+                        .to_string(),
+                ),
+            ],
+            Metadata::MD(p, members) => vec![
+                ("Link", format!("https://modrinth.com/mod/{}", p.slug)),
+                ("Source", "Modrinth Mod".into()),
+                ("Project ID", p.id.clone()),
+                ("Open Source", yes_or_no(p.source_url.as_ref())),
+                ("Downloads", p.downloads.to_string()),
+                (
+                    "Authors",
+                    members
+                        .iter()
+                        .map(|m| &m.user.username)
+                        .display(", ")
+                        .to_string(),
+                ),
+                ("Categories", p.categories.iter().display(", ").to_string()),
+                (
+                    "License",
+                    if p.license.name.is_empty() {
+                        "Custom".into()
+                    } else {
+                        p.license.name.clone()
+                    },
+                ),
+            ],
+            Metadata::GH(p, releases) => vec![
+                ("Link", p.html_url.as_ref().unwrap().to_string()),
+                ("Source", "GitHub Repository".into()),
+                ("Identifier", p.full_name.clone().unwrap_or_default()),
+                ("Open Source", "Yes".into()),
+                (
+                    "Downloads",
+                    releases
+                        .iter()
+                        .flat_map(|release| &release.assets)
+                        .map(|asset| asset.download_count)
+                        .sum::<i64>()
+                        .to_string(),
+                ),
+                ("Authors", p.owner.as_ref().unwrap().login.clone()),
+                (
+                    "Topics",
+                    p.topics
+                        .as_ref()
+                        .map_or(String::new(), |t| t.iter().display(", ").to_string()),
+                ),
+                (
+                    "License",
+                    p.license
+                        .as_ref()
+                        .map_or("None".into(), |license| license.name.clone()),
+                ),
+            ],
+            Metadata::GL(_, p, releases) => vec![
+                ("Link", p.web_url.clone()),
+                ("Source", "GitLab Project".into()),
+                ("Identifier", p.path_with_namespace.clone()),
+                ("Open Source", "Yes".into()),
+                ("Releases", releases.len().to_string()),
+                ("Stars", p.star_count.to_string()),
+                ("Owner", p.namespace.name.clone()),
+                ("Topics", p.topics.iter().display(", ").to_string()),
+            ],
+            Metadata::FJ(_, p, releases) => vec![
+                ("Link", p.html_url.clone()),
+                ("Source", "Forgejo Repository".into()),
+                ("Identifier", p.full_name.clone()),
+                ("Open Source", "Yes".into()),
+                (
+                    "Downloads",
+                    releases
+                        .iter()
+                        .flat_map(|release| &release.assets)
+                        .map(|asset| asset.download_count)
+                        .sum::<usize>()
+                        .to_string(),
+                ),
+                ("Stars", p.stars_count.to_string()),
+                ("Owner", p.owner.login.clone()),
+                ("Topics", p.topics.iter().display(", ").to_string()),
+            ],
+            Metadata::MV(id, versions) => {
+                let SourceIdentifier::MavenArtifact {
+                    repository,
+                    group,
+                    artifact,
+                    ..
+                } = id
+                else {
+                    return Vec::new();
+                };
+                vec![
+                    (
+                        "Link",
+                        format!("{repository}/{}/{artifact}", group.replace('.', "/")),
+                    ),
+                    ("Source", "Maven Artifact".into()),
+                    ("Identifier", format!("{group}:{artifact}")),
+                    ("Versions", versions.len().to_string()),
+                    ("Latest", versions.last().cloned().unwrap_or("None".into())),
+                ]
+            }
+            Metadata::DL(id) => {
+                let SourceIdentifier::DirectUrl { url, sha256 } = id else {
+                    return Vec::new();
+                };
+                vec![
+                    ("Link", url.clone()),
+                    ("Source", "Direct URL".into()),
+                    ("SHA-256", sha256.clone()),
+                ]
+            }
+            Metadata::LF(id) => {
+                let SourceIdentifier::LocalFile { path, sha256 } = id else {
+                    return Vec::new();
+                };
+                vec![
+                    ("Path", path.display().to_string()),
+                    ("Source", "Local File".into()),
+                    ("SHA-256", sha256.clone()),
+                    ("Status", local_status(path, sha256).into()),
+                ]
+            }
+        }
+    }
+
+    /// Set the name of the mod this metadata belongs to in `profile`
+    #[expect(clippy::unwrap_used)]
+    fn update_name(&self, profile: &mut Profile) -> Result<()> {
+        let id = match self {
             Metadata::CF(p) => ModIdentifier::CurseForgeProject(p.id),
             Metadata::MD(p, _) => ModIdentifier::ModrinthProject(p.id.clone()),
             Metadata::GH(p, _) => {
                 ModIdentifier::GitHubRepository((p.owner.clone().unwrap().login, p.name.clone()))
             }
-        }
+            Metadata::GL(id, ..) | Metadata::FJ(id, ..) => {
+                profile
+                    .external_mods
+                    .iter_mut()
+                    .find(|mod_| &mod_.identifier == id)
+                    .context("Could not find expected mod")?
+                    .name = self.name().to_string();
+                return Ok(());
+            }
+            // These do not provide a name
+            Metadata::MV(..) | Metadata::DL(_) | Metadata::LF(_) => return Ok(()),
+        };
+        profile
+            .mods
+            .iter_mut()
+            .find(|mod_| mod_.identifier == id)
+            .context("Could not find expected mod")?
+            .name = self.name().to_string();
+        Ok(())
     }
 }
 
-pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
-    if !markdown {
-        eprint!("Querying metadata... ");
-    }
+fn yes_or_no(source_url: Option<&impl ToString>) -> String {
+    source_url.map_or("No".into(), |url| format!("Yes ({})", url.to_string()))
+}
 
+/// Query the sources of the mods in `profile` for their metadata, sorted by name
+pub async fn fetch_metadata(profile: &Profile) -> Result<Vec<Metadata>> {
     let mut tasks = FuturesUnordered::new();
     let mut mr_ids = Vec::new();
     let mut cf_ids = Vec::new();
@@ -50,15 +263,10 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
             ModIdentifier::CurseForgeProject(project_id) => cf_ids.push(project_id),
             ModIdentifier::ModrinthProject(project_id) => mr_ids.push(project_id),
             ModIdentifier::GitHubRepository((owner, repo)) => {
-                tasks.push(async {
+                tasks.push(async move {
                     Ok::<_, anyhow::Error>((
-                        GITHUB_API.repos(&owner, &repo).get().await?,
-                        GITHUB_API
-                            .repos(owner, repo)
-                            .releases()
-                            .list()
-                            .send()
-                            .await?,
+                        api::get_repository(&owner, &repo).await?,
+                        api::list_releases_page(&owner, &repo, 1).await?,
                     ))
                 });
             }
@@ -68,22 +276,53 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
     let mr_projects = if mr_ids.is_empty() {
         vec![]
     } else {
-        MODRINTH_API
-            .get_multiple_projects(&mr_ids.iter().map(AsRef::as_ref).collect_vec())
-            .await?
+        api::get_multiple_projects(&mr_ids.iter().map(AsRef::as_ref).collect_vec()).await?
     };
     let mr_teams_members = if mr_projects.is_empty() {
         vec![]
     } else {
-        MODRINTH_API
-            .list_multiple_teams_members(&mr_projects.iter().map(|p| p.team.as_ref()).collect_vec())
+        api::list_multiple_teams_members(&mr_projects.iter().map(|p| p.team.as_ref()).collect_vec())
             .await?
     };
 
+    let mut external_tasks = FuturesUnordered::new();
+    for mod_ in &profile.external_mods {
+        let id = mod_.identifier.clone();
+        external_tasks.push(async move {
+            Ok::<_, anyhow::Error>(match &id {
+                SourceIdentifier::GitLabProject { instance, path } => {
+                    let project = gitlab::get_project(instance, path).await?;
+                    let releases = gitlab::list_releases(instance, path).await?;
+                    Metadata::GL(id, project, releases)
+                }
+                SourceIdentifier::ForgejoRepository {
+                    instance,
+                    owner,
+                    repo,
+                } => {
+                    let repository = forgejo::get_repository(instance, owner, repo).await?;
+                    let releases = forgejo::list_releases(instance, owner, repo).await?;
+                    Metadata::FJ(id, repository, releases)
+                }
+                SourceIdentifier::MavenArtifact {
+                    repository,
+                    group,
+                    artifact,
+                    ..
+                } => {
+                    let versions = maven::list_versions(repository, group, artifact).await?;
+                    Metadata::MV(id, versions)
+                }
+                SourceIdentifier::DirectUrl { .. } => Metadata::DL(id),
+                SourceIdentifier::LocalFile { .. } => Metadata::LF(id),
+            })
+        });
+    }
+
     let cf_projects = if cf_ids.is_empty() {
         vec![]
     } else {
-        CURSEFORGE_API.get_mods(cf_ids).await?
+        api::get_mods(cf_ids).await?
     };
 
     let mut metadata = Vec::new();
@@ -95,40 +334,116 @@ pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
     }
     while let Some(res) = tasks.next().await {
         let (repo, releases) = res?;
-        metadata.push(Metadata::GH(repo, releases.items));
+        metadata.push(Metadata::GH(repo, releases));
+    }
+    while let Some(res) = external_tasks.next().await {
+        metadata.push(res?);
     }
     metadata.sort_unstable_by_key(|e| e.name().to_lowercase());
+    Ok(metadata)
+}
 
+pub async fn verbose(profile: &mut Profile, markdown: bool) -> Result<()> {
+    if !markdown {
+        eprint!("Querying metadata... ");
+    }
+    let metadata = fetch_metadata(profile).await?;
     if !markdown {
         println!("{}", &*TICK);
     }
 
     for project in &metadata {
-        profile
-            .mods
-            .iter_mut()
-            .find(|mod_| mod_.identifier == project.id())
-            .context("Could not find expected mod")?
-            .name = project.name().to_string();
+        project.update_name(profile)?;
 
         if markdown {
             match project {
                 Metadata::CF(p) => curseforge_md(p),
                 Metadata::MD(p, t) => modrinth_md(p, t),
                 Metadata::GH(p, _) => github_md(p),
+                Metadata::GL(_, p, _) => gitlab_md(p),
+                Metadata::FJ(_, p, _) => forgejo_md(p),
+                Metadata::MV(id, _) => maven_md(id),
+                Metadata::DL(id) => direct_md(id),
+                Metadata::LF(id) => local_md(id),
             }
         } else {
             match project {
                 Metadata::CF(p) => curseforge(p),
                 Metadata::MD(p, t) => modrinth(p, t),
                 Metadata::GH(p, r) => github(p, r),
+                Metadata::GL(_, p, r) => gitlab(p, r),
+                Metadata::FJ(_, p, r) => forgejo(p, r),
+                Metadata::MV(id, v) => maven(id, v),
+                Metadata::DL(id) => direct(id),
+                Metadata::LF(id) => local(id),
             }
         }
+        if let Some(mod_) = profile
+            .mods
+            .iter()
+            .find(|mod_| platform_id(&mod_.identifier).eq_ignore_ascii_case(&project.id()))
+        {
+            filters(
+                &effective_filters(&profile.filters, mod_),
+                mod_.override_filters,
+                markdown,
+            );
+        }
     }
 
     Ok(())
 }
 
+/// Print the filters used to pick a mod's files, below its metadata
+fn filters(effective: &[Filter], overrides: bool, markdown: bool) {
+    let mut described = if effective.is_empty() {
+        "None".to_owned()
+    } else {
+        effective.iter().map(describe).display(", ").to_string()
+    };
+    if overrides {
+        described.push_str(" (instead of the profile's)");
+    }
+    if markdown {
+        println!("| Filters     | {described} |");
+    } else {
+        println!("  Filters:      {}", described.dimmed());
+    }
+}
+
+/// List the mods inherited from parent profiles, which are not queried in detail
+pub fn inherited(inherited: &Inherited, markdown: bool) {
+    if inherited.is_empty() {
+        return;
+    }
+    let mods = inherited
+        .mods
+        .iter()
+        .map(|(origin, mod_)| (origin, &mod_.name))
+        .chain(
+            inherited
+                .external_mods
+                .iter()
+                .map(|(origin, mod_)| (origin, &mod_.name)),
+        );
+
+    if markdown {
+        println!("\n**Inherited Mods**\n\n| Mod | From |\n|-----|------|");
+        for (origin, name) in mods {
+            println!("| {name} | {origin} |");
+        }
+    } else {
+        println!("\n{}\n", "Inherited Mods".bold());
+        for (origin, name) in mods {
+            println!(
+                "  {:30}  {}",
+                name.bold(),
+                format!("from {origin}").dimmed()
+            );
+        }
+    }
+}
+
 pub fn curseforge(project: &Mod) {
     println!(
         "
@@ -278,6 +593,161 @@ pub fn github(repo: &Repository, releases: &[Release]) {
     );
 }
 
+pub fn gitlab(project: &gitlab::Project, releases: &[gitlab::Release]) {
+    println!(
+        "
+{}{}\n
+  Link:         {}
+  Source:       {}
+  Identifier:   {}
+  Open Source:  {}
+  Releases:     {}
+  Stars:        {}
+  Owner:        {}
+  Topics:       {}",
+        &project.name.bold(),
+        project
+            .description
+            .as_ref()
+            .filter(|description| !description.is_empty())
+            .map_or(String::new(), |description| {
+                format!("\n  {description}")
+            })
+            .italic(),
+        project.web_url.blue().underline(),
+        "GitLab Project".dimmed(),
+        project.path_with_namespace.dimmed(),
+        "Yes".green(),
+        releases.len().to_string().yellow(),
+        project.star_count.to_string().yellow(),
+        project.namespace.name.cyan(),
+        project.topics.iter().display(", ").to_string().magenta(),
+    );
+}
+
+pub fn forgejo(repo: &forgejo::Repository, releases: &[forgejo::Release]) {
+    // Calculate number of downloads
+    let mut downloads = 0;
+    for release in releases {
+        for asset in &release.assets {
+            downloads += asset.download_count;
+        }
+    }
+
+    println!(
+        "
+{}{}\n
+  Link:         {}
+  Source:       {}
+  Identifier:   {}
+  Open Source:  {}
+  Downloads:    {}
+  Stars:        {}
+  Owner:        {}
+  Topics:       {}",
+        &repo.name.bold(),
+        if repo.description.is_empty() {
+            String::new()
+        } else {
+            format!("\n  {}", repo.description)
+        }
+        .italic(),
+        repo.html_url.blue().underline(),
+        "Forgejo Repository".dimmed(),
+        repo.full_name.dimmed(),
+        "Yes".green(),
+        downloads.to_string().yellow(),
+        repo.stars_count.to_string().yellow(),
+        repo.owner.login.cyan(),
+        repo.topics.iter().display(", ").to_string().magenta(),
+    );
+}
+
+pub fn maven(id: &SourceIdentifier, versions: &[String]) {
+    let SourceIdentifier::MavenArtifact {
+        repository,
+        group,
+        artifact,
+        version_pattern,
+    } = id
+    else {
+        return;
+    };
+    println!(
+        "
+{}\n
+  Link:         {}
+  Source:       {}
+  Identifier:   {}
+  Versions:     {}
+  Latest:       {}{}",
+        artifact.bold(),
+        format!("{repository}/{}/{artifact}", group.replace('.', "/"))
+            .blue()
+            .underline(),
+        "Maven Artifact".dimmed(),
+        format!("{group}:{artifact}").dimmed(),
+        versions.len().to_string().yellow(),
+        versions.last().map_or("None", String::as_str).green(),
+        version_pattern
+            .as_ref()
+            .map_or(String::new(), |pattern| format!(
+                "\n  Pattern:      {}",
+                pattern.magenta()
+            )),
+    );
+}
+
+pub fn direct(id: &SourceIdentifier) {
+    let SourceIdentifier::DirectUrl { url, sha256 } = id else {
+        return;
+    };
+    println!(
+        "
+{}\n
+  Link:         {}
+  Source:       {}
+  SHA-256:      {}",
+        id.namespace_and_name().1.bold(),
+        url.blue().underline(),
+        "Direct URL".dimmed(),
+        sha256.dimmed(),
+    );
+}
+
+/// Whether the local file at `path` still exists and matches the hash it was added with
+fn local_status(path: &Path, sha256: &str) -> &'static str {
+    match hash_file(path) {
+        Ok(hash) if hash.eq_ignore_ascii_case(sha256) => "Unchanged",
+        Ok(_) => "Changed since added",
+        Err(_) => "Missing",
+    }
+}
+
+pub fn local(id: &SourceIdentifier) {
+    let SourceIdentifier::LocalFile { path, sha256 } = id else {
+        return;
+    };
+    let status = local_status(path, sha256);
+    println!(
+        "
+{}\n
+  Path:         {}
+  Source:       {}
+  SHA-256:      {}
+  Status:       {}",
+        id.namespace_and_name().1.bold(),
+        path.display().to_string().blue().underline(),
+        "Local File".dimmed(),
+        sha256.dimmed(),
+        if status == "Unchanged" {
+            status.green()
+        } else {
+            status.red()
+        },
+    );
+}
+
 pub fn curseforge_md(project: &Mod) {
     println!(
         "
@@ -370,3 +840,116 @@ pub fn github_md(repo: &Repository) {
         )),
     );
 }
+
+pub fn gitlab_md(project: &gitlab::Project) {
+    println!(
+        "
+**[{}]({})**{}
+
+|             |             |
+|-------------|-------------|
+| Source      | GitLab `{}` |
+| Open Source | Yes         |
+| Owner       | [{}]({})    |{}",
+        project.name,
+        project.web_url,
+        project
+            .description
+            .as_ref()
+            .filter(|description| !description.is_empty())
+            .map_or(String::new(), |description| {
+                format!("  \n_{}_", description.trim())
+            }),
+        project.path_with_namespace,
+        project.namespace.name,
+        project.namespace.web_url,
+        if project.topics.is_empty() {
+            String::new()
+        } else {
+            format!("\n| Topics | {} |", project.topics.iter().display(", "))
+        },
+    );
+}
+
+pub fn forgejo_md(repo: &forgejo::Repository) {
+    println!(
+        "
+**[{}]({})**{}
+
+|             |              |
+|-------------|--------------|
+| Source      | Forgejo `{}` |
+| Open Source | Yes          |
+| Owner       | {}           |{}",
+        repo.name,
+        repo.html_url,
+        if repo.description.is_empty() {
+            String::new()
+        } else {
+            format!("  \n_{}_", repo.description.trim())
+        },
+        repo.full_name,
+        repo.owner.login,
+        if repo.topics.is_empty() {
+            String::new()
+        } else {
+            format!("\n| Topics | {} |", repo.topics.iter().display(", "))
+        },
+    );
+}
+
+pub fn maven_md(id: &SourceIdentifier) {
+    let SourceIdentifier::MavenArtifact {
+        repository,
+        group,
+        artifact,
+        ..
+    } = id
+    else {
+        return;
+    };
+    println!(
+        "
+**[{artifact}]({repository}/{}/{artifact})**
+
+|             |            |
+|-------------|------------|
+| Source      | Maven `{group}:{artifact}` |",
+        group.replace('.', "/"),
+    );
+}
+
+pub fn direct_md(id: &SourceIdentifier) {
+    let SourceIdentifier::DirectUrl { url, sha256 } = id else {
+        return;
+    };
+    println!(
+        "
+**[{}]({url})**
+
+|             |            |
+|-------------|------------|
+| Source      | Direct URL |
+| SHA-256     | `{sha256}` |",
+        id.namespace_and_name().1,
+    );
+}
+
+pub fn local_md(id: &SourceIdentifier) {
+    let SourceIdentifier::LocalFile { path, sha256 } = id else {
+        return;
+    };
+    println!(
+        "
+**{}**
+
+|             |            |
+|-------------|------------|
+| Source      | Local File `{}` |
+| SHA-256     | `{sha256}` |
+| Status      | {}         |",
+        id.namespace_and_name().1,
+        path.display(),
+        local_status(path, sha256),
+    );
+}
@@ -0,0 +1,293 @@
+//! A table of the game versions and mod loaders each mod has files for, to help plan upgrades
+
+use crate::{
+    api,
+    config::Profile,
+    sources::{mentions_version, platform::curseforge_listing, words},
+    CROSS, NETWORK_PERMITS, TICK,
+};
+use anyhow::Result;
+use colored::Colorize as _;
+use ferinth::structures::tag::GameVersionType;
+use futures::{stream::FuturesUnordered, StreamExt as _};
+use libium::{
+    config::{
+        filters::ProfileParameters as _,
+        structs::{ModIdentifier, ModLoader},
+    },
+    iter_ext::IterExt as _,
+};
+use std::sync::Arc;
+
+/// How many of the latest releases are shown when no versions are provided
+const LATEST_RELEASES: usize = 3;
+
+#[derive(Clone, Copy)]
+pub enum Format {
+    Table,
+    Markdown,
+    Csv,
+}
+
+/// What a platform lists about the files of a mod
+enum Listing {
+    /// The game versions and mod loaders each file supports
+    Files(Vec<(Vec<String>, Vec<String>)>),
+    /// The names of the files, which are checked for the game version and mod loader
+    Names(Vec<String>),
+    /// The source does not list its files' versions
+    Unknown,
+}
+
+impl Listing {
+    /// Whether a file supports `version` with `loader`, or `None` if this is not certain
+    fn supports(&self, version: &str, loader: Option<&ModLoader>) -> Option<bool> {
+        match self {
+            Self::Files(files) => Some(files.iter().any(|(versions, loaders)| {
+                versions.iter().any(|v| v == version)
+                    && loader.map_or(true, |loader| {
+                        loaders.iter().any(|name| loader_matches(name, loader))
+                    })
+            })),
+            // Names only suggest which versions a file is for, so matches are not certain
+            Self::Names(names) => {
+                let matches = names.iter().any(|name| {
+                    let name = name.to_lowercase();
+                    mentions_version(&name, version)
+                        && loader.map_or(true, |loader| {
+                            // Whole words, so that NeoForge files are not mistaken for Forge ones
+                            words(&name).contains(&loader.to_string().to_lowercase().as_str())
+                        })
+                });
+                if matches {
+                    None
+                } else {
+                    Some(false)
+                }
+            }
+            Self::Unknown => None,
+        }
+    }
+}
+
+fn loader_matches(name: &str, loader: &ModLoader) -> bool {
+    // Quilt can load most Fabric mods
+    name.eq_ignore_ascii_case(&loader.to_string())
+        || (*loader == ModLoader::Quilt && name.eq_ignore_ascii_case("fabric"))
+}
+
+async fn listing(identifier: ModIdentifier) -> Result<Listing> {
+    let _permit = Arc::clone(&NETWORK_PERMITS).acquire_owned().await?;
+    Ok(match identifier {
+        ModIdentifier::ModrinthProject(id) => Listing::Files(
+            api::list_versions(&id)
+                .await?
+                .into_iter()
+                .map(|version| (version.game_versions, version.loaders))
+                .collect(),
+        ),
+        ModIdentifier::CurseForgeProject(id) => Listing::Files(
+            api::get_mod_files(id)
+                .await?
+                .into_iter()
+                .map(|file| curseforge_listing(file.game_versions))
+                .collect(),
+        ),
+        ModIdentifier::GitHubRepository((owner, repo)) => Listing::Names(
+            api::list_releases(&owner, &repo)
+                .await?
+                .into_iter()
+                .flat_map(|release| release.assets)
+                .map(|asset| asset.name)
+                .collect(),
+        ),
+    })
+}
+
+/// The profile's game versions, followed by the latest releases
+async fn default_versions(profile: &Profile) -> Result<Vec<String>> {
+    let mut versions = profile.filters.game_versions().cloned().unwrap_or_default();
+    let mut releases = api::list_game_versions()
+        .await?
+        .into_iter()
+        .filter(|version| version.version_type == GameVersionType::Release)
+        .collect_vec();
+    releases.sort_by(|a, b| b.date.cmp(&a.date));
+    let latest = releases
+        .into_iter()
+        .map(|release| release.version)
+        .filter(|version| !versions.contains(version))
+        .take(LATEST_RELEASES)
+        .collect_vec();
+    versions.extend(latest.into_iter().rev());
+    Ok(versions)
+}
+
+/// Show which of `versions` and `loaders` the mods of `profile` have files for
+///
+/// Defaults to the profile's game versions and the latest releases, with the profile's mod loader.
+pub async fn matrix(
+    profile: &Profile,
+    versions: Vec<String>,
+    loaders: Vec<ModLoader>,
+    format: Format,
+) -> Result<()> {
+    let versions = if versions.is_empty() {
+        default_versions(profile).await?
+    } else {
+        versions
+    };
+    let loaders = if loaders.is_empty() {
+        vec![profile.filters.mod_loader().cloned()]
+    } else {
+        loaders.into_iter().map(Some).collect_vec()
+    };
+    let columns = versions
+        .iter()
+        .flat_map(|version| loaders.iter().map(move |loader| (version, loader.as_ref())))
+        .collect_vec();
+
+    if let Format::Table = format {
+        eprint!("Querying version listings... ");
+    }
+    let mut tasks = profile
+        .mods
+        .iter()
+        .map(|mod_| {
+            let name = mod_.name.clone();
+            let identifier = mod_.identifier.clone();
+            async move { (name, listing(identifier).await) }
+        })
+        .collect::<FuturesUnordered<_>>();
+    let mut rows = profile
+        .external_mods
+        .iter()
+        .map(|mod_| (mod_.name.clone(), Listing::Unknown))
+        .collect_vec();
+    let mut failed = Vec::new();
+    while let Some((name, listing)) = tasks.next().await {
+        match listing {
+            Ok(listing) => rows.push((name, listing)),
+            Err(err) => {
+                failed.push(format!("{name}: {err}"));
+                rows.push((name, Listing::Unknown));
+            }
+        }
+    }
+    rows.sort_unstable_by_key(|(name, _)| name.to_lowercase());
+    if let Format::Table = format {
+        println!("{}", &*TICK);
+    }
+
+    let headers = columns
+        .iter()
+        .map(|(version, loader)| match loader {
+            Some(loader) if loaders.len() > 1 => format!("{version} {loader}"),
+            _ => (*version).clone(),
+        })
+        .collect_vec();
+    let cells = rows
+        .iter()
+        .map(|(name, listing)| {
+            let cells = columns
+                .iter()
+                .map(|(version, loader)| listing.supports(version, *loader))
+                .collect_vec();
+            (name, cells)
+        })
+        .collect_vec();
+
+    match format {
+        Format::Table => table(&headers, &cells),
+        Format::Markdown => markdown(&headers, &cells),
+        Format::Csv => csv(&headers, &cells),
+    }
+
+    for failure in failed {
+        eprintln!(
+            "{}",
+            format!("Could not get the versions of {failure}").yellow()
+        );
+    }
+    Ok(())
+}
+
+fn table(headers: &[String], rows: &[(&String, Vec<Option<bool>>)]) {
+    let name_width = rows
+        .iter()
+        .map(|(name, _)| name.chars().count())
+        .max()
+        .unwrap_or_default()
+        .max(3);
+    println!(
+        "\n{}  {}",
+        format!("{:name_width$}", "Mod").bold(),
+        headers.iter().map(|header| header.bold()).display("  ")
+    );
+    for (name, cells) in rows {
+        println!(
+            "{:name_width$}  {}",
+            name,
+            cells
+                .iter()
+                .zip(headers)
+                .map(|(cell, header)| {
+                    let width = header.len();
+                    match cell {
+                        Some(true) => format!("{:width$}", "✓").green(),
+                        Some(false) => format!("{CROSS:width$}").red(),
+                        None => format!("{:width$}", "?").dimmed(),
+                    }
+                })
+                .display("  ")
+        );
+    }
+}
+
+fn markdown(headers: &[String], rows: &[(&String, Vec<Option<bool>>)]) {
+    println!("| Mod | {} |", headers.iter().display(" | "));
+    println!("|-----|{}", headers.iter().map(|_| "---|").display(""));
+    for (name, cells) in rows {
+        println!(
+            "| {name} | {} |",
+            cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(true) => "✓",
+                    Some(false) => CROSS,
+                    None => "?",
+                })
+                .display(" | ")
+        );
+    }
+}
+
+/// Quote `field` if it contains characters that are special in CSV
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn csv(headers: &[String], rows: &[(&String, Vec<Option<bool>>)]) {
+    println!(
+        "mod,{}",
+        headers.iter().map(|header| csv_field(header)).display(",")
+    );
+    for (name, cells) in rows {
+        println!(
+            "{},{}",
+            csv_field(name),
+            cells
+                .iter()
+                .map(|cell| match cell {
+                    Some(true) => "yes",
+                    Some(false) => "no",
+                    None => "unknown",
+                })
+                .display(",")
+        );
+    }
+}
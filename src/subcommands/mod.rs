@@ -1,7 +1,19 @@
+pub mod config;
+mod disable;
+mod doctor;
+pub mod filters;
+pub mod group;
 pub mod list;
+pub mod mod_;
 pub mod modpack;
+pub mod old;
 pub mod profile;
 mod remove;
+mod status;
+pub mod tui;
 mod upgrade;
+pub use disable::{disable, enable, set_mod_disabled};
+pub use doctor::doctor;
 pub use remove::remove;
-pub use upgrade::upgrade;
+pub use status::status;
+pub use upgrade::{adopt, print_summary, update_local_hashes, upgrade, upgrade_many};
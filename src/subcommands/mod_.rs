@@ -0,0 +1,198 @@
+use super::filters::{
+    self, channel_name, check_patterns, describe, effective_filters, set_filter, CHANNELS,
+};
+use crate::{
+    cli::{FilterArguments, ModSubCommands},
+    config::{platform_id, ChannelFallback, Profile},
+};
+use anyhow::{bail, Result};
+use colored::Colorize as _;
+use inquire::{Confirm, Select};
+use libium::{
+    config::filters::{Filter, ReleaseChannel},
+    iter_ext::IterExt as _,
+};
+
+pub async fn subcommand(subcommand: ModSubCommands, profile: &mut Profile) -> Result<()> {
+    match subcommand {
+        ModSubCommands::Configure {
+            mod_name,
+            filters,
+            use_profile,
+            clear,
+            channel_fallback,
+            profile_fallback,
+        } => {
+            configure(
+                profile,
+                &mod_name,
+                filters,
+                use_profile,
+                clear,
+                channel_fallback
+                    .map(Some)
+                    .or(profile_fallback.then_some(None)),
+            )
+            .await?;
+        }
+    }
+    Ok(())
+}
+
+/// The index of the mod in `profile` called or identified by `query`
+fn find_mod(profile: &Profile, query: &str) -> Result<usize> {
+    if let Some(index) = profile.mods.iter().position(|mod_| {
+        mod_.name.eq_ignore_ascii_case(query)
+            || platform_id(&mod_.identifier).eq_ignore_ascii_case(query)
+    }) {
+        return Ok(index);
+    }
+    if let Some(mod_) = profile
+        .external_mods
+        .iter()
+        .find(|mod_| mod_.matches(query))
+    {
+        bail!(
+            "{} is not from Modrinth, CurseForge, or GitHub, so it does not have filters",
+            mod_.name
+        );
+    }
+    bail!("A mod with ID or name {query} is not present in this profile");
+}
+
+/// Change the filters of the mod called or identified by `query`,
+/// or pick which ones to change if no changes are given
+///
+/// Each given filter replaces the mod's filter of the same kind.
+/// `channel_fallback` is the release channel fallback to use instead of the profile's,
+/// or `Some(None)` to use the profile's again.
+#[expect(clippy::option_option)]
+pub async fn configure(
+    profile: &mut Profile,
+    query: &str,
+    filters: FilterArguments,
+    use_profile: bool,
+    clear: bool,
+    channel_fallback: Option<Option<ReleaseChannel>>,
+) -> Result<()> {
+    let index = find_mod(profile, query)?;
+    let override_profile = filters.override_profile;
+    let filters: Vec<Filter> = filters.into();
+    check_patterns(&filters)?;
+    let interactive = filters.is_empty()
+        && !override_profile
+        && !use_profile
+        && !clear
+        && channel_fallback.is_none();
+
+    if let Some(channel) = channel_fallback {
+        set_channel_fallback(profile, index, channel);
+    }
+
+    if interactive {
+        configure_interactively(profile, index).await?;
+    } else {
+        let mod_ = &mut profile.mods[index];
+        if clear {
+            mod_.filters.clear();
+        }
+        for filter in filters {
+            set_filter(&mut mod_.filters, filter);
+        }
+        if override_profile {
+            mod_.override_filters = true;
+        }
+        if use_profile {
+            mod_.override_filters = false;
+        }
+    }
+
+    let mod_ = &profile.mods[index];
+    println!(
+        "{} now uses {}",
+        mod_.name.bold(),
+        if mod_.override_filters {
+            "only its own filters"
+        } else {
+            "its own filters in place of the profile's of the same kind"
+        }
+    );
+    for filter in effective_filters(&profile.filters, mod_) {
+        println!("  {}", describe(&filter));
+    }
+    if let Some(channel) = profile.channel_fallback_of(&platform_id(&mod_.identifier)) {
+        println!("  falling back up to the {} channel", channel_name(channel));
+    }
+    Ok(())
+}
+
+/// Make the mod at `index` fall back up to `channel`, or like the rest of the profile if `None`
+fn set_channel_fallback(profile: &mut Profile, index: usize, channel: Option<ReleaseChannel>) {
+    let id = platform_id(&profile.mods[index].identifier);
+    profile
+        .mod_channel_fallbacks
+        .retain(|fallback| !fallback.id.eq_ignore_ascii_case(&id));
+    if let Some(channel) = channel {
+        profile
+            .mod_channel_fallbacks
+            .push(ChannelFallback { id, channel });
+    }
+}
+
+async fn configure_interactively(profile: &mut Profile, index: usize) -> Result<()> {
+    let items = vec![
+        "Its own filters",
+        "Override the profile's filters",
+        "Release channel fallback",
+        "Quit",
+    ];
+    println!(
+        "Configuring {}, which currently uses:",
+        profile.mods[index].name.bold()
+    );
+    for filter in effective_filters(&profile.filters, &profile.mods[index]) {
+        println!("  {}", describe(&filter));
+    }
+    println!();
+
+    while let Ok(selection) =
+        Select::new("What would you like to change", items.clone()).raw_prompt()
+    {
+        let mod_ = &mut profile.mods[index];
+        match selection.index {
+            0 => filters::edit(&mut mod_.filters).await?,
+            1 => {
+                if let Ok(override_filters) =
+                    Confirm::new("Should only the mod's own filters be used?")
+                        .with_default(mod_.override_filters)
+                        .prompt()
+                {
+                    mod_.override_filters = override_filters;
+                }
+            }
+            2 => {
+                let options = ["The same as the profile"]
+                    .into_iter()
+                    .chain(CHANNELS.iter().map(channel_name))
+                    .collect_vec();
+                if let Ok(selection) = Select::new(
+                    "Which is the least stable release channel to fall back to?",
+                    options,
+                )
+                .without_filtering()
+                .raw_prompt()
+                {
+                    let channel = selection
+                        .index
+                        .checked_sub(1)
+                        .map(|index| CHANNELS[index].clone());
+                    set_channel_fallback(profile, index, channel);
+                }
+            }
+            3 => break,
+            _ => unreachable!(),
+        }
+        println!();
+    }
+    Ok(())
+}
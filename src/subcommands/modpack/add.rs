@@ -1,16 +1,24 @@
 use super::check_output_directory;
-use crate::{file_picker::pick_folder, TICK};
-use anyhow::{Context as _, Result};
+use crate::{api, config::Config, file_picker::pick_folder, TICK};
+use anyhow::{ensure, Context as _, Result};
 use colored::Colorize as _;
+use ferinth::structures::project::ProjectType;
 use inquire::Confirm;
 use libium::{
-    config::structs::{Config, Modpack, ModpackIdentifier},
+    config::structs::{Modpack, ModpackIdentifier},
     get_minecraft_dir,
     iter_ext::IterExt as _,
-    modpack::add,
 };
 use std::path::PathBuf;
 
+fn check_not_added(config: &Config, identifier: &ModpackIdentifier, name: &str) -> Result<()> {
+    ensure!(
+        !config.has_modpack(identifier),
+        "{name} has already been added"
+    );
+    Ok(())
+}
+
 pub async fn curseforge(
     config: &mut Config,
     project_id: i32,
@@ -18,7 +26,18 @@ pub async fn curseforge(
     install_overrides: Option<bool>,
 ) -> Result<()> {
     eprint!("Checking modpack... ");
-    let project = add::curseforge(config, project_id).await?;
+    let project = api::get_mod(project_id).await?;
+    // The class of modpacks on CurseForge
+    ensure!(
+        project.class_id == Some(4471),
+        "{} is not a modpack",
+        project.name
+    );
+    check_not_added(
+        config,
+        &ModpackIdentifier::CurseForgeModpack(project.id),
+        &project.name,
+    )?;
     println!("{} ({})", *TICK, project.name);
     println!("Where should the modpack be installed to?");
     let output_dir = match output_dir {
@@ -64,7 +83,17 @@ pub async fn modrinth(
     install_overrides: Option<bool>,
 ) -> Result<()> {
     eprint!("Checking modpack... ");
-    let project = add::modrinth(config, project_id).await?;
+    let project = api::get_project(project_id).await?;
+    ensure!(
+        matches!(project.project_type, ProjectType::Modpack),
+        "{} is not a modpack",
+        project.title
+    );
+    check_not_added(
+        config,
+        &ModpackIdentifier::ModrinthModpack(project.id.clone()),
+        &project.title,
+    )?;
     println!("{} ({})", *TICK, project.title);
     println!("Where should the modpack be installed to?");
     let output_dir = match output_dir {
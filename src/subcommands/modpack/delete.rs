@@ -1,11 +1,9 @@
 use super::switch;
+use crate::config::Config;
 use anyhow::{Context as _, Result};
 use colored::Colorize as _;
 use inquire::Select;
-use libium::{
-    config::structs::{Config, ModpackIdentifier},
-    iter_ext::IterExt as _,
-};
+use libium::{config::structs::ModpackIdentifier, iter_ext::IterExt as _};
 use std::cmp::Ordering;
 
 pub fn delete(
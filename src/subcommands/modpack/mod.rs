@@ -8,15 +8,75 @@ pub use configure::configure;
 pub use delete::delete;
 pub use info::info;
 pub use switch::switch;
-pub use upgrade::upgrade;
+pub use upgrade::{upgrade, upgrade_many};
 
-use crate::file_picker::pick_folder;
+use crate::{
+    cli::ModpackSubCommands, config::Config, file_picker::pick_folder, get_active_modpack,
+};
 use anyhow::{ensure, Context as _, Result};
 use fs_extra::dir::{copy, CopyOptions};
 use inquire::Confirm;
 use libium::HOME;
 use std::{fs::read_dir, path::Path};
 
+pub async fn subcommand(subcommand: ModpackSubCommands, config: &mut Config) -> Result<()> {
+    match subcommand {
+        ModpackSubCommands::Add {
+            identifier,
+            output_dir,
+            install_overrides,
+        } => {
+            if let Ok(project_id) = identifier.parse() {
+                add::curseforge(config, project_id, output_dir, install_overrides).await?;
+            } else {
+                add::modrinth(config, &identifier, output_dir, install_overrides).await?;
+            }
+        }
+        ModpackSubCommands::Configure {
+            output_dir,
+            install_overrides,
+        } => configure(get_active_modpack(config)?, output_dir, install_overrides)?,
+        ModpackSubCommands::Delete {
+            modpack_name,
+            switch_to,
+        } => delete(config, modpack_name, switch_to)?,
+        ModpackSubCommands::Info => {
+            let active = config
+                .modpacks
+                .get(config.active_modpack)
+                .map(|modpack| modpack.name.clone());
+            let modpack = get_active_modpack(config)?;
+            info(modpack, active.as_ref() == Some(&modpack.name));
+        }
+        ModpackSubCommands::List => {
+            for (i, modpack) in config.modpacks.iter().enumerate() {
+                info(modpack, i == config.active_modpack);
+            }
+        }
+        ModpackSubCommands::Switch { modpack_name } => switch(config, modpack_name)?,
+        ModpackSubCommands::Upgrade { all, modpacks } => {
+            if all {
+                upgrade_many(&config.modpacks.iter().collect::<Vec<_>>()).await?;
+            } else if !modpacks.is_empty() {
+                let selected = modpacks
+                    .iter()
+                    .map(|name| {
+                        config
+                            .modpacks
+                            .iter()
+                            .find(|modpack| modpack.name.eq_ignore_ascii_case(name))
+                            .with_context(|| format!("The modpack {name} does not exist"))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                upgrade_many(&selected).await?;
+            } else {
+                upgrade(get_active_modpack(config)?).await?;
+            }
+        }
+    }
+    Ok(())
+}
+
 pub fn check_output_directory(output_dir: &Path) -> Result<()> {
     ensure!(
         output_dir.is_absolute(),
@@ -1,10 +1,8 @@
+use crate::config::Config;
 use anyhow::{anyhow, Result};
 use colored::Colorize as _;
 use inquire::Select;
-use libium::{
-    config::structs::{Config, ModpackIdentifier},
-    iter_ext::IterExt as _,
-};
+use libium::{config::structs::ModpackIdentifier, iter_ext::IterExt as _};
 
 pub fn switch(config: &mut Config, modpack_name: Option<String>) -> Result<()> {
     if config.modpacks.len() <= 1 {
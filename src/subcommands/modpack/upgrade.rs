@@ -1,11 +1,14 @@
 use crate::{
-    download::{clean, download, read_overrides},
-    STYLE_BYTE, TICK,
+    api,
+    config::{OldRetention, UntrackedFiles},
+    download::{clean, download, download_many, read_overrides, Batch},
+    sources::platform,
+    subcommands::print_summary,
+    TICK,
 };
 use anyhow::{Context as _, Result};
 use colored::Colorize as _;
 use futures::{stream::FuturesUnordered, StreamExt as _};
-use indicatif::ProgressBar;
 use libium::{
     config::structs::{Modpack, ModpackIdentifier},
     iter_ext::IterExt as _,
@@ -14,36 +17,43 @@ use libium::{
         read_file_from_zip, zip_extract,
     },
     upgrade::{from_modpack_file, try_from_cf_file, DistributionDeniedError, DownloadData},
-    CURSEFORGE_API, HOME,
+    HOME,
 };
 use std::{
     fs::File,
     io::BufReader,
     path::{Path, PathBuf},
-    time::Duration,
 };
 
 pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
+    let (batch, install_msg) = prepare(modpack).await?;
+    if !batch.is_empty() {
+        println!(
+            "\n{}\n",
+            format!("Downloading {} Mod Files", batch.to_download.len()).bold()
+        );
+        download(batch.output_dir, batch.to_download, batch.to_install).await?;
+    }
+    println!("\n{}", install_msg.bold());
+    Ok(())
+}
+
+/// Resolve the files of `modpack` and clean its output directory, leaving the files to download
+///
+/// Returns them along with the message explaining how to play the modpack.
+async fn prepare(modpack: &Modpack) -> Result<(Batch, String)> {
     let mut to_download: Vec<DownloadData> = Vec::new();
     let mut to_install = Vec::new();
     let install_msg;
 
-    let progress_bar = ProgressBar::new(0).with_style(STYLE_BYTE.clone());
-    let modpack_filepath = modpack
-        .identifier
-        .download_file(
-            |total| {
-                progress_bar.println("Downloading Modpack".bold().to_string());
-                progress_bar.enable_steady_tick(Duration::from_millis(100));
-                progress_bar.set_length(total as u64);
-            },
-            |additional| {
-                progress_bar.inc(additional as u64);
-            },
-        )
-        .await?;
+    let cache_dir = HOME.join(".config").join("ferium").join(".cache");
+    let modpack_download = platform::fetch_modpack_file(&modpack.identifier).await?;
+    let modpack_filepath = cache_dir.join(&modpack_download.output);
+    if !modpack_filepath.exists() {
+        println!("{}", "Downloading Modpack".bold());
+        download(cache_dir, vec![modpack_download], Vec::new()).await?;
+    }
     let modpack_file = File::open(&modpack_filepath)?;
-    progress_bar.finish_and_clear();
 
     match &modpack.identifier {
         ModpackIdentifier::CurseForgeModpack(_) => {
@@ -55,7 +65,7 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
             eprint!("\n{}", "Determining files to download... ".bold());
 
             let file_ids = manifest.files.iter().map(|file| file.file_id).collect();
-            let files = CURSEFORGE_API.get_files(file_ids).await?;
+            let files = api::get_files(file_ids).await?;
             println!("{} Fetched {} mods", &*TICK, files.len());
 
             let mut tasks = FuturesUnordered::new();
@@ -82,7 +92,7 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
                         }
                         msg_shown = true;
                         tasks.push(async move {
-                            let project = CURSEFORGE_API.get_mod(mod_id).await?;
+                            let project = api::get_mod(mod_id).await?;
                             eprintln!(
                                 "- {}
                            \r  {}",
@@ -91,7 +101,7 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
                                     .blue()
                                     .underline(),
                             );
-                            Ok::<(), furse::Error>(())
+                            Ok::<(), anyhow::Error>(())
                         });
                     }
                 }
@@ -156,24 +166,77 @@ pub async fn upgrade(modpack: &'_ Modpack) -> Result<()> {
         &modpack.output_dir.join("mods"),
         &mut to_download,
         &mut Vec::new(),
+        UntrackedFiles::Move,
+        false,
+        OldRetention::default(),
+        &[],
     )
     .await?;
     clean(
         &modpack.output_dir.join("resourcepacks"),
         &mut to_download,
         &mut Vec::new(),
+        UntrackedFiles::Move,
+        false,
+        OldRetention::default(),
+        &[],
     )
     .await?;
     // TODO: Check for `to_install` files that are already installed
-    if to_download.is_empty() && to_install.is_empty() {
+    let batch = Batch {
+        output_dir: modpack.output_dir.clone(),
+        to_download,
+        to_install,
+    };
+    if batch.is_empty() {
         println!("\n{}", "All up to date!".bold());
-    } else {
+    }
+    Ok((batch, install_msg))
+}
+
+/// Upgrade each of `modpacks`, then print a summary
+///
+/// Every modpack is resolved first, then all of their files are downloaded together,
+/// sharing the network permits. A modpack failing to upgrade does not stop the others from being upgraded.
+pub async fn upgrade_many(modpacks: &[&Modpack]) -> Result<()> {
+    let mut results = Vec::new();
+    let mut batches = Vec::new();
+    let mut pending = Vec::new();
+    for modpack in modpacks {
         println!(
             "\n{}\n",
-            format!("Downloading {} Mod Files", to_download.len()).bold()
+            format!("Resolving {}", modpack.name).bold().underline()
         );
-        download(modpack.output_dir.clone(), to_download, to_install).await?;
+        match prepare(modpack).await {
+            Ok((batch, install_msg)) => {
+                batches.push(batch);
+                pending.push((modpack, install_msg));
+            }
+            Err(err) => {
+                println!("{}", err.to_string().trim().red());
+                results.push((modpack.name.clone(), Err(err)));
+            }
+        }
     }
-    println!("\n{}", install_msg.bold());
-    Ok(())
+
+    let count = batches
+        .iter()
+        .map(|batch| batch.to_download.len())
+        .sum::<usize>();
+    if batches.iter().any(|batch| !batch.is_empty()) {
+        println!("\n{}\n", format!("Downloading {count} Mod Files").bold());
+    }
+    let downloaded = download_many(batches).await;
+
+    for ((modpack, install_msg), result) in pending.into_iter().zip(downloaded) {
+        match &result {
+            Ok(()) => println!("\n{}", format!("{}: {install_msg}", modpack.name).bold()),
+            Err(err) => println!(
+                "{}",
+                format!("{}: {}", modpack.name, err.to_string().trim()).red()
+            ),
+        }
+        results.push((modpack.name.clone(), result));
+    }
+    print_summary(&results)
 }
@@ -0,0 +1,184 @@
+use crate::{
+    backup::{self, Backup},
+    cli::OldSubCommands,
+    config::Profile,
+    download::mark_installed,
+    scan::read_metadata,
+};
+use anyhow::{bail, ensure, Result};
+use colored::Colorize as _;
+use fs_extra::file::{move_file, CopyOptions};
+use inquire::Select;
+use libium::iter_ext::IterExt as _;
+use std::{
+    fs::read_dir,
+    io::{stdin, IsTerminal as _},
+    path::{Path, PathBuf},
+};
+
+pub fn subcommand(subcommand: OldSubCommands, profile: &Profile) -> Result<()> {
+    match subcommand {
+        OldSubCommands::List => list(profile)?,
+        OldSubCommands::Restore { file, from } => {
+            restore(profile, file.as_deref(), from.as_deref())?;
+        }
+        OldSubCommands::Purge { all } => purge(profile, all)?,
+    }
+    Ok(())
+}
+
+fn list(profile: &Profile) -> Result<()> {
+    let backups = backup::list(&profile.output_dir)?;
+    if backups.is_empty() {
+        println!("Upgrading has not replaced any files in this profile yet");
+        return Ok(());
+    }
+    for backup in backups {
+        let files = backup.files()?;
+        println!(
+            "{} {}",
+            backup.name().bold(),
+            format!("({} files)", files.len()).dimmed()
+        );
+        for file in files {
+            println!("  {file}");
+        }
+    }
+    println!(
+        "\nThe files replaced by {} are kept",
+        profile.keep_old.unwrap_or_default()
+    );
+    Ok(())
+}
+
+/// Move the file called or containing `query` from the latest backup that has it,
+/// or from the backup called `from`, back into the output directory
+///
+/// The installed files of the same mod are moved into a new backup,
+/// and the restored file is replaced by the next upgrade like the rest of ferium's files.
+fn restore(profile: &Profile, query: Option<&str>, from: Option<&str>) -> Result<()> {
+    let directory = &profile.output_dir;
+    let mut backups = backup::list(directory)?;
+    if let Some(from) = from {
+        backups.retain(|backup| backup.name() == from);
+        ensure!(
+            !backups.is_empty(),
+            "There is no backup called {from}, use `ferium old list` to see them"
+        );
+    }
+
+    // The latest copy of every matching file
+    let mut candidates: Vec<(String, &Backup)> = Vec::new();
+    for backup in backups.iter().rev() {
+        for file in backup.files()? {
+            if query.map_or(true, |query| {
+                file.to_lowercase().contains(&query.to_lowercase())
+            }) && !candidates.iter().any(|(other, _)| *other == file)
+            {
+                candidates.push((file, backup));
+            }
+        }
+    }
+    if let Some(index) = candidates
+        .iter()
+        .position(|(file, _)| Some(file.as_str()) == query)
+    {
+        candidates = vec![candidates.swap_remove(index)];
+    }
+
+    let (file, backup) = match candidates.len() {
+        0 => match query {
+            Some(query) => bail!("No backup has a file called {query}"),
+            None => bail!("There are no backups to restore files from"),
+        },
+        1 => candidates.swap_remove(0),
+        _ if !stdin().is_terminal() => bail!(
+            "Several files in the backups match, pick one of them:\n  {}",
+            candidates.iter().map(|(file, _)| file).display("\n  ")
+        ),
+        _ => {
+            let Ok(selection) = Select::new(
+                "Which file would you like to restore?",
+                candidates
+                    .iter()
+                    .map(|(file, backup)| format!("{file} ({})", backup.name()))
+                    .collect_vec(),
+            )
+            .raw_prompt() else {
+                return Ok(());
+            };
+            candidates.swap_remove(selection.index)
+        }
+    };
+
+    let source = backup.path.join(&file);
+    let destination = directory.join(&file);
+    ensure!(
+        !destination.exists(),
+        "{file} is already in the output directory"
+    );
+    if let Ok(Some(metadata)) = read_metadata(&source) {
+        let replaced = Backup::other(directory, "restore");
+        for other in installed_jars(directory)? {
+            let same_mod =
+                matches!(read_metadata(&other), Ok(Some(other)) if other.id == metadata.id);
+            if same_mod {
+                replaced.store(&other)?;
+                if let Some(name) = other.file_name() {
+                    println!(
+                        "Moved {} to the backup {}",
+                        name.to_string_lossy().dimmed(),
+                        replaced.name()
+                    );
+                }
+            }
+        }
+    }
+    move_file(&source, &destination, &CopyOptions::new())?;
+    mark_installed(directory, &file)?;
+    if backup.time.is_some() && backup.files()?.is_empty() {
+        backup.remove()?;
+    }
+
+    println!("Restored {} from the backup {}", file.bold(), backup.name());
+    println!(
+        "{}",
+        "The next upgrade will replace it again, change the mod's filters to keep it".yellow()
+    );
+    Ok(())
+}
+
+/// The jar files in the output directory `directory`
+fn installed_jars(directory: &Path) -> Result<Vec<PathBuf>> {
+    Ok(read_dir(directory)?
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "jar"))
+        .collect())
+}
+
+/// Delete the backups that the profile does not keep anymore, or all of them if `all` is set
+fn purge(profile: &Profile, all: bool) -> Result<()> {
+    let removed = if all {
+        let backups = backup::list(&profile.output_dir)?;
+        for backup in &backups {
+            backup.remove()?;
+        }
+        backups
+    } else {
+        backup::prune(&profile.output_dir, profile.keep_old.unwrap_or_default())?
+    };
+
+    if removed.is_empty() {
+        println!("There are no backups to delete");
+    } else {
+        println!(
+            "Deleted the backups {}",
+            removed
+                .iter()
+                .map(|backup| backup.name().dimmed())
+                .display(", ")
+        );
+    }
+    Ok(())
+}
@@ -1,36 +1,59 @@
 use super::{check_output_directory, pick_minecraft_versions, pick_mod_loader};
-use crate::file_picker::pick_folder;
-use anyhow::{Context as _, Result};
-use inquire::{Select, Text};
-use libium::{
-    config::filters::ProfileParameters as _,
-    config::structs::{ModLoader, Profile},
+use crate::{
+    cli::{InheritanceArguments, ProfileFilterArguments},
+    config::{OldRetention, Profile, UntrackedFiles},
+    file_picker::pick_folder,
+    subcommands::filters,
+};
+use anyhow::Result;
+use inquire::{validator::Validation, CustomType, Select, Text};
+use libium::config::{
+    filters::{Filter, ProfileParameters as _, ReleaseChannel},
+    structs::ModLoader,
 };
 use std::path::PathBuf;
 
+#[expect(clippy::too_many_arguments)]
 pub async fn configure(
     profile: &mut Profile,
     game_versions: Vec<String>,
     mod_loaders: Vec<ModLoader>,
     name: Option<String>,
     output_dir: Option<PathBuf>,
+    inheritance: InheritanceArguments,
+    filter_arguments: ProfileFilterArguments,
+    channel_fallback: Option<ReleaseChannel>,
+    untracked_files: Option<UntrackedFiles>,
+    keep_old: Option<OldRetention>,
 ) -> Result<()> {
-    let mut interactive = true;
+    let mut interactive = !filters::apply(&mut profile.filters, filter_arguments)?;
 
     if !game_versions.is_empty() {
-        *profile
-            .filters
-            .game_versions_mut()
-            .context("Active profile does not filter by game version")? = game_versions;
-
+        match profile.filters.game_versions_mut() {
+            Some(versions) => *versions = game_versions,
+            None => profile
+                .filters
+                .push(Filter::GameVersionStrict(game_versions)),
+        }
         interactive = false;
     }
     if !mod_loaders.is_empty() {
-        *profile
-            .filters
-            .mod_loaders_mut()
-            .context("Active profile does not filter mod loader")? = mod_loaders;
-
+        match profile.filters.mod_loaders_mut() {
+            Some(loaders) => *loaders = mod_loaders,
+            None => profile.filters.push(Filter::ModLoaderPrefer(mod_loaders)),
+        }
+        interactive = false;
+    }
+    if let Some(channel) = channel_fallback {
+        set_channel_fallback(profile, channel);
+        interactive = false;
+    }
+    if let Some(untracked_files) = untracked_files {
+        profile.untracked_files = Some(untracked_files);
+        interactive = false;
+    }
+    if let Some(keep_old) = keep_old {
+        profile.keep_old = Some(keep_old);
         interactive = false;
     }
     if let Some(name) = name {
@@ -41,6 +64,33 @@ pub async fn configure(
         profile.output_dir = output_dir;
         interactive = false;
     }
+    if inheritance.clear_parents {
+        profile.parents.clear();
+        interactive = false;
+    }
+    if !inheritance.parents.is_empty() {
+        profile.parents = inheritance.parents;
+        interactive = false;
+    }
+    for excluded in inheritance.exclude {
+        if !profile
+            .exclude
+            .iter()
+            .any(|e| e.eq_ignore_ascii_case(&excluded))
+        {
+            profile.exclude.push(excluded);
+        }
+        interactive = false;
+    }
+    if !inheritance.include.is_empty() {
+        profile.exclude.retain(|excluded| {
+            !inheritance
+                .include
+                .iter()
+                .any(|included| included.eq_ignore_ascii_case(excluded))
+        });
+        interactive = false;
+    }
 
     if interactive {
         let items = vec![
@@ -52,6 +102,14 @@ pub async fn configure(
             "Mod loader",
             // Show a dialog to change name
             "Profile Name",
+            // Show a menu to add, change, remove and reorder any filter
+            "Other filters",
+            // Show a picker of the release channels to fall back to
+            "Release channel fallback",
+            // Show a picker of what to do with untracked files when not asked
+            "Untracked files",
+            // Show a picker of how long to keep the files that upgrading replaced
+            "Old file backups",
             // Quit the configuration
             "Quit",
         ];
@@ -103,7 +161,51 @@ pub async fn configure(
                         continue;
                     }
                 }
-                4 => break,
+                4 => filters::edit(&mut profile.filters).await?,
+                5 => {
+                    if let Ok(channel) = filters::pick_channel(
+                        "Which is the least stable release channel to fall back to?",
+                    ) {
+                        set_channel_fallback(profile, channel);
+                    }
+                }
+                6 => {
+                    let actions = [
+                        UntrackedFiles::Move,
+                        UntrackedFiles::Keep,
+                        UntrackedFiles::Adopt,
+                    ];
+                    if let Ok(selection) = Select::new(
+                        "What should upgrading without asking do with files ferium did not install?",
+                        vec![
+                            "Move them to the .old directory",
+                            "Keep them",
+                            "Add their mods to the profile",
+                        ],
+                    )
+                    .with_starting_cursor(
+                        actions
+                            .iter()
+                            .position(|action| {
+                                *action == profile.untracked_files.unwrap_or_default()
+                            })
+                            .unwrap_or_default(),
+                    )
+                    .raw_prompt()
+                    {
+                        profile.untracked_files = Some(actions[selection.index]);
+                    }
+                }
+                7 => {
+                    println!(
+                        "The files replaced by {} are currently kept",
+                        profile.keep_old.unwrap_or_default()
+                    );
+                    if let Some(keep_old) = pick_old_retention() {
+                        profile.keep_old = Some(keep_old);
+                    }
+                }
+                8 => break,
                 _ => unreachable!(),
             }
             println!();
@@ -112,3 +214,37 @@ pub async fn configure(
 
     Ok(())
 }
+
+/// Fall back to release channels up to `channel`, where the release channel disables falling back
+fn set_channel_fallback(profile: &mut Profile, channel: ReleaseChannel) {
+    profile.channel_fallback = (!matches!(channel, ReleaseChannel::Release)).then_some(channel);
+}
+
+/// Ask how long to keep the files that upgrading replaced, or `None` if cancelled
+fn pick_old_retention() -> Option<OldRetention> {
+    let selection = Select::new(
+        "How should the backups in the .old directory be kept?",
+        vec!["For a number of upgrades", "For a number of days"],
+    )
+    .without_filtering()
+    .raw_prompt()
+    .ok()?;
+    if selection.index == 1 {
+        CustomType::new("How many days should they be kept for?")
+            .prompt()
+            .ok()
+            .map(OldRetention::Days)
+    } else {
+        CustomType::new("How many of the latest upgrades should they be kept for?")
+            .with_validator(|count: &usize| {
+                Ok(if *count == 0 {
+                    Validation::Invalid("Keep the files of at least one upgrade".into())
+                } else {
+                    Validation::Valid
+                })
+            })
+            .prompt()
+            .ok()
+            .map(OldRetention::Upgrades)
+    }
+}
@@ -1,5 +1,10 @@
 use super::{check_output_directory, pick_minecraft_versions, pick_mod_loader};
-use crate::file_picker::pick_folder;
+use crate::{
+    cli::ProfileFilterArguments,
+    config::{Config, Profile},
+    file_picker::pick_folder,
+    subcommands::filters,
+};
 use anyhow::{bail, ensure, Context as _, Result};
 use colored::Colorize as _;
 use inquire::{
@@ -7,13 +12,13 @@ use inquire::{
     Confirm, Select, Text,
 };
 use libium::{
-    config::structs::{Config, ModLoader, Profile},
+    config::{filters::ReleaseChannel, structs::ModLoader},
     get_minecraft_dir,
     iter_ext::IterExt as _,
 };
 use std::path::PathBuf;
 
-#[expect(clippy::option_option)]
+#[expect(clippy::option_option, clippy::too_many_arguments)]
 pub async fn create(
     config: &mut Config,
     import: Option<Option<String>>,
@@ -21,7 +26,17 @@ pub async fn create(
     mod_loader: Option<ModLoader>,
     name: Option<String>,
     output_dir: Option<PathBuf>,
+    parents: Vec<String>,
+    filter_arguments: ProfileFilterArguments,
+    channel_fallback: Option<ReleaseChannel>,
 ) -> Result<()> {
+    for parent in &parents {
+        ensure!(
+            config.find_profile(parent).is_some(),
+            "The parent profile {parent} does not exist"
+        );
+    }
+
     let mut profile = match (game_versions, mod_loader, name, output_dir) {
         (Some(game_versions), Some(mod_loader), Some(name), output_dir) => {
             for profile in &config.profiles {
@@ -71,12 +86,20 @@ pub async fn create(
                 })
                 .prompt()?;
 
-            Profile::new(
+            let mut profile = Profile::new(
                 name,
                 selected_mods_dir,
                 pick_minecraft_versions(&[]).await?,
                 pick_mod_loader(None)?,
-            )
+            );
+            if Confirm::new("Would you like to set other filters, such as a release channel?")
+                .with_default(false)
+                .prompt()
+                .unwrap_or_default()
+            {
+                filters::edit(&mut profile.filters).await?;
+            }
+            profile
         }
         _ => {
             bail!("Provide the name, game version, mod loader, and output directory options to create a profile")
@@ -97,6 +120,9 @@ pub async fn create(
                 .position(|profile| profile.name.eq_ignore_ascii_case(&profile_name))
                 .context("The profile name provided does not exist")?;
             profile.mods.clone_from(&config.profiles[selection].mods);
+            profile
+                .external_mods
+                .clone_from(&config.profiles[selection].external_mods);
         } else {
             let profile_names = config
                 .profiles
@@ -111,10 +137,18 @@ pub async fn create(
                 profile
                     .mods
                     .clone_from(&config.profiles[selection.index].mods);
+                profile
+                    .external_mods
+                    .clone_from(&config.profiles[selection.index].external_mods);
             }
         };
     }
 
+    profile.parents = parents;
+    filters::apply(&mut profile.filters, filter_arguments)?;
+    profile.channel_fallback =
+        channel_fallback.filter(|channel| !matches!(channel, ReleaseChannel::Release));
+
     println!(
         "{}",
         "After adding your mods, remember to run `ferium upgrade` to download them!".yellow()
@@ -1,11 +1,9 @@
 use super::switch;
-use anyhow::{Context as _, Result};
+use crate::config::Config;
+use anyhow::{ensure, Context as _, Result};
 use colored::Colorize as _;
 use inquire::Select;
-use libium::{
-    config::{filters::ProfileParameters as _, structs::Config},
-    iter_ext::IterExt as _,
-};
+use libium::{config::filters::ProfileParameters as _, iter_ext::IterExt as _};
 use std::cmp::Ordering;
 
 pub fn delete(
@@ -40,7 +38,7 @@ pub fn delete(
                         .unwrap_or_default()
                         .green(),
                     profile.name.bold(),
-                    format!("({} mods)", profile.mods.len()).yellow(),
+                    format!("({} mods)", profile.mod_count()).yellow(),
                 )
             })
             .collect_vec();
@@ -54,6 +52,23 @@ pub fn delete(
             return Ok(());
         }
     };
+    let inheriting = config
+        .profiles
+        .iter()
+        .filter(|profile| {
+            profile
+                .parents
+                .iter()
+                .any(|parent| parent.eq_ignore_ascii_case(&config.profiles[selection].name))
+        })
+        .map(|profile| &profile.name)
+        .collect_vec();
+    ensure!(
+        inheriting.is_empty(),
+        "{} cannot be deleted because {} inherit mods from it",
+        config.profiles[selection].name,
+        inheriting.iter().display(", ")
+    );
     config.profiles.remove(selection);
 
     match config.active_profile.cmp(&selection) {
@@ -1,14 +1,12 @@
+use crate::{config::Profile, subcommands::filters::channel_name};
 use colored::Colorize;
-use libium::{
-    config::{filters::ProfileParameters as _, structs::Profile},
-    iter_ext::IterExt as _,
-};
+use libium::{config::filters::ProfileParameters as _, iter_ext::IterExt as _};
 
 pub fn info(profile: &Profile, active: bool) {
     println!(
         "{}{}
         \r  Output directory:   {}{}{}
-        \r  Mods:               {}\n",
+        \r  Mods:               {}{}{}{}{}\n",
         profile.name.bold(),
         if active { " *" } else { "" },
         profile.output_dir.display().to_string().blue().underline(),
@@ -28,6 +26,44 @@ pub fn info(profile: &Profile, active: bool) {
             .mod_loader()
             .map(|l| format!("\n  Mod Loader:         {}", l.to_string().purple()))
             .unwrap_or_default(),
-        profile.mods.len().to_string().yellow(),
+        profile.mod_count().to_string().yellow(),
+        if profile.parents.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n  Inherits from:      {}",
+                profile.parents.iter().map(|p| p.bold()).display(", ")
+            )
+        },
+        if profile.exclude.is_empty() {
+            String::new()
+        } else {
+            format!(
+                "\n  Excluded mods:      {}",
+                profile.exclude.iter().map(|e| e.dimmed()).display(", ")
+            )
+        },
+        profile
+            .channel_fallback
+            .as_ref()
+            .map(|channel| format!(
+                "\n  Falls back up to:   {} channel",
+                channel_name(channel).yellow()
+            ))
+            .unwrap_or_default(),
+        if profile.pending_count() == 0 {
+            String::new()
+        } else {
+            format!(
+                "\n  Waiting for:        {}",
+                profile
+                    .pending_mods
+                    .iter()
+                    .map(|mod_| &mod_.name)
+                    .chain(profile.pending_external_mods.iter().map(|mod_| &mod_.name))
+                    .map(|name| name.dimmed())
+                    .display(", ")
+            )
+        },
     );
 }
@@ -0,0 +1,311 @@
+use crate::{
+    cli::FilterKind,
+    config::{Config, Profile},
+    get_active_profile,
+    subcommands::{
+        filters::set_release_channel,
+        upgrade::{get_platform_downloadables, is_filter_error},
+    },
+    CROSS, TICK,
+};
+use anyhow::{ensure, Result};
+use colored::Colorize as _;
+use inquire::{Select, Text};
+use libium::{
+    config::{
+        filters::{Filter, ReleaseChannel},
+        structs::ModLoader,
+    },
+    iter_ext::IterExt as _,
+};
+use std::{
+    io::{stdin, IsTerminal as _},
+    mem::take,
+};
+
+/// What to do with the profile once the report has been shown
+enum Action {
+    /// Create a new profile with this name
+    Create(String),
+    /// Update the profile in place
+    Update,
+}
+
+/// Check which of the active profile's mods are available for the game version `to`
+/// (and `loader`, if provided), then create a new profile or update this one with the compatible mods
+///
+/// Nothing is changed until the report has been shown, nor at all if some mods could not be checked.
+/// Mods without a compatible release are kept as pending mods in the migrated profile,
+/// and are checked again the next time it is migrated.
+/// The game versions and mod loaders of the mods' own filters are changed along with the profile's.
+/// Inherited mods are left to the profiles they come from.
+pub async fn migrate(
+    config: &mut Config,
+    to: String,
+    loader: Option<ModLoader>,
+    create: Option<String>,
+    update: bool,
+) -> Result<()> {
+    let mut profile = get_active_profile(config)?.clone();
+    let filters = target_filters(&profile.filters, &to, loader.as_ref());
+    for mod_ in profile.mods.iter_mut().chain(&mut profile.pending_mods) {
+        mod_.filters = retarget(&mod_.filters, &to, loader.as_ref());
+    }
+
+    let mut candidates = profile.clone();
+    candidates.mods.extend(profile.pending_mods.iter().cloned());
+    candidates
+        .external_mods
+        .extend(profile.pending_external_mods.iter().cloned());
+    // Release channels are checked one at a time below, so they should not fall back
+    candidates.channel_fallback = None;
+    candidates.mod_channel_fallbacks.clear();
+    ensure!(
+        candidates.mod_count() > 0,
+        "Profile {} does not have any mods to migrate",
+        profile.name
+    );
+
+    let target = match &loader {
+        Some(loader) => format!("{to} ({loader})"),
+        None => to.clone(),
+    };
+    println!(
+        "{}\n",
+        format!("Checking the mods of {} against {target}", profile.name).bold()
+    );
+
+    candidates.filters = with_release_channel(&filters, ReleaseChannel::Release);
+    let (failed, mut unchecked) =
+        partition_failures(get_platform_downloadables(&candidates).await?.1);
+    let mut unavailable = Vec::new();
+    if !failed.is_empty() {
+        // Check whether the mods without a release have a beta or alpha instead
+        candidates.mods.retain(|mod_| failed.contains(&mod_.name));
+        candidates
+            .external_mods
+            .retain(|mod_| failed.contains(&mod_.name));
+        candidates.filters = with_release_channel(&filters, ReleaseChannel::Alpha);
+        for mod_ in &mut candidates.mods {
+            set_release_channel(&mut mod_.filters, &ReleaseChannel::Alpha);
+        }
+        println!();
+        let (still_failed, also_unchecked) =
+            partition_failures(get_platform_downloadables(&candidates).await?.1);
+        unavailable = still_failed;
+        unchecked.extend(also_unchecked);
+    }
+    let is_unchecked = |name: &str| unchecked.iter().any(|(unchecked, _)| unchecked == name);
+
+    let names = profile
+        .mods
+        .iter()
+        .chain(&profile.pending_mods)
+        .map(|mod_| &mod_.name)
+        .chain(
+            profile
+                .external_mods
+                .iter()
+                .chain(&profile.pending_external_mods)
+                .map(|mod_| &mod_.name),
+        )
+        .collect_vec();
+    let compatible = names
+        .iter()
+        .filter(|name| !failed.contains(**name) && !is_unchecked(name))
+        .collect_vec();
+    let prerelease = names
+        .iter()
+        .filter(|name| {
+            failed.contains(**name) && !unavailable.contains(**name) && !is_unchecked(name)
+        })
+        .collect_vec();
+
+    println!("\n{}", format!("Readiness for {target}").bold());
+    println!(
+        "{} {}",
+        &*TICK,
+        format!("Compatible now ({})", compatible.len()).green()
+    );
+    for name in &compatible {
+        println!("    {name}");
+    }
+    if !prerelease.is_empty() {
+        println!(
+            "{} {}",
+            "!".yellow(),
+            format!("Only beta or alpha versions ({})", prerelease.len()).yellow()
+        );
+        for name in &prerelease {
+            println!("    {name}");
+        }
+    }
+    if !unavailable.is_empty() {
+        println!(
+            "{}",
+            format!("{CROSS} Not available ({})", unavailable.len()).red()
+        );
+        for name in &unavailable {
+            println!("    {name}");
+        }
+    }
+    if !unchecked.is_empty() {
+        println!(
+            "{}",
+            format!("{CROSS} Could not be checked ({})", unchecked.len()).red()
+        );
+        for (name, err) in &unchecked {
+            println!("    {name}: {err}");
+        }
+    }
+    println!();
+    ensure!(
+        unchecked.is_empty(),
+        "Some mods could not be checked, so the profile was not changed"
+    );
+
+    let action = if let Some(name) = create {
+        Action::Create(name)
+    } else if update {
+        Action::Update
+    } else if stdin().is_terminal() {
+        match pick_action(&profile, &to)? {
+            Some(action) => action,
+            None => return Ok(()),
+        }
+    } else {
+        println!(
+            "Use `--create <NAME>` to create a new profile with the compatible mods, or `--update` to update this one"
+        );
+        return Ok(());
+    };
+
+    let migrated = migrated_profile(profile, filters, &failed);
+    let pending = migrated.pending_count();
+    match action {
+        Action::Create(name) => {
+            ensure!(
+                config.find_profile(&name).is_none(),
+                "A profile with name {name} already exists"
+            );
+            let mut migrated = migrated;
+            migrated.name.clone_from(&name);
+            config.profiles.push(migrated);
+            println!(
+                "{} Created profile {}, switch to it using `ferium profile switch`",
+                &*TICK,
+                name.bold()
+            );
+        }
+        Action::Update => {
+            *get_active_profile(config)? = migrated;
+            println!("{} Updated profile to {target}", &*TICK);
+        }
+    }
+    if pending > 0 {
+        println!(
+            "{pending} mod(s) will wait until they are available, run this again with the same version to check on them"
+        );
+    }
+    Ok(())
+}
+
+fn pick_action(profile: &Profile, to: &str) -> Result<Option<Action>> {
+    let options = vec![
+        "Create a new profile with the compatible mods",
+        "Update this profile",
+        "Do nothing",
+    ];
+    let selected = Select::new("What would you like to do?", options)
+        .without_filtering()
+        .raw_prompt()?;
+    Ok(match selected.index {
+        0 => Some(Action::Create(
+            Text::new("What should the new profile be called?")
+                .with_default(&format!("{} {to}", profile.name))
+                .prompt()?,
+        )),
+        1 => Some(Action::Update),
+        _ => None,
+    })
+}
+
+/// The names of the mods that failed to resolve because none of their files are compatible,
+/// and the mods that failed for other reasons along with their errors
+fn partition_failures(
+    failures: Vec<(String, anyhow::Error)>,
+) -> (Vec<String>, Vec<(String, anyhow::Error)>) {
+    let (unavailable, unchecked): (Vec<_>, _) = failures
+        .into_iter()
+        .partition(|(_, err)| is_filter_error(err));
+    (
+        unavailable.into_iter().map(|(name, _)| name).collect(),
+        unchecked,
+    )
+}
+
+/// `filters` with its game versions replaced by `to`, and its mod loaders by `loader` if provided,
+/// keeping the kind of each filter
+fn retarget(filters: &[Filter], to: &str, loader: Option<&ModLoader>) -> Vec<Filter> {
+    filters
+        .iter()
+        .map(|filter| match (filter, loader) {
+            (Filter::GameVersionStrict(_), _) => Filter::GameVersionStrict(vec![to.to_owned()]),
+            (Filter::GameVersionMinor(_), _) => Filter::GameVersionMinor(vec![to.to_owned()]),
+            (Filter::ModLoaderPrefer(_), Some(loader)) => {
+                Filter::ModLoaderPrefer(vec![loader.clone()])
+            }
+            (Filter::ModLoaderAny(_), Some(loader)) => Filter::ModLoaderAny(vec![loader.clone()]),
+            (filter, _) => filter.clone(),
+        })
+        .collect()
+}
+
+/// The profile's `filters` [retargeted](retarget) to `to` and `loader`,
+/// with the game version and mod loader added if it does not filter by them
+fn target_filters(filters: &[Filter], to: &str, loader: Option<&ModLoader>) -> Vec<Filter> {
+    let mut target = retarget(filters, to, loader);
+    let has_kind =
+        |target: &[Filter], kind| target.iter().any(|filter| FilterKind::of(filter) == kind);
+    if !has_kind(&target, FilterKind::GameVersion) {
+        target.push(Filter::GameVersionStrict(vec![to.to_owned()]));
+    }
+    if let Some(loader) = loader {
+        if !has_kind(&target, FilterKind::ModLoader) {
+            target.push(Filter::ModLoaderPrefer(vec![loader.clone()]));
+        }
+    }
+    target
+}
+
+/// `filters` with its release channel replaced by `channel`
+fn with_release_channel(filters: &[Filter], channel: ReleaseChannel) -> Vec<Filter> {
+    filters
+        .iter()
+        .filter(|filter| !matches!(filter, Filter::ReleaseChannel(_)))
+        .cloned()
+        .chain([Filter::ReleaseChannel(channel)])
+        .collect()
+}
+
+/// `profile` using `filters`, with the mods called one of `failed` moved to its pending mods
+fn migrated_profile(mut profile: Profile, filters: Vec<Filter>, failed: &[String]) -> Profile {
+    profile.filters = filters;
+
+    let mut mods = take(&mut profile.mods);
+    mods.append(&mut profile.pending_mods);
+    let (pending, mods) = mods
+        .into_iter()
+        .partition(|mod_| failed.contains(&mod_.name));
+    profile.mods = mods;
+    profile.pending_mods = pending;
+
+    let mut external_mods = take(&mut profile.external_mods);
+    external_mods.append(&mut profile.pending_external_mods);
+    let (pending, external_mods) = external_mods
+        .into_iter()
+        .partition(|mod_| failed.contains(&mod_.name));
+    profile.external_mods = external_mods;
+    profile.pending_external_mods = pending;
+    profile
+}
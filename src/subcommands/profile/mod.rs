@@ -2,17 +2,24 @@ mod configure;
 mod create;
 mod delete;
 mod info;
+mod migrate;
 mod switch;
 pub use configure::configure;
 pub use create::create;
 pub use delete::delete;
 pub use info::info;
+pub use migrate::migrate;
 pub use switch::switch;
 
-use crate::file_picker::pick_folder;
+use crate::{
+    api,
+    cli::ProfileSubCommands,
+    config::{Config, OldRetention},
+    file_picker::pick_folder,
+    get_active_profile,
+};
 use anyhow::{ensure, Context as _, Result};
 use colored::Colorize as _;
-use ferinth::Ferinth;
 use fs_extra::dir::{copy, CopyOptions};
 use inquire::{Confirm, MultiSelect, Select};
 use libium::{config::structs::ModLoader, iter_ext::IterExt as _, HOME};
@@ -21,6 +28,108 @@ use std::{
     path::PathBuf,
 };
 
+pub async fn subcommand(subcommand: ProfileSubCommands, config: &mut Config) -> Result<()> {
+    match subcommand {
+        ProfileSubCommands::Configure {
+            game_versions,
+            mod_loaders,
+            name,
+            output_dir,
+            inheritance,
+            filters,
+            channel_fallback,
+            untracked_files,
+            keep_old_upgrades,
+            keep_old_days,
+        } => {
+            let profile = get_active_profile(config)?;
+            let old_name = profile.name.clone();
+            configure(
+                profile,
+                game_versions,
+                mod_loaders,
+                name,
+                output_dir,
+                inheritance,
+                filters,
+                channel_fallback,
+                untracked_files,
+                keep_old_upgrades
+                    .map(OldRetention::Upgrades)
+                    .or(keep_old_days.map(OldRetention::Days)),
+            )
+            .await?;
+
+            // Keep the profiles that inherit from this one pointing to it
+            let profile = get_active_profile(config)?.clone();
+            if profile.name != old_name {
+                for parent in config
+                    .profiles
+                    .iter_mut()
+                    .flat_map(|profile| profile.parents.iter_mut())
+                    .filter(|parent| parent.eq_ignore_ascii_case(&old_name))
+                {
+                    parent.clone_from(&profile.name);
+                }
+            }
+            // Catch missing parents and circular inheritance before saving
+            config.inherited_mods(&profile)?;
+        }
+        ProfileSubCommands::Create {
+            import,
+            game_version,
+            mod_loader,
+            name,
+            output_dir,
+            parents,
+            filters,
+            channel_fallback,
+        } => {
+            create(
+                config,
+                import,
+                if game_version.is_empty() {
+                    None
+                } else {
+                    Some(game_version)
+                },
+                mod_loader,
+                name,
+                output_dir,
+                parents,
+                filters,
+                channel_fallback,
+            )
+            .await?;
+        }
+        ProfileSubCommands::Delete {
+            profile_name,
+            switch_to,
+        } => delete(config, profile_name, switch_to)?,
+        ProfileSubCommands::Info => {
+            let active = config
+                .profiles
+                .get(config.active_profile)
+                .map(|profile| profile.name.clone());
+            let profile = get_active_profile(config)?;
+            info(profile, active.as_ref() == Some(&profile.name));
+        }
+        ProfileSubCommands::List => {
+            for (i, profile) in config.profiles.iter().enumerate() {
+                info(profile, i == config.active_profile);
+            }
+        }
+        ProfileSubCommands::Migrate {
+            to,
+            loader,
+            create,
+            update,
+        } => migrate(config, to, loader, create, update).await?,
+        ProfileSubCommands::Switch { profile_name } => switch(config, profile_name)?,
+    }
+    Ok(())
+}
+
 #[expect(clippy::unwrap_used, reason = "All variants are present")]
 pub fn pick_mod_loader(default: Option<&ModLoader>) -> Result<ModLoader> {
     let options = vec![
@@ -39,7 +148,7 @@ pub fn pick_mod_loader(default: Option<&ModLoader>) -> Result<ModLoader> {
 }
 
 pub async fn pick_minecraft_versions(default: &[String]) -> Result<Vec<String>> {
-    let mut versions = Ferinth::default().list_game_versions().await?;
+    let mut versions = api::list_game_versions().await?;
     versions.sort_by(|a, b| {
         // Sort by release type (release > snapshot > beta > alpha) then in reverse chronological order
         a.version_type
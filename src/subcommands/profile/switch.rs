@@ -1,10 +1,8 @@
+use crate::config::Config;
 use anyhow::{anyhow, Result};
 use colored::Colorize as _;
 use inquire::Select;
-use libium::{
-    config::{filters::ProfileParameters as _, structs::Config},
-    iter_ext::IterExt as _,
-};
+use libium::{config::filters::ProfileParameters as _, iter_ext::IterExt as _};
 
 pub fn switch(config: &mut Config, profile_name: Option<String>) -> Result<()> {
     if config.profiles.len() <= 1 {
@@ -39,7 +37,7 @@ pub fn switch(config: &mut Config, profile_name: Option<String>) -> Result<()> {
                         .map(|v| v[0].green())
                         .unwrap_or_default(),
                     profile.name.bold(),
-                    format!("({} mods)", profile.mods.len()).yellow(),
+                    format!("({} mods)", profile.mod_count()).yellow(),
                 )
             })
             .collect_vec();
@@ -1,10 +1,17 @@
+use crate::config::{platform_id, Profile};
 use anyhow::{bail, Result};
 use colored::Colorize as _;
 use inquire::MultiSelect;
-use libium::{
-    config::structs::{ModIdentifier, Profile},
-    iter_ext::IterExt as _,
-};
+use libium::{config::structs::ModIdentifier, iter_ext::IterExt as _};
+
+/// Show a disabled mod dimmed in the picker
+fn dim_if_disabled(info: String, disabled: bool) -> String {
+    if disabled {
+        format!("{info} (disabled)").dimmed().to_string()
+    } else {
+        info
+    }
+}
 
 /// If `to_remove` is empty, display a list of projects in the profile to select from and remove selected ones
 ///
@@ -15,7 +22,7 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
             .mods
             .iter()
             .map(|mod_| {
-                format!(
+                let info = format!(
                     "{:11}  {}",
                     match &mod_.identifier {
                         ModIdentifier::CurseForgeProject(id) => format!("CF {:8}", id.to_string()),
@@ -27,8 +34,17 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
                             mod_.name.clone(),
                         ModIdentifier::GitHubRepository(id) => format!("{}/{}", id.0, id.1),
                     },
-                )
+                );
+                dim_if_disabled(info, profile.is_mod_disabled(mod_))
             })
+            .chain(profile.external_mods.iter().map(|mod_| {
+                let info = format!(
+                    "{:11}  {}",
+                    mod_.identifier.tag(),
+                    mod_.identifier.display_id()
+                );
+                dim_if_disabled(info, profile.is_external_mod_disabled(mod_))
+            }))
             .collect_vec();
         MultiSelect::new("Select mods to remove", mod_info.clone())
             .raw_prompt_skippable()?
@@ -50,6 +66,13 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
                     }
             }) {
                 items_to_remove.push(index);
+            } else if let Some(index) = profile
+                .external_mods
+                .iter()
+                .position(|mod_| mod_.matches(&to_remove))
+            {
+                // External mods are indexed after the platform mods
+                items_to_remove.push(profile.mods.len() + index);
             } else {
                 bail!("A mod with ID or name {to_remove} is not present in this profile");
             }
@@ -62,8 +85,17 @@ pub fn remove(profile: &mut Profile, to_remove: Vec<String>) -> Result<()> {
     indices_to_remove.reverse();
 
     let mut removed = Vec::new();
+    let mods_len = profile.mods.len();
     for index in indices_to_remove {
-        removed.push(profile.mods.swap_remove(index).name);
+        let (id, name) = if index >= mods_len {
+            let mod_ = profile.external_mods.swap_remove(index - mods_len);
+            (mod_.identifier.display_id(), mod_.name)
+        } else {
+            let mod_ = profile.mods.swap_remove(index);
+            (platform_id(&mod_.identifier), mod_.name)
+        };
+        profile.forget_mod(&id);
+        removed.push(name);
     }
 
     if !removed.is_empty() {
@@ -0,0 +1,71 @@
+use crate::{
+    api,
+    rate_limit::{Platform, Quota},
+    sources::{fetch_quota, DEFAULT_FORGEJO_URL, DEFAULT_GITLAB_URL},
+};
+use anyhow::Result;
+use colored::Colorize as _;
+use std::time::Duration;
+
+/// Display the remaining request quota of each platform
+pub async fn status() -> Result<()> {
+    let (modrinth, github, gitlab, forgejo) = futures::join!(
+        fetch_quota(&format!("{}/v2/tag/loader", api::modrinth_url())),
+        api::github_quota(),
+        fetch_quota(&format!(
+            "{}/api/v4/projects?per_page=1",
+            *DEFAULT_GITLAB_URL
+        )),
+        fetch_quota(&format!("{}/api/v1/version", *DEFAULT_FORGEJO_URL)),
+    );
+
+    println!("{}\n", "Rate Limits".bold());
+    print_quota(Platform::Modrinth, modrinth);
+    print_quota(Platform::CurseForge, Ok(Quota::default()));
+    let github_unauthenticated = github
+        .as_ref()
+        .is_ok_and(|quota| quota.limit.is_some_and(|limit| limit <= 60));
+    print_quota(Platform::GitHub, github);
+    print_quota(Platform::GitLab, gitlab);
+    print_quota(Platform::Forgejo, forgejo);
+
+    if github_unauthenticated {
+        println!(
+            "\n{}",
+            "Provide a GitHub personal access token using `--github-token` or `GITHUB_TOKEN` to increase GitHub's rate limit"
+                .yellow()
+        );
+    }
+    Ok(())
+}
+
+fn print_quota(platform: Platform, quota: Result<Quota>) {
+    let platform = format!("{platform:12}");
+    match quota {
+        Ok(quota) if quota.is_reported() => println!(
+            "{} {}/{} requests left{}",
+            platform.bold(),
+            quota
+                .remaining
+                .map_or("?".to_owned(), |n| n.to_string())
+                .green(),
+            quota.limit.map_or("?".to_owned(), |n| n.to_string()),
+            quota.reset.map_or(String::new(), |reset| format!(
+                ", resets in {}",
+                format_duration(reset).cyan()
+            )),
+        ),
+        Ok(_) => println!("{} {}", platform.bold(), "Not reported".dimmed()),
+        Err(err) => println!("{} {}", platform.bold(), err.to_string().red()),
+    }
+}
+
+/// Format `duration` like `1h 2m 3s`
+fn format_duration(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match (seconds / 3600, seconds / 60 % 60, seconds % 60) {
+        (0, 0, s) => format!("{s}s"),
+        (0, m, s) => format!("{m}m {s}s"),
+        (h, m, s) => format!("{h}h {m}m {s}s"),
+    }
+}
@@ -0,0 +1,793 @@
+//! A full-screen interface for browsing and managing profiles with the keyboard
+//!
+//! It draws in the terminal's alternate screen, so it works over SSH without a graphical session.
+
+mod ui;
+
+use crate::{
+    add,
+    api::{self, SearchHit},
+    config::{platform_id, Config, Profile},
+    download, sources,
+    subcommands::{
+        list::{self, Metadata},
+        set_mod_disabled,
+    },
+};
+use anyhow::{ensure, Result};
+use colored::Colorize as _;
+use futures::{channel::mpsc, StreamExt as _};
+use libium::{
+    config::{filters::ProfileParameters as _, structs::ModIdentifier},
+    iter_ext::IterExt as _,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    widgets::ListState,
+    DefaultTerminal,
+};
+use std::{
+    io::{stdin, stdout, IsTerminal as _},
+    mem::replace,
+    pin::pin,
+    time::Duration,
+};
+use tokio::time::sleep;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Profiles,
+    Mods,
+}
+
+/// What the keyboard is currently being used for
+enum Mode {
+    Browse,
+    /// Typing a query to search Modrinth with
+    Search(String),
+    /// Typing the identifier of or a link to a mod to add
+    Add(String),
+    /// Picking one of the search results to add
+    Results(Vec<SearchHit>, ListState),
+    /// Confirming the removal of the selected mod
+    ConfirmRemove,
+    /// Upgrading the selected profile, or looking at how its upgrade went
+    Upgrade(Upgrade),
+}
+
+/// Work that a key started, which is done outside of [`App::handle_key`]
+/// because it needs the network or the terminal
+#[derive(Debug, PartialEq, Eq)]
+enum Action {
+    QueryMetadata,
+    Search(String),
+    Add(String),
+    Upgrade,
+}
+
+/// The progress of an upgrade, built from the [events](download::Event) it sends
+struct Upgrade {
+    name: String,
+    files: Vec<FileProgress>,
+    /// The messages that `ferium upgrade` would have printed
+    log: Vec<String>,
+    /// How the upgrade went, once it is done
+    outcome: Option<String>,
+}
+
+struct FileProgress {
+    filename: String,
+    length: u64,
+    downloaded: u64,
+    done: bool,
+    error: Option<String>,
+}
+
+impl Upgrade {
+    fn new(name: String) -> Self {
+        Self {
+            name,
+            files: Vec::new(),
+            log: Vec::new(),
+            outcome: None,
+        }
+    }
+
+    fn file(&mut self, filename: &str) -> Option<&mut FileProgress> {
+        self.files.iter_mut().find(|file| file.filename == filename)
+    }
+
+    fn apply(&mut self, event: download::Event) {
+        match event {
+            download::Event::Message(message) => self.log.extend(
+                message
+                    .trim_end()
+                    .lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(ToOwned::to_owned),
+            ),
+            download::Event::Queued(filename, length) => self.files.push(FileProgress {
+                filename,
+                length,
+                downloaded: 0,
+                done: false,
+                error: None,
+            }),
+            download::Event::Downloaded(filename, downloaded) => {
+                if let Some(file) = self.file(&filename) {
+                    file.downloaded = downloaded;
+                }
+            }
+            download::Event::Finished(filename, error) => {
+                if let Some(file) = self.file(&filename) {
+                    file.done = true;
+                    file.error = error;
+                }
+            }
+        }
+    }
+}
+
+/// A mod of the selected profile, as shown in the mods pane
+struct Row {
+    id: String,
+    name: String,
+    source: &'static str,
+    disabled: bool,
+    /// The name of the group the mod is in, and whether it is enabled
+    group: Option<(String, bool)>,
+}
+
+fn rows(profile: &Profile) -> Vec<Row> {
+    let mut rows = profile
+        .mods
+        .iter()
+        .map(|mod_| {
+            let source = match mod_.identifier {
+                ModIdentifier::CurseForgeProject(_) => "CF",
+                ModIdentifier::ModrinthProject(_) => "MR",
+                ModIdentifier::GitHubRepository(_) => "GH",
+            };
+            (platform_id(&mod_.identifier), &mod_.name, source)
+        })
+        .chain(profile.external_mods.iter().map(|mod_| {
+            (
+                mod_.identifier.display_id(),
+                &mod_.name,
+                mod_.identifier.tag(),
+            )
+        }))
+        .map(|(id, name, source)| Row {
+            disabled: profile.is_disabled(&id),
+            group: profile
+                .group_of(&id)
+                .map(|group| (group.name.clone(), group.enabled)),
+            name: name.clone(),
+            id,
+            source,
+        })
+        .collect_vec();
+    rows.sort_unstable_by_key(|row| row.name.to_lowercase());
+    rows
+}
+
+/// Move the selection of `state` by one, staying within `len` items
+fn step(state: &mut ListState, len: usize, forward: bool) {
+    let selected = state.selected().unwrap_or_default();
+    state.select(Some(if forward {
+        (selected + 1).min(len.saturating_sub(1))
+    } else {
+        selected.saturating_sub(1)
+    }));
+}
+
+/// Apply `key` to the text being typed
+fn edit(text: &mut String, key: KeyCode) {
+    match key {
+        KeyCode::Char(c) => text.push(c),
+        KeyCode::Backspace => {
+            text.pop();
+        }
+        _ => {}
+    }
+}
+
+struct App<'a> {
+    config: &'a mut Config,
+    focus: Focus,
+    mode: Mode,
+    profiles: ListState,
+    mods: ListState,
+    /// The metadata of the mods of a profile, with the index of that profile
+    metadata: Option<(usize, Vec<Metadata>)>,
+    status: String,
+    quit: bool,
+}
+
+/// Open the interface, starting at the active profile
+///
+/// Changes are made to `config` as they happen, and saved when the interface is closed.
+pub async fn tui(config: &mut Config) -> Result<()> {
+    ensure!(
+        stdin().is_terminal() && stdout().is_terminal(),
+        "`ferium tui` needs an interactive terminal"
+    );
+    ensure!(
+        !config.profiles.is_empty(),
+        "There are no profiles to manage, create one using `ferium profile create`"
+    );
+    let active = config.active_profile.min(config.profiles.len() - 1);
+
+    let mut app = App {
+        config,
+        focus: Focus::Mods,
+        mode: Mode::Browse,
+        profiles: ListState::default().with_selected(Some(active)),
+        mods: ListState::default().with_selected(Some(0)),
+        metadata: None,
+        status: String::new(),
+        quit: false,
+    };
+    let mut terminal = ratatui::try_init()?;
+    // The messages of upgrades are shown in the interface, where escape codes would be drawn as text
+    colored::control::set_override(false);
+    let result = app.run(&mut terminal).await;
+    colored::control::unset_override();
+    ratatui::restore();
+    result
+}
+
+impl App<'_> {
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        while !self.quit {
+            terminal.draw(|frame| ui::draw(frame, self))?;
+            if let Event::Key(key) = event::read()? {
+                // Windows also reports key releases
+                if key.kind == KeyEventKind::Press {
+                    if let Some(action) = self.handle_key(key.code) {
+                        self.perform(action, terminal).await?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn profile(&self) -> Option<&Profile> {
+        self.config.profiles.get(self.profiles.selected()?)
+    }
+
+    fn rows(&self) -> Vec<Row> {
+        self.profile().map_or_else(Vec::new, rows)
+    }
+
+    fn selected_row(&self) -> Option<Row> {
+        self.rows().into_iter().nth(self.mods.selected()?)
+    }
+
+    /// The metadata of the mod identified by `id`, if it has been queried
+    fn metadata_of(&self, id: &str) -> Option<&Metadata> {
+        let (index, metadata) = self.metadata.as_ref()?;
+        if Some(*index) != self.profiles.selected() {
+            return None;
+        }
+        metadata
+            .iter()
+            .find(|metadata| metadata.id().eq_ignore_ascii_case(id))
+    }
+
+    /// Show `message` while work that blocks the interface is done
+    fn busy(&mut self, terminal: &mut DefaultTerminal, message: String) -> Result<()> {
+        self.status = message;
+        terminal.draw(|frame| ui::draw(frame, self))?;
+        Ok(())
+    }
+
+    /// Apply `key` to the state of the interface, returning the work it started
+    fn handle_key(&mut self, key: KeyCode) -> Option<Action> {
+        match replace(&mut self.mode, Mode::Browse) {
+            Mode::Browse => return self.browse(key),
+            Mode::Search(mut query) => match key {
+                KeyCode::Esc => {}
+                KeyCode::Enter if !query.trim().is_empty() => {
+                    return Some(Action::Search(query.trim().to_owned()));
+                }
+                key => {
+                    edit(&mut query, key);
+                    self.mode = Mode::Search(query);
+                }
+            },
+            Mode::Add(mut id) => match key {
+                KeyCode::Esc => {}
+                KeyCode::Enter if !id.trim().is_empty() => {
+                    return Some(Action::Add(id.trim().to_owned()));
+                }
+                key => {
+                    edit(&mut id, key);
+                    self.mode = Mode::Add(id);
+                }
+            },
+            Mode::Results(hits, mut state) => match key {
+                KeyCode::Esc => {}
+                KeyCode::Enter => {
+                    return state
+                        .selected()
+                        .and_then(|index| hits.get(index))
+                        .map(|hit| Action::Add(hit.project_id.clone()));
+                }
+                key => {
+                    match key {
+                        KeyCode::Up | KeyCode::Char('k') => step(&mut state, hits.len(), false),
+                        KeyCode::Down | KeyCode::Char('j') => step(&mut state, hits.len(), true),
+                        _ => {}
+                    }
+                    self.mode = Mode::Results(hits, state);
+                }
+            },
+            Mode::ConfirmRemove => {
+                if matches!(key, KeyCode::Char('y' | 'Y')) {
+                    self.remove();
+                } else {
+                    self.status = "Cancelled".into();
+                }
+            }
+            Mode::Upgrade(upgrade) => {
+                if !matches!(key, KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q')) {
+                    self.mode = Mode::Upgrade(upgrade);
+                }
+            }
+        }
+        None
+    }
+
+    async fn perform(&mut self, action: Action, terminal: &mut DefaultTerminal) -> Result<()> {
+        match action {
+            Action::QueryMetadata => self.query_metadata(terminal).await,
+            Action::Search(query) => self.search(&query, terminal).await,
+            Action::Add(id) => self.add(&id, terminal).await,
+            Action::Upgrade => self.upgrade(terminal).await,
+        }
+    }
+
+    fn browse(&mut self, key: KeyCode) -> Option<Action> {
+        let on_mods = self.focus == Focus::Mods;
+        match key {
+            KeyCode::Char('q') | KeyCode::Esc => self.quit = true,
+            KeyCode::Tab
+            | KeyCode::BackTab
+            | KeyCode::Left
+            | KeyCode::Right
+            | KeyCode::Char('h' | 'l') => {
+                self.focus = match self.focus {
+                    Focus::Profiles => Focus::Mods,
+                    Focus::Mods => Focus::Profiles,
+                };
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(false),
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(true),
+            KeyCode::Enter if !on_mods => self.switch_profile(),
+            KeyCode::Enter | KeyCode::Char('i') => return Some(Action::QueryMetadata),
+            KeyCode::Char('/' | 's') => self.mode = Mode::Search(String::new()),
+            KeyCode::Char('a') => self.mode = Mode::Add(String::new()),
+            KeyCode::Char(' ' | 'd' | 'x') | KeyCode::Delete if !on_mods => {
+                self.status = "Press tab to select a mod in the mods pane first".into();
+            }
+            KeyCode::Char(' ' | 'd') => self.toggle(),
+            KeyCode::Char('x') | KeyCode::Delete => {
+                if self.selected_row().is_some() {
+                    self.mode = Mode::ConfirmRemove;
+                }
+            }
+            KeyCode::Char('u') => return Some(Action::Upgrade),
+            _ => {}
+        }
+        None
+    }
+
+    fn move_selection(&mut self, forward: bool) {
+        match self.focus {
+            Focus::Profiles => {
+                step(&mut self.profiles, self.config.profiles.len(), forward);
+                self.mods.select(Some(0));
+            }
+            Focus::Mods => {
+                let len = self.rows().len();
+                step(&mut self.mods, len, forward);
+            }
+        }
+    }
+
+    fn switch_profile(&mut self) {
+        if let Some(index) = self.profiles.selected() {
+            self.config.active_profile = index;
+            self.status = format!("Switched to {}", self.config.profiles[index].name);
+        }
+    }
+
+    async fn query_metadata(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(index) = self.profiles.selected() else {
+            return Ok(());
+        };
+        self.busy(terminal, "Querying metadata...".into())?;
+        self.status = match list::fetch_metadata(&self.config.profiles[index]).await {
+            Ok(metadata) => {
+                self.metadata = Some((index, metadata));
+                "Queried the details of every mod".into()
+            }
+            Err(err) => format!("Could not query metadata: {err}"),
+        };
+        Ok(())
+    }
+
+    async fn search(&mut self, query: &str, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(profile) = self.profile() else {
+            return Ok(());
+        };
+        let versions = profile.filters.game_versions().cloned().unwrap_or_default();
+        let loader = profile.filters.mod_loader().map(ToString::to_string);
+        self.busy(terminal, format!("Searching Modrinth for {query}..."))?;
+        match api::search_mods(query, &versions, loader.as_deref()).await {
+            Ok(hits) if hits.is_empty() => self.status = format!("No mods found for {query}"),
+            Ok(hits) => {
+                self.status = format!("{} results for {query}", hits.len());
+                self.mode = Mode::Results(hits, ListState::default().with_selected(Some(0)));
+            }
+            Err(err) => self.status = err.to_string(),
+        }
+        Ok(())
+    }
+
+    async fn add(&mut self, id: &str, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(index) = self.profiles.selected() else {
+            return Ok(());
+        };
+        self.busy(terminal, format!("Adding {id}..."))?;
+        self.status = match add_mods(&mut self.config.profiles[index], id).await {
+            Ok(message) => message,
+            Err(err) => format!("Could not add {id}: {err}"),
+        };
+        self.metadata = None;
+        Ok(())
+    }
+
+    fn toggle(&mut self) {
+        let (Some(index), Some(row)) = (self.profiles.selected(), self.selected_row()) else {
+            return;
+        };
+        let disable = !row.disabled;
+        let done = if disable { "Disabled" } else { "Enabled" };
+        self.status = match set_mod_disabled(&mut self.config.profiles[index], &row.id, disable) {
+            Ok(()) => format!("{done} {}", row.name),
+            Err(err) => format!(
+                "{done} {}, but could not rename its installed file: {err}",
+                row.name
+            ),
+        };
+    }
+
+    fn remove(&mut self) {
+        let (Some(index), Some(row)) = (self.profiles.selected(), self.selected_row()) else {
+            return;
+        };
+        let profile = &mut self.config.profiles[index];
+        profile
+            .mods
+            .retain(|mod_| platform_id(&mod_.identifier) != row.id);
+        profile
+            .external_mods
+            .retain(|mod_| mod_.identifier.display_id() != row.id);
+        profile.forget_mod(&row.id);
+        self.status = format!("Removed {}", row.name);
+
+        let len = self.rows().len();
+        step(&mut self.mods, len, false);
+        step(&mut self.mods, len, true);
+    }
+
+    /// Upgrade the selected profile, showing the progress of each file in the interface
+    async fn upgrade(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        let Some(profile) = self.profile() else {
+            return Ok(());
+        };
+        let name = profile.name.clone();
+        let merged = match self.config.merged(profile) {
+            Ok(merged) if merged.mod_count() == 0 => {
+                self.status = format!("{name} does not have any mods");
+                return Ok(());
+            }
+            Ok(merged) => merged,
+            Err(err) => {
+                self.status = err.to_string();
+                return Ok(());
+            }
+        };
+
+        self.mode = Mode::Upgrade(Upgrade::new(name.clone()));
+        self.status = format!("Upgrading {name}...");
+        let (sender, mut receiver) = mpsc::unbounded();
+        let result = {
+            let mut upgrade = pin!(download::with_events(
+                sender,
+                super::upgrade(&merged, false)
+            ));
+            loop {
+                terminal.draw(|frame| ui::draw(frame, self))?;
+                // Keys pressed during the upgrade are ignored
+                while event::poll(Duration::ZERO)? {
+                    event::read()?;
+                }
+                tokio::select! {
+                    result = &mut upgrade => break result,
+                    Some(event) = receiver.next() => self.apply(event),
+                    () = sleep(Duration::from_millis(100)) => {}
+                }
+            }
+        };
+        // The sender is dropped with the upgrade, so this only reads the events left in the channel
+        while let Some(event) = receiver.next().await {
+            self.apply(event);
+        }
+
+        let result = match result {
+            Ok(adopted) => {
+                super::update_local_hashes(self.config, &name);
+                if adopted.is_empty() {
+                    Ok(())
+                } else {
+                    // Adopting asks what each untracked file is, so it is done outside of the interface
+                    ratatui::restore();
+                    colored::control::unset_override();
+                    let result = super::adopt(self.config, &name, &adopted).await;
+                    if let Err(err) = &result {
+                        println!("{}", err.to_string().trim().red());
+                    }
+                    println!("\nPress Enter to return");
+                    stdin().read_line(&mut String::new())?;
+                    colored::control::set_override(false);
+                    *terminal = ratatui::try_init()?;
+                    result
+                }
+            }
+            Err(err) => Err(err),
+        };
+        let outcome = match result {
+            Ok(()) => format!("Upgraded {name}"),
+            Err(err) => {
+                self.apply(download::Event::Message(err.to_string()));
+                format!("Some mods of {name} could not be upgraded")
+            }
+        };
+        self.status = format!("{outcome}, press enter to return");
+        if let Mode::Upgrade(upgrade) = &mut self.mode {
+            upgrade.outcome = Some(outcome);
+        }
+        Ok(())
+    }
+
+    /// Show the `event` of the upgrade in progress
+    fn apply(&mut self, event: download::Event) {
+        if let Mode::Upgrade(upgrade) = &mut self.mode {
+            upgrade.apply(event);
+        }
+    }
+}
+
+/// Add the mod identified by or linked to by `id` to `profile`, describing what was added
+async fn add_mods(profile: &mut Profile, id: &str) -> Result<String> {
+    let (external_ids, ids): (Vec<_>, Vec<_>) = add::resolve_url(id)
+        .await?
+        .into_iter()
+        .partition(|id| sources::is_source_id(id));
+
+    let (mut successes, failures) = add::add(
+        profile,
+        ids.into_iter().map(libium::add::parse_id).collect_vec(),
+        true,
+        false,
+        Vec::new(),
+    )
+    .await;
+    let mut failures = failures
+        .into_iter()
+        .map(|(id, err)| format!("{id} ({err})"))
+        .collect_vec();
+    let (external_successes, external_failures) = sources::add(profile, external_ids, true).await;
+    successes.extend(external_successes);
+    failures.extend(
+        external_failures
+            .into_iter()
+            .map(|(id, err)| format!("{id} ({err})")),
+    );
+
+    let mut message = Vec::new();
+    if !successes.is_empty() {
+        message.push(format!("Added {}", successes.iter().display(", ")));
+    }
+    if !failures.is_empty() {
+        message.push(format!("Could not add {}", failures.iter().display(", ")));
+    }
+    Ok(message.join(", "))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::bail;
+
+    fn config() -> Result<Config> {
+        let (config, _) = crate::config::deserialise(include_str!(
+            "../../../tests/configs/two_profiles_one_empty.json"
+        ))?;
+        Ok(config)
+    }
+
+    fn app(config: &mut Config) -> App<'_> {
+        App {
+            config,
+            focus: Focus::Mods,
+            mode: Mode::Browse,
+            profiles: ListState::default().with_selected(Some(0)),
+            mods: ListState::default().with_selected(Some(0)),
+            metadata: None,
+            status: String::new(),
+            quit: false,
+        }
+    }
+
+    /// Press each of `keys`, returning the work started by the last one
+    fn press(app: &mut App, keys: &[KeyCode]) -> Option<Action> {
+        keys.iter().fold(None, |_, key| app.handle_key(*key))
+    }
+
+    fn names(app: &App) -> Vec<String> {
+        app.rows().into_iter().map(|row| row.name).collect()
+    }
+
+    #[test]
+    fn moving_stays_within_the_focused_pane() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        press(&mut app, &[KeyCode::Char('j'); 5]);
+        assert_eq!(app.mods.selected(), Some(2));
+        assert_eq!(app.profiles.selected(), Some(0));
+
+        press(&mut app, &[KeyCode::Tab, KeyCode::Down]);
+        assert!(app.focus == Focus::Profiles);
+        assert_eq!(app.profiles.selected(), Some(1));
+        assert_eq!(app.mods.selected(), Some(0));
+        Ok(())
+    }
+
+    #[test]
+    fn enter_switches_profiles_only_in_the_profiles_pane() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        assert_eq!(
+            press(&mut app, &[KeyCode::Enter]),
+            Some(Action::QueryMetadata)
+        );
+        assert_eq!(
+            press(
+                &mut app,
+                &[KeyCode::Tab, KeyCode::Char('j'), KeyCode::Enter]
+            ),
+            None
+        );
+        assert_eq!(app.config.active_profile, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn mod_actions_do_nothing_in_the_profiles_pane() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        press(
+            &mut app,
+            &[KeyCode::Tab, KeyCode::Char('x'), KeyCode::Char('y')],
+        );
+        press(&mut app, &[KeyCode::Delete, KeyCode::Char(' ')]);
+        assert!(matches!(app.mode, Mode::Browse));
+        assert_eq!(names(&app).len(), 3);
+        assert!(app.config.profiles[0].disabled.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn removing_a_mod_is_confirmed() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        press(&mut app, &[KeyCode::Char('x')]);
+        assert!(matches!(app.mode, Mode::ConfirmRemove));
+        press(&mut app, &[KeyCode::Char('n')]);
+        assert_eq!(app.status, "Cancelled");
+        assert_eq!(names(&app).len(), 3);
+
+        press(&mut app, &[KeyCode::Char('x'), KeyCode::Char('y')]);
+        assert_eq!(names(&app), ["sodium-fabric", "Starlight (Fabric)"]);
+        assert!(matches!(app.mode, Mode::Browse));
+        Ok(())
+    }
+
+    #[test]
+    fn space_toggles_the_selected_mod() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        press(&mut app, &[KeyCode::Char(' ')]);
+        assert_eq!(app.config.profiles[0].disabled, ["591388"]);
+        assert!(app.rows()[0].disabled);
+        press(&mut app, &[KeyCode::Char('d')]);
+        assert!(app.config.profiles[0].disabled.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn typing_a_query_searches_for_it() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        press(
+            &mut app,
+            &[
+                KeyCode::Char('/'),
+                KeyCode::Char('j'),
+                KeyCode::Char('e'),
+                KeyCode::Char('x'),
+                KeyCode::Backspace,
+            ],
+        );
+        assert!(matches!(&app.mode, Mode::Search(query) if query == "je"));
+        assert_eq!(
+            press(&mut app, &[KeyCode::Enter]),
+            Some(Action::Search("je".into()))
+        );
+        assert!(matches!(app.mode, Mode::Browse));
+
+        press(
+            &mut app,
+            &[KeyCode::Char('a'), KeyCode::Char('x'), KeyCode::Esc],
+        );
+        assert!(matches!(app.mode, Mode::Browse));
+        assert_eq!(names(&app).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn upgrade_shows_the_progress_of_each_file() -> Result<()> {
+        let mut config = config()?;
+        let mut app = app(&mut config);
+
+        assert_eq!(
+            press(&mut app, &[KeyCode::Char('u')]),
+            Some(Action::Upgrade)
+        );
+        app.mode = Mode::Upgrade(Upgrade::new("Profile One".into()));
+        for event in [
+            download::Event::Message("\nDownloading Mod Files\n".into()),
+            download::Event::Queued("a.jar".into(), 10),
+            download::Event::Queued("b.jar".into(), 10),
+            download::Event::Downloaded("a.jar".into(), 4),
+            download::Event::Finished("b.jar".into(), Some("Not found".into())),
+        ] {
+            app.apply(event);
+        }
+        let Mode::Upgrade(upgrade) = &app.mode else {
+            bail!("Not upgrading");
+        };
+        assert_eq!(upgrade.log, ["Downloading Mod Files"]);
+        assert_eq!(upgrade.files[0].downloaded, 4);
+        assert!(!upgrade.files[0].done);
+        assert!(upgrade.files[1].done);
+        assert_eq!(upgrade.files[1].error.as_deref(), Some("Not found"));
+
+        press(&mut app, &[KeyCode::Char('j')]);
+        assert!(matches!(app.mode, Mode::Upgrade(_)));
+        press(&mut app, &[KeyCode::Enter]);
+        assert!(matches!(app.mode, Mode::Browse));
+        Ok(())
+    }
+}
@@ -0,0 +1,274 @@
+use super::{App, Focus, Mode, Row, Upgrade};
+use libium::iter_ext::IterExt as _;
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, Clear, List, ListItem, Paragraph, Wrap},
+    Frame,
+};
+
+const HINTS: &str = "↑↓ move  tab switch pane  enter select  i details  / search  a add  space toggle  x remove  u upgrade  q quit";
+
+fn dim() -> Style {
+    Style::new().add_modifier(Modifier::DIM)
+}
+
+fn bold() -> Style {
+    Style::new().add_modifier(Modifier::BOLD)
+}
+
+fn pane(title: &str, focused: bool) -> Block<'_> {
+    let block = Block::bordered().title(format!(" {title} "));
+    if focused {
+        block.border_style(Style::new().fg(Color::Cyan))
+    } else {
+        block
+    }
+}
+
+fn list<'a>(items: Vec<ListItem<'a>>, block: Block<'a>) -> List<'a> {
+    List::new(items)
+        .block(block)
+        .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ")
+}
+
+/// The part of `area` of `width` by `height` in its centre
+fn centered(area: Rect, width: Constraint, height: Constraint) -> Rect {
+    let [area] = Layout::horizontal([width]).flex(Flex::Center).areas(area);
+    let [area] = Layout::vertical([height]).flex(Flex::Center).areas(area);
+    area
+}
+
+pub fn draw(frame: &mut Frame, app: &mut App) {
+    let [main, status, hints] = Layout::vertical([
+        Constraint::Min(0),
+        Constraint::Length(1),
+        Constraint::Length(1),
+    ])
+    .areas(frame.area());
+    let [profiles, mods, details] = Layout::horizontal([
+        Constraint::Percentage(20),
+        Constraint::Percentage(35),
+        Constraint::Percentage(45),
+    ])
+    .areas(main);
+
+    let rows = app.rows();
+    let selected = app.mods.selected().and_then(|index| rows.get(index));
+    draw_profiles(frame, app, profiles);
+    draw_mods(frame, app, &rows, mods);
+    draw_details(frame, app, selected, details);
+    frame.render_widget(Paragraph::new(app.status.as_str()), status);
+    frame.render_widget(Paragraph::new(HINTS).style(dim()), hints);
+
+    match &mut app.mode {
+        Mode::Browse => {}
+        Mode::Search(query) => draw_input(frame, "Search Modrinth", query),
+        Mode::Add(id) => draw_input(frame, "Add by identifier or link", id),
+        Mode::Results(hits, state) => {
+            let items = hits
+                .iter()
+                .map(|hit| {
+                    ListItem::new(vec![
+                        Line::from(vec![
+                            Span::styled(hit.title.clone(), bold()),
+                            Span::raw(format!(" by {}", hit.author)),
+                            Span::styled(format!("  {} downloads", hit.downloads), dim()),
+                        ]),
+                        Line::styled(format!("  {}", hit.description), dim()),
+                    ])
+                })
+                .collect_vec();
+            let area = centered(
+                frame.area(),
+                Constraint::Percentage(80),
+                Constraint::Percentage(70),
+            );
+            frame.render_widget(Clear, area);
+            frame.render_stateful_widget(
+                list(items, pane("Results, enter to add", true)),
+                area,
+                state,
+            );
+        }
+        Mode::ConfirmRemove => {
+            let name = selected.map_or("", |row| row.name.as_str());
+            let area = centered(frame.area(), Constraint::Length(50), Constraint::Length(3));
+            frame.render_widget(Clear, area);
+            frame.render_widget(
+                Paragraph::new(format!("Remove {name}? (y/n)")).block(pane("Remove", true)),
+                area,
+            );
+        }
+        Mode::Upgrade(upgrade) => draw_upgrade(frame, upgrade),
+    }
+}
+
+fn draw_profiles(frame: &mut Frame, app: &mut App, area: Rect) {
+    let items = app
+        .config
+        .profiles
+        .iter()
+        .enumerate()
+        .map(|(index, profile)| {
+            let mut line = Line::raw(profile.name.clone());
+            if index == app.config.active_profile {
+                line.push_span(Span::styled(" (active)", dim()));
+            }
+            ListItem::new(line)
+        })
+        .collect_vec();
+    frame.render_stateful_widget(
+        list(items, pane("Profiles", app.focus == Focus::Profiles)),
+        area,
+        &mut app.profiles,
+    );
+}
+
+fn draw_mods(frame: &mut Frame, app: &mut App, rows: &[Row], area: Rect) {
+    let items = rows
+        .iter()
+        .map(|row| {
+            let installed = !row.disabled && row.group.as_ref().map_or(true, |(_, on)| *on);
+            let mut line = Line::from(vec![
+                Span::styled(format!("{} ", row.source), dim()),
+                Span::styled(
+                    row.name.clone(),
+                    if installed { Style::new() } else { dim() },
+                ),
+            ]);
+            if row.disabled {
+                line.push_span(Span::styled(" (disabled)", dim()));
+            }
+            if let Some((group, _)) = &row.group {
+                line.push_span(Span::styled(format!(" [{group}]"), dim()));
+            }
+            ListItem::new(line)
+        })
+        .collect_vec();
+    let title = format!("Mods ({})", rows.len());
+    frame.render_stateful_widget(
+        list(items, pane(&title, app.focus == Focus::Mods)),
+        area,
+        &mut app.mods,
+    );
+}
+
+fn draw_details(frame: &mut Frame, app: &App, row: Option<&Row>, area: Rect) {
+    let Some(row) = row else {
+        frame.render_widget(
+            Paragraph::new("This profile has no mods, press / to search for some")
+                .wrap(Wrap { trim: false })
+                .block(pane("Details", false)),
+            area,
+        );
+        return;
+    };
+
+    let metadata = app.metadata_of(&row.id);
+    let mut lines = vec![Line::styled(row.name.clone(), bold())];
+    if let Some(description) = metadata.and_then(|metadata| metadata.description()) {
+        lines.push(Line::styled(
+            description.to_owned(),
+            Style::new().add_modifier(Modifier::ITALIC),
+        ));
+    }
+    lines.push(Line::default());
+
+    let state = match &row.group {
+        _ if row.disabled => "Disabled".to_owned(),
+        Some((group, false)) => format!("Not installed, {group} is disabled"),
+        _ => "Enabled".to_owned(),
+    };
+    let mut fields = vec![("Identifier", row.id.clone()), ("State", state)];
+    if let Some((group, _)) = &row.group {
+        fields.push(("Group", group.clone()));
+    }
+    if let Some(metadata) = metadata {
+        fields.extend(metadata.details());
+    }
+    for (key, value) in fields {
+        lines.push(Line::from(vec![
+            Span::styled(format!("{key:13}"), dim()),
+            Span::raw(value),
+        ]));
+    }
+    if metadata.is_none() {
+        lines.push(Line::default());
+        lines.push(Line::styled(
+            "Press i to query the details of the mods",
+            dim(),
+        ));
+    }
+
+    frame.render_widget(
+        Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(pane("Details", false)),
+        area,
+    );
+}
+
+fn draw_input(frame: &mut Frame, title: &str, text: &str) {
+    let area = centered(
+        frame.area(),
+        Constraint::Percentage(60),
+        Constraint::Length(3),
+    );
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(text).block(pane(title, true)), area);
+    let offset = u16::try_from(text.chars().count())
+        .unwrap_or(u16::MAX)
+        .min(area.width.saturating_sub(3));
+    frame.set_cursor_position((area.x + 1 + offset, area.y + 1));
+}
+
+/// The messages of `upgrade` followed by the progress of each of its files,
+/// scrolled so that the latest lines are visible
+fn draw_upgrade(frame: &mut Frame, upgrade: &Upgrade) {
+    let area = centered(
+        frame.area(),
+        Constraint::Percentage(80),
+        Constraint::Percentage(80),
+    );
+    let mut lines = upgrade
+        .log
+        .iter()
+        .map(|line| Line::raw(line.clone()))
+        .collect_vec();
+    for file in &upgrade.files {
+        let (state, style) = match &file.error {
+            Some(_) => ("✗".to_owned(), Style::new().fg(Color::Red)),
+            None if file.done => ("✓".to_owned(), Style::new().fg(Color::Green)),
+            None => (
+                format!(
+                    "{}%",
+                    (file.downloaded * 100)
+                        .checked_div(file.length)
+                        .unwrap_or_default()
+                        .min(100)
+                ),
+                dim(),
+            ),
+        };
+        let mut line = Line::from(vec![
+            Span::styled(format!("{state:>4} "), style),
+            Span::raw(file.filename.clone()),
+        ]);
+        if let Some(error) = &file.error {
+            line.push_span(Span::styled(format!("  {error}"), style));
+        }
+        lines.push(line);
+    }
+    let visible = usize::from(area.height.saturating_sub(2));
+    let lines = lines.split_off(lines.len().saturating_sub(visible));
+
+    let title = match &upgrade.outcome {
+        Some(outcome) => format!("{outcome}, enter to return"),
+        None => format!("Upgrading {}", upgrade.name),
+    };
+    frame.render_widget(Clear, area);
+    frame.render_widget(Paragraph::new(lines).block(pane(&title, true)), area);
+}
@@ -1,19 +1,28 @@
 #![expect(clippy::expect_used, reason = "For mutex poisons")]
 
+use super::filters::{channel_name, effective_filters, fallback_channels, set_release_channel};
 use crate::{
-    download::{clean, download},
-    CROSS, DEFAULT_PARALLEL_NETWORK, PARALLEL_NETWORK, STYLE_NO, TICK,
+    cli,
+    config::{platform_id, Config, Profile},
+    download::{
+        clean, download, download_many, progress_bar, record_mod_files, report, report_above, Batch,
+    },
+    rate_limit::{self, Platform},
+    scan,
+    sources::{self, platform, SourceIdentifier},
+    CROSS, NETWORK_PERMITS, STYLE_NO, TICK,
 };
-use anyhow::{anyhow, bail, Result};
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
 use colored::Colorize as _;
-use futures::{stream::FuturesUnordered, StreamExt as _};
+use futures::{stream::FuturesUnordered, FutureExt as _, StreamExt as _};
 use indicatif::ProgressBar;
 use libium::{
     config::{
-        filters::ProfileParameters as _,
-        structs::{ModLoader, Profile},
+        filters::{Filter, ProfileParameters as _, ReleaseChannel},
+        structs::{Mod, ModIdentifier, ModLoader},
     },
-    upgrade::{mod_downloadable, DownloadData},
+    iter_ext::IterExt as _,
+    upgrade::DownloadData,
 };
 use std::{
     fs::read_dir,
@@ -24,93 +33,267 @@ use tokio::sync::Semaphore;
 
 /// Get the latest compatible downloadable for the mods in `profile`
 ///
+/// Local files are not resolved here, see [`sources::local::to_install`].
+/// Disabled mods and the mods in disabled groups are skipped.
+///
 /// If an error occurs with a resolving task, instead of failing immediately,
-/// resolution will continue and the error return flag is set to true.
-pub async fn get_platform_downloadables(profile: &Profile) -> Result<(Vec<DownloadData>, bool)> {
+/// resolution will continue and the name of the mod is returned with its error along with the downloadables.
+/// If a platform's rate limit is exceeded, its mods wait for it to reset and are resolved again.
+///
+/// Mods without files in their release channel are resolved again with the less stable channels
+/// allowed by their [fallback](Profile::channel_fallback_of), and are listed at the end.
+pub async fn get_platform_downloadables(
+    profile: &Profile,
+) -> Result<(Vec<(String, DownloadData)>, Vec<(String, anyhow::Error)>)> {
     let to_download = Arc::new(Mutex::new(Vec::new()));
+    let mods = profile
+        .mods
+        .iter()
+        .filter(|mod_| profile.is_mod_installed(mod_))
+        .cloned()
+        .collect::<Vec<_>>();
+    let external_mods = profile
+        .external_mods
+        .iter()
+        .filter(|mod_| !mod_.identifier.is_local() && profile.is_external_mod_installed(mod_))
+        .cloned()
+        .collect::<Vec<_>>();
     let progress_bar = Arc::new(Mutex::new(
-        ProgressBar::new(profile.mods.len() as u64).with_style(STYLE_NO.clone()),
+        progress_bar((mods.len() + external_mods.len()) as u64).with_style(STYLE_NO.clone()),
     ));
     let mut tasks = FuturesUnordered::new();
 
-    println!("{}\n", "Determining the Latest Compatible Versions".bold());
-    let semaphore = Arc::new(Semaphore::new(
-        *PARALLEL_NETWORK.get_or_init(|| DEFAULT_PARALLEL_NETWORK),
+    report(format!(
+        "{}\n",
+        "Determining the Latest Compatible Versions".bold()
     ));
+    let semaphore = Arc::clone(&NETWORK_PERMITS);
     progress_bar
         .lock()
         .expect("Mutex poisoned")
         .enable_steady_tick(Duration::from_millis(100));
-    let pad_len = profile
-        .mods
+    let pad_len = mods
         .iter()
         .map(|m| m.name.len())
+        .chain(external_mods.iter().map(|m| m.name.len()))
         .max()
         .unwrap_or(20)
         .clamp(20, 50);
-    for mod_ in profile.mods.clone() {
+    for mod_ in mods {
         let semaphore = Arc::clone(&semaphore);
         let to_download = Arc::clone(&to_download);
         let progress_bar = Arc::clone(&progress_bar);
 
-        tasks.push(async move {
-            let _permit = semaphore.acquire_owned().await?;
-            let result = mod_.fetch_download_file(profile.filters.clone()).await;
-            let progress_bar = progress_bar.lock().expect("Mutex poisoned");
-            progress_bar.inc(1);
-            match result {
-                Ok(download_file) => {
-                    progress_bar.println(format!(
-                        "{} {:pad_len$}  {}",
-                        TICK.clone(),
-                        mod_.name,
-                        download_file.filename().dimmed()
-                    ));
-                    to_download
-                        .lock()
-                        .expect("Mutex poisoned")
-                        .push(download_file);
-                    Ok(true)
+        tasks.push(
+            async move {
+                let mut result =
+                    fetch_mod(&mod_, &profile.filters, &semaphore, &progress_bar).await;
+                let mut fallback = None;
+                for channel in fallback_channels(
+                    &effective_filters(&profile.filters, &mod_),
+                    profile.channel_fallback_of(&platform_id(&mod_.identifier)),
+                ) {
+                    if !result.as_ref().is_err_and(is_filter_error) {
+                        break;
+                    }
+                    let mut mod_ = mod_.clone();
+                    let mut filters = profile.filters.clone();
+                    set_release_channel(&mut mod_.filters, &channel);
+                    set_release_channel(&mut filters, &channel);
+                    result = fetch_mod(&mod_, &filters, &semaphore, &progress_bar).await;
+                    fallback = Some(channel);
                 }
-                Err(err) => {
-                    if let mod_downloadable::Error::ModrinthError(
-                        ferinth::Error::RateLimitExceeded(_),
-                    ) = err
-                    {
-                        // Immediately fail if the rate limit has been exceeded
-                        progress_bar.finish_and_clear();
-                        bail!(err);
+                let fallback = fallback.filter(|_| result.is_ok());
+                let resolved = report_resolution(
+                    &progress_bar,
+                    &to_download,
+                    (&platform_id(&mod_.identifier), &mod_.name),
+                    result,
+                    fallback.as_ref(),
+                    pad_len,
+                );
+                Ok::<_, anyhow::Error>((mod_.name, resolved, fallback))
+            }
+            .boxed(),
+        );
+    }
+    for mod_ in external_mods {
+        let semaphore = Arc::clone(&semaphore);
+        let to_download = Arc::clone(&to_download);
+        let progress_bar = Arc::clone(&progress_bar);
+
+        tasks.push(
+            async move {
+                let _permit = semaphore.acquire_owned().await?;
+                let mut result =
+                    sources::fetch_download_file(&mod_.identifier, &profile.filters).await;
+                let mut fallback = None;
+                for channel in fallback_channels(
+                    &profile.filters,
+                    profile.channel_fallback_of(&mod_.identifier.display_id()),
+                ) {
+                    if !result.as_ref().is_err_and(is_filter_error) {
+                        break;
                     }
-                    progress_bar.println(format!(
-                        "{}",
-                        format!("{CROSS} {:pad_len$}  {err}", mod_.name).red()
-                    ));
-                    Ok(false)
+                    let mut filters = profile.filters.clone();
+                    set_release_channel(&mut filters, &channel);
+                    result = sources::fetch_download_file(&mod_.identifier, &filters).await;
+                    fallback = Some(channel);
                 }
+                let fallback = fallback.filter(|_| result.is_ok());
+                let resolved = report_resolution(
+                    &progress_bar,
+                    &to_download,
+                    (&mod_.identifier.display_id(), &mod_.name),
+                    result,
+                    fallback.as_ref(),
+                    pad_len,
+                );
+                Ok::<_, anyhow::Error>((mod_.name, resolved, fallback))
             }
-        });
+            .boxed(),
+        );
     }
 
-    let mut error = false;
+    let mut failed = Vec::new();
+    let mut fallbacks = Vec::new();
     while let Some(res) = tasks.next().await {
-        let res = res?;
-        error |= !res;
+        let (name, resolved, fallback) = res?;
+        if let Err(err) = resolved {
+            failed.push((name, err));
+        } else if let Some(channel) = fallback {
+            fallbacks.push((name, channel));
+        }
     }
     Arc::try_unwrap(progress_bar)
         .map_err(|_| anyhow!("Failed to run threads to completion"))?
         .into_inner()?
         .finish_and_clear();
+    if !fallbacks.is_empty() {
+        report(format!(
+            "\n{}",
+            "These mods have no compatible release, so a less stable file was picked:".yellow()
+        ));
+        for (name, channel) in fallbacks {
+            report(format!("  {name} ({})", channel_name(&channel)));
+        }
+    }
     Ok((
         Arc::try_unwrap(to_download)
             .map_err(|_| anyhow!("Failed to run threads to completion"))?
             .into_inner()?,
-        error,
+        failed,
     ))
 }
 
-pub async fn upgrade(profile: &Profile) -> Result<()> {
-    let (mut to_download, error) = get_platform_downloadables(profile).await?;
-    let mut to_install = Vec::new();
+/// Fetch the latest file of `mod_` compatible with the `profile_filters` and its own filters
+async fn fetch_mod(
+    mod_: &Mod,
+    profile_filters: &[Filter],
+    semaphore: &Arc<Semaphore>,
+    progress_bar: &Mutex<ProgressBar>,
+) -> Result<DownloadData> {
+    let platform = match mod_.identifier {
+        ModIdentifier::CurseForgeProject(_) => Platform::CurseForge,
+        ModIdentifier::ModrinthProject(_) => Platform::Modrinth,
+        ModIdentifier::GitHubRepository(_) => Platform::GitHub,
+    };
+    rate_limit::until_ready(platform, Some(progress_bar)).await;
+    let _permit = Arc::clone(semaphore).acquire_owned().await?;
+    platform::fetch_download_file(&mod_.identifier, &effective_filters(profile_filters, mod_)).await
+}
+
+/// Whether `err` is because none of a mod's files satisfied its filters
+pub fn is_filter_error(err: &anyhow::Error) -> bool {
+    err.is::<sources::NoCompatibleFile>()
+}
+
+/// Print the outcome of resolving the mod with the ID and name `(id, name)`,
+/// and queue its file along with its ID if successful
+///
+/// The file is marked if it is from the `fallback` release channel.
+/// Returns the error if the resolution failed.
+fn report_resolution(
+    progress_bar: &Mutex<ProgressBar>,
+    to_download: &Mutex<Vec<(String, DownloadData)>>,
+    (id, name): (&str, &str),
+    result: Result<DownloadData>,
+    fallback: Option<&ReleaseChannel>,
+    pad_len: usize,
+) -> Result<()> {
+    let progress_bar = progress_bar.lock().expect("Mutex poisoned");
+    progress_bar.inc(1);
+    match result {
+        Ok(download_file) => {
+            report_above(
+                &progress_bar,
+                format!(
+                    "{} {:pad_len$}  {}{}",
+                    TICK.clone(),
+                    name,
+                    download_file.filename().dimmed(),
+                    fallback
+                        .map(|channel| format!("  {} fallback", channel_name(channel))
+                            .yellow()
+                            .to_string())
+                        .unwrap_or_default()
+                ),
+            );
+            to_download
+                .lock()
+                .expect("Mutex poisoned")
+                .push((id.to_owned(), download_file));
+            Ok(())
+        }
+        Err(err) => {
+            report_above(
+                &progress_bar,
+                format!("{CROSS} {name:pad_len$}  {err}").red(),
+            );
+            Err(err)
+        }
+    }
+}
+
+/// A profile whose mods have been resolved and whose output directory has been cleaned,
+/// so that only its files are left to download
+struct PreparedUpgrade {
+    batch: Batch,
+    /// The untracked files that should be adopted
+    adopted: Vec<String>,
+    /// Whether some of the mods could not be resolved
+    error: bool,
+}
+
+/// Upgrade the mods of `profile`, asking what to do with untracked files in its output directory if `ask`
+///
+/// Returns the untracked files that should be adopted, which are left in the output directory.
+pub async fn upgrade(profile: &Profile, ask: bool) -> Result<Vec<String>> {
+    let PreparedUpgrade {
+        batch,
+        adopted,
+        error,
+    } = prepare(profile, ask).await?;
+    if !batch.is_empty() {
+        report(format!("\n{}\n", "Downloading Mod Files".bold()));
+        download(batch.output_dir, batch.to_download, batch.to_install).await?;
+    }
+    finish(profile, adopted, error)
+}
+
+/// Resolve the mods of `profile` and clean its output directory, leaving the files to download
+async fn prepare(profile: &Profile, ask: bool) -> Result<PreparedUpgrade> {
+    let (resolved, failed) = get_platform_downloadables(profile).await?;
+    let mut error = !failed.is_empty();
+    let local_mods = profile
+        .external_mods
+        .iter()
+        .filter(|mod_| profile.is_external_mod_installed(mod_))
+        .cloned()
+        .collect_vec();
+    let (mut to_install, local_error) =
+        sources::local::to_install(&local_mods, &profile.output_dir)?;
+    error |= local_error;
     if profile.output_dir.join("user").exists()
         && profile.filters.mod_loader() != Some(&ModLoader::Quilt)
     {
@@ -127,17 +310,60 @@ pub async fn upgrade(profile: &Profile) -> Result<()> {
         }
     }
 
-    clean(&profile.output_dir, &mut to_download, &mut to_install).await?;
+    // The file each mod is installed as, so that it can be found when the mod is disabled
+    let mod_files = resolved
+        .iter()
+        .map(|(id, file)| (id.clone(), file.filename()))
+        .chain(local_mods.iter().filter_map(|mod_| match &mod_.identifier {
+            SourceIdentifier::LocalFile { path, .. } => Some((
+                mod_.identifier.display_id(),
+                path.file_name()?.to_string_lossy().into_owned(),
+            )),
+            _ => None,
+        }))
+        .collect_vec();
+    let mut to_download = resolved.into_iter().map(|(_, file)| file).collect_vec();
+
+    let adopted = clean(
+        &profile.output_dir,
+        &mut to_download,
+        &mut to_install,
+        profile.untracked_files.unwrap_or_default(),
+        ask,
+        profile.keep_old.unwrap_or_default(),
+        &profile.disabled,
+    )
+    .await?;
+    record_mod_files(&profile.output_dir, mod_files)?;
     to_download
         .iter_mut()
         // Download directly to the output directory
         .map(|thing| thing.output = thing.filename().into())
         .for_each(drop); // Doesn't drop any data, just runs the iterator
-    if to_download.is_empty() && to_install.is_empty() {
-        println!("\n{}", "All up to date!".bold());
-    } else {
-        println!("\n{}\n", "Downloading Mod Files".bold());
-        download(profile.output_dir.clone(), to_download, to_install).await?;
+    let batch = Batch {
+        output_dir: profile.output_dir.clone(),
+        to_download,
+        to_install,
+    };
+    if batch.is_empty() {
+        report(format!("\n{}", "All up to date!".bold()));
+    }
+    Ok(PreparedUpgrade {
+        batch,
+        adopted,
+        error,
+    })
+}
+
+/// Check the files of `profile` once they have been downloaded,
+/// failing if some of its mods could not be resolved
+fn finish(profile: &Profile, adopted: Vec<String>, error: bool) -> Result<Vec<String>> {
+    let mismatched = sources::direct::verify_hashes(&profile.external_mods, &profile.output_dir)?;
+    if !mismatched.is_empty() {
+        bail!(
+            "\nThe following files did not match their expected hash and have been deleted: {}",
+            mismatched.iter().display(", ")
+        );
     }
 
     if error {
@@ -145,6 +371,172 @@ pub async fn upgrade(profile: &Profile) -> Result<()> {
             "\nCould not get the latest compatible version of some mods"
         ))
     } else {
-        Ok(())
+        Ok(adopted)
     }
 }
+
+/// Add the mods of the `adopted` files in the output directory of the profile called `name` to it,
+/// then upgrade it again so that the adopted files are replaced by the latest files of their mods
+///
+/// The adopted files are recorded as installed by ferium, so the ones that could not be adopted
+/// are moved to `.old` by the second upgrade.
+pub async fn adopt(config: &mut Config, name: &str, adopted: &[String]) -> Result<()> {
+    if adopted.is_empty() {
+        return Ok(());
+    }
+    let profile = config
+        .find_profile_mut(name)
+        .with_context(|| format!("Profile {name} does not exist"))?;
+    println!("\n{}\n", "Adopting Untracked Files".bold());
+    let output_dir = profile.output_dir.clone();
+    let failed = scan::scan(
+        profile,
+        &output_dir,
+        cli::Platform::default(),
+        false,
+        Some(adopted),
+    )
+    .await?;
+
+    let profile = config
+        .find_profile(name)
+        .with_context(|| format!("Profile {name} does not exist"))?;
+    let merged = config.merged(profile)?;
+    println!("\n{}\n", "Upgrading Adopted Mods".bold());
+    upgrade(&merged, false).await?;
+    ensure!(
+        !failed,
+        "Some of the untracked files could not be adopted, they were moved to .old"
+    );
+    Ok(())
+}
+
+/// Store the current hashes of the local files installed for the profile called `name`,
+/// in the profiles they belong to, once it has been upgraded
+pub fn update_local_hashes(config: &mut Config, name: &str) {
+    let Some(profile) = config.find_profile(name) else {
+        return;
+    };
+    let mut owned = profile
+        .external_mods
+        .iter()
+        .map(|mod_| (profile.name.clone(), mod_.clone()))
+        .collect_vec();
+    if let Ok(inherited) = config.inherited_mods(profile) {
+        owned.extend(inherited.external_mods);
+    }
+    let installed = owned
+        .into_iter()
+        .filter(|(_, mod_)| mod_.identifier.is_local() && profile.is_external_mod_installed(mod_))
+        .map(|(owner, mod_)| (owner, mod_.identifier.display_id()))
+        .collect_vec();
+    for (owner, id) in installed {
+        if let Some(owner) = config.find_profile_mut(&owner) {
+            sources::local::update_hash(&mut owner.external_mods, &id);
+        }
+    }
+}
+
+/// Upgrade each of the profiles called one of `names`, then print a summary
+///
+/// Every profile is resolved first, then all of their files are downloaded together,
+/// sharing the network permits. A profile failing to upgrade does not stop the others from being upgraded.
+pub async fn upgrade_many(config: &mut Config, names: &[String], ask: bool) -> Result<()> {
+    let mut results = Vec::new();
+    let mut prepared = Vec::new();
+    for name in names {
+        let Some(profile) = config.find_profile(name) else {
+            results.push((name.clone(), Err(anyhow!("Profile {name} does not exist"))));
+            continue;
+        };
+        let merged = match config.merged(profile) {
+            Ok(merged) if merged.mod_count() == 0 => {
+                println!(
+                    "{}",
+                    format!("Skipping {} as it has no mods", profile.name).yellow()
+                );
+                continue;
+            }
+            Ok(merged) => merged,
+            Err(err) => {
+                results.push((profile.name.clone(), Err(err)));
+                continue;
+            }
+        };
+
+        println!(
+            "\n{}\n",
+            format!("Resolving {}", merged.name).bold().underline()
+        );
+        match prepare(&merged, ask).await {
+            Ok(upgrade) => prepared.push((name, merged, upgrade)),
+            Err(err) => {
+                println!("{}", err.to_string().trim().red());
+                results.push((merged.name.clone(), Err(err)));
+            }
+        }
+    }
+
+    let mut batches = Vec::new();
+    let mut pending = Vec::new();
+    for (name, merged, upgrade) in prepared {
+        batches.push(upgrade.batch);
+        pending.push((name, merged, upgrade.adopted, upgrade.error));
+    }
+    if batches.iter().any(|batch| !batch.is_empty()) {
+        println!("\n{}\n", "Downloading Mod Files".bold());
+    }
+    let downloaded = download_many(batches).await;
+
+    for ((name, merged, adopted, error), downloaded) in pending.into_iter().zip(downloaded) {
+        let result = match downloaded.and_then(|()| finish(&merged, adopted, error)) {
+            Ok(adopted) => {
+                update_local_hashes(config, name);
+                adopt(config, name, &adopted).await
+            }
+            Err(err) => Err(err),
+        };
+        if let Err(err) = &result {
+            println!(
+                "{}",
+                format!("{}: {}", merged.name, err.to_string().trim()).red()
+            );
+        }
+        results.push((merged.name.clone(), result));
+    }
+    print_summary(&results)
+}
+
+/// Print whether each of the named upgrades succeeded
+///
+/// Returns an error if any of them failed, so that ferium exits with a failure.
+pub fn print_summary(results: &[(String, Result<()>)]) -> Result<()> {
+    println!("\n{}\n", "Summary".bold());
+    let pad_len = results
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(20)
+        .clamp(20, 50);
+    for (name, result) in results {
+        match result {
+            Ok(()) => println!("{} {name}", TICK.clone()),
+            Err(err) => println!(
+                "{}",
+                format!(
+                    "{CROSS} {name:pad_len$}  {}",
+                    err.to_string().trim().lines().next().unwrap_or_default()
+                )
+                .red()
+            ),
+        }
+    }
+
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    ensure!(
+        failed == 0,
+        "\n{failed} of {} failed to upgrade",
+        results.len()
+    );
+    Ok(())
+}
@@ -1,8 +1,18 @@
 mod util;
 
 use libium::HOME;
-use std::fs::remove_dir;
-use util::run_command;
+use serde_json::json;
+use sha2::{Digest as _, Sha256, Sha512};
+use std::{
+    fs::{create_dir_all, read_dir, read_to_string, remove_dir, write},
+    path::Path,
+    sync::Mutex,
+};
+use util::{
+    curseforge_file, curseforge_mod, github_release, mock_server, modrinth_project,
+    modrinth_version, recording_mock_server, run_command, run_in_workspace, scripted_mock_server,
+    MockResponse, TestConfig, TestWorkspace,
+};
 
 type Result = std::io::Result<()>;
 
@@ -147,6 +157,256 @@ fn add_all() -> Result {
     )
 }
 
+#[test]
+fn add_urls() -> Result {
+    run_command(
+        vec![
+            "add",
+            "https://modrinth.com/mod/starlight",
+            "https://www.curseforge.com/projects/591388",
+            "https://github.com/CaffeineMC/sodium-fabric/releases",
+        ],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn add_curseforge_slug_url() -> Result {
+    let mod_ = curseforge_mod(1, "test-mod", "Test Mod");
+    let (server, requests) = recording_mock_server(vec![
+        ("/v1/mods/search", json!({ "data": [mod_] }).to_string()),
+        ("/v1/mods/1", json!({ "data": mod_ }).to_string()),
+        (
+            "/v1/mods/1/files",
+            json!({
+                "data": [curseforge_file(1, "test-mod-1.0.0.jar", "1.18.2,Fabric")],
+                "pagination": { "index": 0, "pageSize": 50, "resultCount": 1, "totalCount": 1 },
+            })
+            .to_string(),
+        ),
+    ]);
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_CURSEFORGE_URL", &server);
+
+    config.run(vec![
+        "add",
+        "https://www.curseforge.com/minecraft/mc-mods/test-mod",
+    ])?;
+    assert_eq!(
+        config.read()?["profiles"][0]["mods"][0]["identifier"],
+        json!({ "CurseForgeProject": 1 })
+    );
+    // The slug is looked up using libium's default key when none is provided
+    let requests = requests.lock().unwrap();
+    let search = requests
+        .iter()
+        .find(|request| request.starts_with("GET /v1/mods/search"))
+        .unwrap();
+    assert!(search.to_lowercase().contains("x-api-key:"));
+    Ok(())
+}
+
+#[test]
+fn add_unknown_url() {
+    assert!(run_command(
+        vec!["add", "https://example.com/mods/starlight"],
+        Some("empty_profile")
+    )
+    .is_err());
+}
+
+#[test]
+fn add_from_file() -> Result {
+    run_command(
+        vec!["add", "--from-file", "./tests/mod_list.txt"],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn add_gitlab() -> Result {
+    let server = mock_server(vec![
+        (
+            "/api/v4/projects/ferium%2Ftest-mod",
+            r#"{"name": "Test Mod", "path_with_namespace": "ferium/test-mod", "description": null, "web_url": "https://gitlab.com/ferium/test-mod", "star_count": 0, "namespace": {"name": "ferium", "web_url": "https://gitlab.com/ferium"}}"#.to_owned(),
+        ),
+        (
+            "/api/v4/projects/ferium%2Ftest-mod/releases",
+            r#"[{"name": "v1.0.0", "tag_name": "v1.0.0", "assets": {"links": [{"name": "Fabric", "url": "https://example.com/test-mod-fabric-1.18.2.jar"}]}}]"#.to_owned(),
+        ),
+    ]);
+    run_command(
+        vec!["add", &format!("gitlab:{server}/ferium/test-mod")],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn add_forgejo() -> Result {
+    let server = mock_server(vec![
+        (
+            "/api/v1/repos/ferium/test-mod",
+            r#"{"name": "test-mod", "full_name": "ferium/test-mod", "description": "", "html_url": "https://codeberg.org/ferium/test-mod", "stars_count": 0, "owner": {"login": "ferium"}}"#.to_owned(),
+        ),
+        (
+            "/api/v1/repos/ferium/test-mod/releases",
+            r#"[{"name": "1.0.0 for 1.18.2", "tag_name": "v1.0.0", "draft": false, "prerelease": false, "assets": [{"name": "test-mod-1.0.0.jar", "size": 1024, "browser_download_url": "https://example.com/test-mod-1.0.0.jar"}]}]"#.to_owned(),
+        ),
+    ]);
+    run_command(
+        vec!["add", &format!("forgejo:{server}/ferium/test-mod")],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn add_gitlab_incompatible() {
+    let server = mock_server(vec![
+        (
+            "/api/v4/projects/ferium%2Ftest-mod",
+            r#"{"name": "Test Mod", "path_with_namespace": "ferium/test-mod", "description": null, "web_url": "https://gitlab.com/ferium/test-mod", "star_count": 0, "namespace": {"name": "ferium", "web_url": "https://gitlab.com/ferium"}}"#.to_owned(),
+        ),
+        (
+            "/api/v4/projects/ferium%2Ftest-mod/releases",
+            // Only available for Forge
+            r#"[{"name": "v1.0.0", "tag_name": "v1.0.0", "assets": {"links": [{"name": "Forge", "url": "https://example.com/test-mod-forge-1.18.2.jar"}]}}]"#.to_owned(),
+        ),
+    ]);
+    assert!(run_command(
+        vec!["add", &format!("gitlab:{server}/ferium/test-mod")],
+        Some("empty_profile"),
+    )
+    .is_err());
+}
+
+#[test]
+fn gitlab_game_versions_are_matched_exactly() -> Result {
+    let (files, requests) = recording_mock_server(vec![
+        ("/test-mod-fabric-1.18.2.jar", "1.18.2 jar".to_owned()),
+        ("/test-mod-fabric-1.18.jar", "1.18 jar".to_owned()),
+    ]);
+    let server = mock_server(vec![
+        (
+            "/api/v4/projects/ferium%2Ftest-mod".to_owned(),
+            r#"{"name": "Test Mod", "path_with_namespace": "ferium/test-mod", "description": null, "web_url": "https://gitlab.com/ferium/test-mod", "star_count": 0, "namespace": {"name": "ferium", "web_url": "https://gitlab.com/ferium"}}"#.to_owned(),
+        ),
+        (
+            "/api/v4/projects/ferium%2Ftest-mod/releases".to_owned(),
+            format!(
+                r#"[{{"name": "v2.0.0", "tag_name": "v2.0.0", "assets": {{"links": [{{"name": "Fabric", "url": "{files}/test-mod-fabric-1.18.2.jar"}}]}}}}, {{"name": "v1.0.0", "tag_name": "v1.0.0", "assets": {{"links": [{{"name": "Fabric", "url": "{files}/test-mod-fabric-1.18.jar"}}]}}}}]"#
+            ),
+        ),
+    ]);
+    let config = TestConfig::new("empty_profile")?;
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["profile", "configure", "--game-versions", "1.18"])?;
+
+    config.run(vec!["add", &format!("gitlab:{server}/ferium/test-mod")])?;
+    // Checking compatibility only needs the release list
+    assert!(requests.lock().unwrap().is_empty());
+
+    // `1.18.2` is not mistaken for `1.18`
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("test-mod-fabric-1.18.jar"))?,
+        "1.18 jar"
+    );
+    assert!(!output_dir.join("test-mod-fabric-1.18.2.jar").exists());
+    Ok(())
+}
+
+#[test]
+fn add_maven() -> Result {
+    let server = mock_server(vec![(
+        "/net/example/test-mod/maven-metadata.xml",
+        "<metadata><versioning><versions><version>1.0.0+1.18.2</version><version>1.1.0+1.19.2</version></versions></versioning></metadata>".to_owned(),
+    )]);
+    run_command(
+        vec![
+            "add",
+            &format!("maven:net.example:test-mod:.*\\+1\\.18\\.2@{server}"),
+        ],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn maven_versions_are_read_from_versioning() -> Result {
+    let server = mock_server(vec![
+        (
+            "/net/example/test-mod/maven-metadata.xml",
+            "<metadata><groupId>net.example</groupId><artifactId>test-mod</artifactId><version>2.0.0</version><versioning><!-- <version>3.0.0</version> --><latest>1.1.0</latest><release>1.1.0</release><versions><version>1.0.0</version><version>1.1.0</version></versions></versioning></metadata>".to_owned(),
+        ),
+        (
+            "/net/example/latest-mod/maven-metadata.xml",
+            "<metadata><versioning><latest>1.2.0</latest></versioning></metadata>".to_owned(),
+        ),
+        ("/net/example/test-mod/1.1.0/test-mod-1.1.0.jar", "test-mod jar".to_owned()),
+        ("/net/example/latest-mod/1.2.0/latest-mod-1.2.0.jar", "latest-mod jar".to_owned()),
+    ]);
+    let config = TestConfig::new("empty_profile")?;
+    let output_dir = config.with_output_dir()?;
+
+    let output = config.run(vec![
+        "add",
+        &format!("maven:net.example:test-mod@{server}"),
+        &format!("maven:net.example:latest-mod@{server}"),
+    ])?;
+    // The profile's filters cannot be applied to Maven artifacts
+    assert!(output.contains("test-mod is not checked against the profile's game version"));
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("test-mod-1.1.0.jar"))?,
+        "test-mod jar"
+    );
+    assert_eq!(
+        read_to_string(output_dir.join("latest-mod-1.2.0.jar"))?,
+        "latest-mod jar"
+    );
+    Ok(())
+}
+
+#[test]
+fn add_url() -> Result {
+    let server = mock_server(vec![("/files/test-mod.jar", "not a real jar".to_owned())]);
+    run_command(
+        vec!["add", &format!("url:{server}/files/test-mod.jar")],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn add_local_file() -> Result {
+    run_command(
+        vec!["add", "./tests/test_mods/Sodium.jar"],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn upgrade_stores_hash_of_changed_local_file() -> Result {
+    let config = TestConfig::new("empty_profile")?;
+    let output_dir = config.with_output_dir()?;
+    let source = output_dir.with_extension("jar");
+    write(&source, "old jar")?;
+    config.run(vec!["add", &source.to_string_lossy()])?;
+
+    write(&source, "new jar")?;
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join(source.file_name().unwrap_or_default()))?,
+        "new jar"
+    );
+    let identifier = config.read()?["profiles"][0]["external_mods"][0]["identifier"].to_string();
+    assert!(identifier.contains(&format!("{:x}", Sha256::digest("new jar"))));
+    Ok(())
+}
+
+#[test]
+fn add_local_file_not_a_jar() {
+    assert!(run_command(vec!["add", "file:./tests/util.rs"], Some("empty_profile")).is_err());
+}
+
 #[test]
 fn scan_dir() -> Result {
     run_command(
@@ -155,6 +415,14 @@ fn scan_dir() -> Result {
     )
 }
 
+#[test]
+fn scan_unknown_jar() -> Result {
+    run_command(
+        vec!["scan", "--directory", "./tests/unknown_mods"],
+        Some("empty_profile"),
+    )
+}
+
 #[test]
 fn modpack_add_modrinth() -> Result {
     // Add Fabulously Optimised
@@ -218,6 +486,782 @@ fn list_markdown() -> Result {
     )
 }
 
+#[test]
+fn list_matrix() -> Result {
+    run_command(
+        vec!["list", "--matrix", "--versions", "1.20.1,1.21.1"],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn list_matrix_csv() -> Result {
+    run_command(
+        vec![
+            "list",
+            "--matrix",
+            "--csv",
+            "--versions",
+            "1.21.1",
+            "--loaders",
+            "fabric,neoforge",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn upgrade_reads_releases_until_compatible() -> Result {
+    let releases = (0..100)
+        .map(|i| github_release(&format!("v1.{i}"), "test-mod-fabric-1.18.2.jar"))
+        .collect::<Vec<_>>();
+    let (server, requests) = recording_mock_server(vec![
+        (
+            "/repos/ferium/test-mod/releases",
+            json!(releases).to_string(),
+        ),
+        (
+            "/ferium/test-mod/releases/download/v1.0/test-mod-fabric-1.18.2.jar",
+            "new".to_owned(),
+        ),
+    ]);
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_GITHUB_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    let mut contents = config.read()?;
+    contents["profiles"][0]["mods"] = json!([
+        { "name": "Test Mod", "identifier": { "GitHubRepository": ["ferium", "test-mod"] } },
+    ]);
+    write(config.path(), contents.to_string())?;
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("test-mod-fabric-1.18.2.jar"))?,
+        "new"
+    );
+    // The first page is full, but has a compatible asset, so the next one is not needed
+    let requests = requests.lock().unwrap();
+    assert_eq!(
+        requests
+            .iter()
+            .filter(|request| request.starts_with("GET /repos/ferium/test-mod/releases"))
+            .count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn list_matrix_reads_every_page() -> Result {
+    // Only the second page of each listing has files for 1.18.2
+    let github_pages = [
+        (0..100)
+            .map(|i| github_release(&format!("v0.{i}"), "test-mod-forge-1.16.5.jar"))
+            .collect::<Vec<_>>(),
+        vec![github_release("v1.0.0", "test-mod-fabric-1.18.2.jar")],
+    ];
+    let curseforge_pages = [
+        (0..50)
+            .map(|_| curseforge_file(1, "cf-mod-forge-1.16.5.jar", "1.16.5,Forge"))
+            .collect::<Vec<_>>(),
+        vec![curseforge_file(
+            1,
+            "cf-mod-fabric-1.18.2.jar",
+            "1.18.2,Fabric",
+        )],
+    ];
+    let (server, _) = scripted_mock_server(vec![
+        (
+            "/repos/ferium/test-mod/releases",
+            github_pages
+                .iter()
+                .map(|page| MockResponse::ok(json!(page).to_string()))
+                .collect(),
+        ),
+        (
+            "/v1/mods/1/files",
+            curseforge_pages
+                .iter()
+                .enumerate()
+                .map(|(i, page)| {
+                    MockResponse::ok(
+                        json!({
+                            "data": page,
+                            "pagination": {
+                                "index": i * 50,
+                                "pageSize": 50,
+                                "resultCount": page.len(),
+                                "totalCount": 51,
+                            },
+                        })
+                        .to_string(),
+                    )
+                })
+                .collect(),
+        ),
+    ]);
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_GITHUB_URL", &server)
+        .env("FERIUM_CURSEFORGE_URL", &server);
+    let mut contents = config.read()?;
+    contents["profiles"][0]["mods"] = json!([
+        { "name": "Test Mod", "identifier": { "GitHubRepository": ["ferium", "test-mod"] } },
+        { "name": "CF Mod", "identifier": { "CurseForgeProject": 1 } },
+    ]);
+    write(config.path(), contents.to_string())?;
+
+    let output = config.run(vec![
+        "list",
+        "--matrix",
+        "--csv",
+        "--versions",
+        "1.18.2,1.18",
+    ])?;
+    assert!(output.contains("CF Mod,yes,no"));
+    // File names are only a hint, and `1.18` is not mistaken for `1.18.2`
+    assert!(output.contains("Test Mod,unknown,no"));
+    Ok(())
+}
+
+#[test]
+fn list_versions_without_matrix() {
+    assert!(run_command(
+        vec!["list", "--versions", "1.21.1"],
+        Some("one_profile_full")
+    )
+    .is_err());
+}
+
+#[test]
+fn list_inherited() -> Result {
+    run_command(vec!["list"], Some("inherited_profiles"))
+}
+
+#[test]
+fn profile_configure_missing_parent() {
+    assert!(run_command(
+        vec!["profile", "configure", "--parent", "Nonexistent"],
+        Some("inherited_profiles"),
+    )
+    .is_err());
+}
+
+#[test]
+fn delete_inherited_profile() {
+    // This should fail as the other profile inherits from it
+    assert!(run_command(
+        vec!["profile", "delete", "Base"],
+        Some("inherited_profiles")
+    )
+    .is_err());
+}
+
+#[test]
+fn profile_migrate_report() -> Result {
+    run_command(
+        vec!["profile", "migrate", "--to", "1.21.1"],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn profile_migrate_create() -> Result {
+    run_command(
+        vec![
+            "profile",
+            "migrate",
+            "--to",
+            "1.21.1",
+            "--loader",
+            "neoforge",
+            "--create",
+            "Test profile 1.21.1",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn profile_migrate_empty() {
+    assert!(run_command(
+        vec!["profile", "migrate", "--to", "1.21.1", "--update"],
+        Some("empty_profile")
+    )
+    .is_err());
+}
+
+#[test]
+fn profile_migrate_with_mock_api() -> Result {
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", &mock_modrinth());
+    config.run(vec!["add", "mock-mod", "--game-version-minor", "1.18.2"])?;
+
+    config.run(vec!["profile", "migrate", "--to", "1.19.2", "--update"])?;
+    let profile = &config.read()?["profiles"][0];
+    assert!(profile["filters"].to_string().contains("1.19.2"));
+    // The mod's own game version is changed too, and keeps its kind
+    let mod_filters = profile["mods"][0]["filters"].to_string();
+    assert!(mod_filters.contains("GameVersionMinor"));
+    assert!(mod_filters.contains("1.19.2"));
+    assert!(profile["pending_mods"].is_null());
+    Ok(())
+}
+
+#[test]
+fn profile_migrate_keeps_unavailable_mods_pending() -> Result {
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", &mock_modrinth());
+    config.run(vec!["add", "mock-mod"])?;
+
+    config.run(vec!["profile", "migrate", "--to", "1.20.1", "--update"])?;
+    let profile = &config.read()?["profiles"][0];
+    assert!(profile["mods"].as_array().map_or(true, Vec::is_empty));
+    assert_eq!(profile["pending_mods"][0]["name"], "Mock Mod");
+    Ok(())
+}
+
+#[test]
+fn profile_migrate_unreachable_api() -> Result {
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", &mock_modrinth());
+    config.run(vec!["add", "mock-mod"])?;
+
+    // Mods that cannot be checked are not mistaken for unavailable ones
+    let config = config.env("FERIUM_MODRINTH_URL", "http://127.0.0.1:1");
+    assert!(config
+        .run(vec!["profile", "migrate", "--to", "1.19.2", "--update"])
+        .is_err());
+    let profile = &config.read()?["profiles"][0];
+    assert_eq!(profile["mods"][0]["name"], "Mock Mod");
+    assert!(!profile["filters"].to_string().contains("1.19.2"));
+    Ok(())
+}
+
+/// The mock Modrinth API, with `responses` to the requests for the mod's file in turn
+fn modrinth_routes_with_file(responses: Vec<MockResponse>) -> Vec<(String, Vec<MockResponse>)> {
+    let file = "/files/mock-mod-1.0.0.jar";
+    modrinth_routes("")
+        .into_iter()
+        .filter(|(path, _)| path != file)
+        .map(|(path, body)| (path, vec![MockResponse::ok(body)]))
+        .chain([(file.to_owned(), responses)])
+        .collect()
+}
+
+/// The requests the mock server received for the mod's file
+fn file_requests(requests: &Mutex<Vec<String>>) -> Vec<String> {
+    requests
+        .lock()
+        .unwrap()
+        .iter()
+        .filter(|request| request.starts_with("GET /files/mock-mod-1.0.0.jar"))
+        .map(|request| request.to_lowercase())
+        .collect()
+}
+
+#[test]
+fn upgrade_resumes_interrupted_download() -> Result {
+    let (server, requests) = scripted_mock_server(modrinth_routes_with_file(vec![
+        MockResponse::ok("j").truncated(3),
+        MockResponse::ok("jar").with_ranges(),
+    ]));
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("mock-mod-1.0.0.jar"))?,
+        "jar"
+    );
+    assert!(!output_dir.join("mock-mod-1.0.0.jar.part").exists());
+    let file_requests = file_requests(&requests);
+    assert_eq!(file_requests.len(), 2);
+    // Only the rest of the file is requested again
+    assert!(!file_requests[0].contains("range:"));
+    assert!(file_requests[1].contains("range: bytes=1-"));
+    Ok(())
+}
+
+#[test]
+fn upgrade_retries_failed_download() -> Result {
+    let (server, requests) = scripted_mock_server(modrinth_routes_with_file(vec![
+        MockResponse::status("503 Service Unavailable"),
+        MockResponse::ok("jar"),
+    ]));
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("mock-mod-1.0.0.jar"))?,
+        "jar"
+    );
+    assert_eq!(file_requests(&requests).len(), 2);
+    Ok(())
+}
+
+#[test]
+fn retries_network_setting() -> Result {
+    let (server, requests) = scripted_mock_server(modrinth_routes_with_file(vec![
+        MockResponse::status("503 Service Unavailable"),
+        MockResponse::ok("jar"),
+    ]));
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    let mut settings = config.read()?;
+    settings["network"] = json!({ "retries": 0 });
+    write(config.path(), settings.to_string())?;
+
+    assert!(config.run(vec!["upgrade"]).is_err());
+    assert!(!output_dir.join("mock-mod-1.0.0.jar").exists());
+    assert_eq!(file_requests(&requests).len(), 1);
+    Ok(())
+}
+
+/// A Modrinth API with a mod whose newest version is for another game version
+/// The routes of a Modrinth API at `base` with one mod, `mock-mod`,
+/// which has files for 1.19.2 and 1.18.2
+fn modrinth_routes(base: &str) -> Vec<(String, String)> {
+    vec![
+        (
+            format!("{base}/v2/project/mock-mod"),
+            modrinth_project("MOCKMOD1", "mock-mod", "Mock Mod").to_string(),
+        ),
+        (
+            format!("{base}/v2/project/MOCKMOD1/version"),
+            format!(
+                "[{}, {}]",
+                modrinth_version(
+                    "MOCKMOD1",
+                    "mock-mod-1.1.0.jar",
+                    "1.19.2",
+                    "fabric",
+                    "release",
+                    "2024-02-01T00:00:00Z"
+                ),
+                modrinth_version(
+                    "MOCKMOD1",
+                    "mock-mod-1.0.0.jar",
+                    "1.18.2",
+                    "fabric",
+                    "release",
+                    "2024-01-01T00:00:00Z"
+                ),
+            ),
+        ),
+        (format!("{base}/files/mock-mod-1.0.0.jar"), "jar".to_owned()),
+    ]
+}
+
+fn mock_modrinth() -> String {
+    mock_server(modrinth_routes(""))
+}
+
+#[test]
+fn add_and_upgrade_with_mock_api() -> Result {
+    let server = mock_modrinth();
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+
+    config.run(vec!["add", "mock-mod"])?;
+    let mod_ = &config.read()?["profiles"][0]["mods"][0];
+    assert_eq!(mod_["name"], "Mock Mod");
+    assert_eq!(mod_["identifier"]["ModrinthProject"], "MOCKMOD1");
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("mock-mod-1.0.0.jar"))?,
+        "jar"
+    );
+    assert!(!output_dir.join("mock-mod-1.1.0.jar").exists());
+    Ok(())
+}
+
+#[test]
+fn status() -> Result {
+    let (server, _) = scripted_mock_server(vec![
+        (
+            "/v2/tag/loader",
+            vec![MockResponse::ok("[]")
+                .header("X-RateLimit-Limit", "300")
+                .header("X-RateLimit-Remaining", "299")
+                .header("X-RateLimit-Reset", "30")],
+        ),
+        // GitHub reports that its quota has run out with a 403 at first
+        (
+            "/rate_limit",
+            vec![
+                MockResponse::status("403 Forbidden")
+                    .header("X-RateLimit-Remaining", "0")
+                    .header("X-RateLimit-Reset", "1"),
+                MockResponse::ok(
+                    r#"{"resources": {"core": {"limit": 60, "remaining": 59, "reset": 30}}}"#,
+                ),
+            ],
+        ),
+        (
+            "/api/v4/projects",
+            vec![MockResponse::ok("[]")
+                .header("RateLimit-Limit", "2000")
+                .header("RateLimit-Remaining", "1999")],
+        ),
+    ]);
+    let config = TestConfig::new("empty")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_GITHUB_URL", &server)
+        .env("FERIUM_GITLAB_URL", &server)
+        .env("FERIUM_FORGEJO_URL", &server);
+
+    let output = config.run(vec!["status"])?;
+    assert!(output.contains("299/300 requests left"));
+    assert!(output.contains("59/60 requests left"));
+    assert!(output.contains("1999/2000 requests left"));
+    // Forgejo does not report its rate limit
+    assert!(output.contains("Not reported"));
+    Ok(())
+}
+
+#[test]
+fn rate_limit_too_long_to_wait() -> Result {
+    let (server, requests) = scripted_mock_server(vec![(
+        "/rate_limit",
+        vec![MockResponse::status("403 Forbidden")
+            .header("X-RateLimit-Remaining", "0")
+            .header("X-RateLimit-Reset", "86400")],
+    )]);
+    let config = TestConfig::new("empty")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_GITHUB_URL", &server)
+        .env("FERIUM_GITLAB_URL", &server)
+        .env("FERIUM_FORGEJO_URL", &server);
+
+    let output = config.run(vec!["status"])?;
+    assert!(output.contains("resets in 1440 minutes"));
+    // GitHub is not asked again when its rate limit resets too far in the future
+    assert_eq!(
+        requests
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request.starts_with("GET /rate_limit"))
+            .count(),
+        1
+    );
+    Ok(())
+}
+
+#[test]
+fn invalid_api_url() {
+    assert!(run_command(vec!["profile", "list"], Some("invalid_api_url")).is_err());
+}
+
+#[test]
+fn network_flags() -> Result {
+    run_command(
+        vec![
+            "--connect-timeout",
+            "30",
+            "--user-agent-suffix",
+            "test",
+            "profile",
+            "list",
+        ],
+        Some("empty_profile"),
+    )
+}
+
+#[test]
+fn missing_ca_certificate() {
+    assert!(run_command(
+        vec!["--ca-certificate", "./tests/missing.pem", "profile", "list"],
+        Some("empty_profile"),
+    )
+    .is_err());
+}
+
+#[test]
+fn config_migrate() -> Result {
+    run_command(vec!["config", "migrate"], Some("one_profile_full"))
+}
+
+#[test]
+fn config_migrate_writes_backup_and_filters() -> Result {
+    let config = TestConfig::new("old_version")?;
+    config.run(vec!["config", "migrate"])?;
+
+    assert_eq!(
+        read_to_string(format!("{}.v0.bak", config.path()))?,
+        read_to_string("./tests/configs/old_version.json")?
+    );
+    let migrated = config.read()?;
+    assert_eq!(migrated["version"], 2);
+    let profile = &migrated["profiles"][0];
+    assert!(profile["game_version"].is_null());
+    assert!(profile["mod_loader"].is_null());
+    let filters = profile["filters"].to_string();
+    assert!(filters.contains("ModLoaderPrefer"));
+    assert!(filters.contains("Quilt") && filters.contains("Fabric"));
+    assert!(filters.contains("GameVersionStrict") && filters.contains("1.18.2"));
+
+    // The mod that skipped the version check only keeps the loader filter
+    let unchecked = &profile["mods"][0];
+    assert_eq!(unchecked["override_filters"], true);
+    let unchecked_filters = unchecked["filters"].to_string();
+    assert!(unchecked_filters.contains("ModLoaderPrefer"));
+    assert!(!unchecked_filters.contains("GameVersion"));
+    assert!(unchecked["check_game_version"].is_null());
+
+    // Redundant checks are removed without adding filters
+    let checked = &profile["mods"][1];
+    assert!(checked["check_mod_loader"].is_null());
+    assert_ne!(checked["override_filters"], true);
+    Ok(())
+}
+
+#[test]
+fn config_migrate_keeps_profile_filters_of_mods() -> Result {
+    let config = TestConfig::new("current_version")?;
+    let mut contents = config.read()?;
+    contents["version"] = json!(1);
+    contents["profiles"][0]["mods"] = json!([
+        {
+            "name": "Replacing Mod",
+            "identifier": { "ModrinthProject": "AANobbMI" },
+            "filters": [{ "GameVersionStrict": ["1.18.1"] }],
+        },
+        {
+            "name": "Other Mod",
+            "identifier": { "ModrinthProject": "H8CaAYZC" },
+            "filters": [{ "Filename": "fabric" }],
+        },
+    ]);
+    write(config.path(), contents.to_string())?;
+    config.run(vec!["config", "migrate"])?;
+
+    let mods = &config.read()?["profiles"][0]["mods"];
+    // Both version filters are still used, like before mods' filters replaced the profile's
+    assert_eq!(mods[0]["override_filters"], true);
+    let filters = mods[0]["filters"].to_string();
+    assert!(filters.contains("1.18.2") && filters.contains("1.18.1"));
+    assert!(filters.contains("ModLoaderPrefer"));
+    // Filters of other kinds are used together with the profile's anyway
+    assert_ne!(mods[1]["override_filters"], true);
+    assert_eq!(mods[1]["filters"], json!([{ "Filename": "fabric" }]));
+    Ok(())
+}
+
+#[test]
+fn config_migrate_backs_up_once() -> Result {
+    let config = TestConfig::new("old_version")?;
+    let backup = format!("{}.v0.bak", config.path());
+    // A failing command does not save the migrated config, so the next one migrates it again
+    assert!(config.run(vec!["remove", "Nonexistent mod"]).is_err());
+    write(&backup, "first backup")?;
+    assert!(config.run(vec!["remove", "Nonexistent mod"]).is_err());
+    assert_eq!(read_to_string(&backup)?, "first backup");
+    Ok(())
+}
+
+#[test]
+fn config_migrate_check() -> Result {
+    // An old config needs to be migrated
+    assert!(run_command(
+        vec!["config", "migrate", "--check"],
+        Some("one_profile_full")
+    )
+    .is_err());
+    run_command(
+        vec!["config", "migrate", "--check"],
+        Some("current_version"),
+    )
+}
+
+#[test]
+fn invalid_config_names_profile() {
+    let err = run_command(vec!["profile", "list"], Some("invalid_profile")).unwrap_err();
+    assert!(err.to_string().contains("Broken profile"));
+}
+
+#[test]
+fn workspace_list() -> Result {
+    run_in_workspace(vec!["list"], "workspace")
+}
+
+#[test]
+fn workspace_add() -> Result {
+    run_in_workspace(vec!["add", "591388"], "workspace")
+}
+
+/// A workspace file with only the settings of the profile
+const EMPTY_WORKSPACE: &str = r#"version = 1
+name = "Server"
+output_dir = "mods"
+
+[[filters]]
+ModLoaderPrefer = ["Fabric"]
+
+[[filters]]
+GameVersionStrict = ["1.18.2"]
+"#;
+
+#[test]
+fn workspace_add_rewrites_file() -> Result {
+    let server = mock_modrinth();
+    let workspace = TestWorkspace::new("workspace")?.env("FERIUM_MODRINTH_URL", &server);
+    workspace.run(vec!["add", "mock-mod"])?;
+
+    let file = workspace.read()?;
+    assert_eq!(file["name"].as_str(), Some("Server"));
+    // The output directory is kept relative to the file
+    assert_eq!(file["output_dir"].as_str(), Some("mods"));
+    let mods = file["mods"].as_array().cloned().unwrap_or_default();
+    assert_eq!(mods.len(), 2);
+    assert_eq!(mods[1]["name"].as_str(), Some("Mock Mod"));
+    Ok(())
+}
+
+#[test]
+fn workspace_keeps_api_and_network_settings() -> Result {
+    let server = mock_modrinth();
+    let workspace = TestWorkspace::new("workspace")?;
+    workspace.write(&format!(
+        "{EMPTY_WORKSPACE}\n[api]\nmodrinth = \"{server}\"\n\n[network]\nretries = 2\n"
+    ))?;
+
+    // The mod can only be found if the API URL in the file is used
+    workspace.run(vec!["add", "mock-mod"])?;
+    let file = workspace.read()?;
+    assert_eq!(file["api"]["modrinth"].as_str(), Some(server.as_str()));
+    assert_eq!(file["network"]["retries"].as_integer(), Some(2));
+    assert_eq!(file["mods"][0]["name"].as_str(), Some("Mock Mod"));
+    Ok(())
+}
+
+#[test]
+fn workspace_paths_are_relative_to_file() -> Result {
+    let server = mock_modrinth();
+    let workspace = TestWorkspace::new("workspace")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    workspace.write(EMPTY_WORKSPACE)?;
+    let directory = workspace.directory();
+    create_dir_all(directory.join("mods"))?;
+    create_dir_all(directory.join("local"))?;
+    write(directory.join("local").join("local-mod.jar"), "local jar")?;
+
+    // Commands are run in the `server` subdirectory
+    workspace.run(vec![
+        "--config-file",
+        "../ferium.toml",
+        "add",
+        "mock-mod",
+        "../local/local-mod.jar",
+    ])?;
+    workspace.run(vec!["--config-file", "../ferium.toml", "upgrade"])?;
+    assert!(directory.join("mods").join("mock-mod-1.0.0.jar").exists());
+    assert!(directory.join("mods").join("local-mod.jar").exists());
+
+    let file = workspace.read()?;
+    assert_eq!(file["output_dir"].as_str(), Some("mods"));
+    assert_eq!(
+        file["external_mods"][0]["identifier"]["LocalFile"]["path"].as_str(),
+        Some("local/local-mod.jar")
+    );
+    Ok(())
+}
+
+#[test]
+fn workspace_rejects_other_profiles_and_modpacks() -> Result {
+    let workspace = TestWorkspace::new("workspace")?;
+    let original = workspace.read()?;
+    for args in [
+        vec!["modpack", "add", "1121137"],
+        vec![
+            "profile", "create", "--name", "Other", "-v", "1.18.2", "-m", "fabric",
+        ],
+    ] {
+        let err = workspace.run(args).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("can only contain a single profile"));
+    }
+    assert_eq!(workspace.read()?, original);
+    Ok(())
+}
+
+#[test]
+fn doctor_shared_output_dir() {
+    assert!(run_command(vec!["doctor"], Some("two_profiles_one_empty")).is_err());
+}
+
+#[test]
+fn doctor_fix_old_backups() -> Result {
+    let config = TestConfig::new("empty_profile")?;
+    let output_dir = config.with_output_dir()?;
+    // One more upgrade than is kept by default, and a backup that is not of an upgrade
+    create_backups(
+        &output_dir,
+        &[
+            "2024-01-01_00-00-00",
+            "2024-01-02_00-00-00",
+            "2024-01-03_00-00-00",
+            "2024-01-04_00-00-00_doctor",
+            "2024-01-05_00-00-00",
+            "2024-01-06_00-00-00",
+            "2024-01-07_00-00-00",
+        ],
+    )?;
+
+    config.run(vec!["doctor", "--fix"])?;
+    let old = output_dir.join(".old");
+    assert!(!old.join("2024-01-01_00-00-00").exists());
+    for kept in [
+        "2024-01-02_00-00-00",
+        "2024-01-03_00-00-00",
+        "2024-01-04_00-00-00_doctor",
+        "2024-01-05_00-00-00",
+        "2024-01-06_00-00-00",
+        "2024-01-07_00-00-00",
+    ] {
+        assert!(old.join(kept).exists(), "{kept} was deleted");
+    }
+    Ok(())
+}
+
+#[test]
+fn doctor_reports_resolution_errors() -> Result {
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", &mock_modrinth());
+    config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+
+    // The mod cannot be reached, which is not a problem with the profile's filters
+    let config = config.env("FERIUM_MODRINTH_URL", "http://127.0.0.1:1");
+    let output = match config.run(vec!["doctor"]) {
+        Ok(output) => output,
+        Err(err) => err.to_string(),
+    };
+    assert!(output.contains("Mock Mod could not be resolved in profile"));
+    assert!(!output.contains("Change the profile's game versions"));
+    Ok(())
+}
+
+#[test]
+fn doctor_fix_missing_output_dir() -> Result {
+    let _ = remove_dir("./tests/doctor_mods");
+    run_command(vec!["doctor", "--fix"], Some("missing_output_dir"))?;
+    assert!(Path::new("./tests/doctor_mods").is_dir());
+    Ok(())
+}
+
 #[test]
 fn profile_list() -> Result {
     run_command(vec!["profile", "list"], Some("one_profile_full"))
@@ -229,6 +1273,174 @@ fn upgrade() -> Result {
     run_command(vec!["upgrade"], Some("one_profile_full"))
 }
 
+#[test]
+fn upgrade_no_retries() -> Result {
+    let _ = remove_dir("./tests/mods");
+    run_command(vec!["--retries", "0", "upgrade"], Some("one_profile_full"))
+}
+
+#[test]
+fn upgrade_all() -> Result {
+    let _ = remove_dir("./tests/mods");
+    // The empty profile should be skipped
+    run_command(vec!["upgrade", "--all"], Some("two_profiles_one_empty"))
+}
+
+#[test]
+fn upgrade_all_resolves_before_downloading() -> Result {
+    let (server, requests) = recording_mock_server(modrinth_routes(""));
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    let second_dir = output_dir.with_extension("second");
+    create_dir_all(&second_dir)?;
+    let mut contents = config.read()?;
+    let mut second = contents["profiles"][0].clone();
+    second["name"] = json!("Second profile");
+    second["output_dir"] = json!(second_dir);
+    contents["profiles"] = json!([contents["profiles"][0], second]);
+    write(config.path(), contents.to_string())?;
+    requests.lock().unwrap().clear();
+
+    config.run(vec!["upgrade", "--all"])?;
+    let requests = requests.lock().unwrap();
+    let first_download = requests
+        .iter()
+        .position(|request| request.starts_with("GET /files/"))
+        .unwrap_or_default();
+    // Both profiles are resolved before any of their files are downloaded
+    assert!(requests[first_download..]
+        .iter()
+        .all(|request| request.starts_with("GET /files/mock-mod-1.0.0.jar")));
+    assert_eq!(requests.len() - first_download, 2);
+    assert!(output_dir.join("mock-mod-1.0.0.jar").exists());
+    assert!(second_dir.join("mock-mod-1.0.0.jar").exists());
+    Ok(())
+}
+
+#[test]
+fn upgrade_yes() -> Result {
+    let _ = remove_dir("./tests/mods");
+    run_command(vec!["upgrade", "--yes"], Some("one_profile_full"))
+}
+
+#[test]
+fn profile_untracked_files() -> Result {
+    run_command(
+        vec!["profile", "configure", "--untracked-files", "keep"],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn upgrade_profiles_missing() {
+    assert!(run_command(
+        vec!["upgrade", "--profiles", "Profile One,Nonexistent"],
+        Some("two_profiles_one_empty")
+    )
+    .is_err());
+}
+
+#[test]
+fn profile_keep_old() -> Result {
+    let config = TestConfig::new("one_profile_full")?;
+    config.run(vec!["profile", "configure", "--keep-old-days", "30"])?;
+    assert_eq!(config.read()?["profiles"][0]["keep_old"]["days"], 30);
+    Ok(())
+}
+
+#[test]
+fn profile_keep_no_old_upgrades() -> Result {
+    let config = TestConfig::new("one_profile_full")?;
+    assert!(config
+        .run(vec!["profile", "configure", "--keep-old-upgrades", "0"])
+        .is_err());
+    Ok(())
+}
+
+/// Create backups called `names` in the `.old` directory of `output_dir`, each with a file `{name}.jar`
+fn create_backups(output_dir: &Path, names: &[&str]) -> Result {
+    for name in names {
+        let backup = output_dir.join(".old").join(name);
+        create_dir_all(&backup)?;
+        write(backup.join(format!("{name}.jar")), "jar")?;
+    }
+    Ok(())
+}
+
+#[test]
+fn old_list() -> Result {
+    let config = TestConfig::new("one_profile_full")?;
+    let output_dir = config.with_output_dir()?;
+    create_backups(&output_dir, &["2024-01-01_00-00-00"])?;
+
+    let output = config.run(vec!["old", "list"])?;
+    assert!(output.contains("2024-01-01_00-00-00"));
+    assert!(output.contains("2024-01-01_00-00-00.jar"));
+    Ok(())
+}
+
+#[test]
+fn old_purge() -> Result {
+    let config = TestConfig::new("one_profile_full")?;
+    let output_dir = config.with_output_dir()?;
+    create_backups(
+        &output_dir,
+        &[
+            "2024-01-01_00-00-00",
+            "2024-01-02_00-00-00",
+            "2024-01-03_00-00-00_restore",
+            "2024-01-04_00-00-00",
+        ],
+    )?;
+    config.run(vec!["profile", "configure", "--keep-old-upgrades", "2"])?;
+
+    config.run(vec!["old", "purge"])?;
+    let old = output_dir.join(".old");
+    assert!(!old.join("2024-01-01_00-00-00").exists());
+    // The backup of the restore does not count as an upgrade
+    assert!(old.join("2024-01-02_00-00-00").exists());
+    assert!(old.join("2024-01-03_00-00-00_restore").exists());
+    assert!(old.join("2024-01-04_00-00-00").exists());
+    Ok(())
+}
+
+#[test]
+fn old_restore() -> Result {
+    let config = TestConfig::new("one_profile_full")?;
+    let output_dir = config.with_output_dir()?;
+    create_backups(&output_dir, &["2024-01-01_00-00-00"])?;
+
+    config.run(vec!["old", "restore", "2024-01-01_00-00-00.jar"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("2024-01-01_00-00-00.jar"))?,
+        "jar"
+    );
+    // The backup is deleted once it is empty
+    assert!(!output_dir.join(".old").join("2024-01-01_00-00-00").exists());
+    Ok(())
+}
+
+#[test]
+fn old_restore_missing() {
+    assert!(run_command(
+        vec!["old", "restore", "nonexistent-mod-1.0.0.jar"],
+        Some("one_profile_full")
+    )
+    .is_err());
+}
+
+#[test]
+fn modpack_upgrade_modpacks_missing() {
+    assert!(run_command(
+        vec!["modpack", "upgrade", "--modpacks", "Nonexistent"],
+        Some("two_modpacks_mdactive")
+    )
+    .is_err());
+}
+
 #[test]
 fn cf_modpack_upgrade() -> Result {
     let _ = remove_dir("./tests/cf_modpack");
@@ -241,6 +1453,34 @@ fn md_modpack_upgrade() -> Result {
     run_command(vec!["modpack", "upgrade"], Some("two_modpacks_mdactive"))
 }
 
+#[test]
+fn add_to_other_profile() -> Result {
+    run_command(
+        vec![
+            "add",
+            "--profile",
+            "Profile Two",
+            "./tests/test_mods/Sodium.jar",
+        ],
+        Some("two_profiles_one_empty"),
+    )
+}
+
+#[test]
+fn list_other_profile() {
+    // The targeted profile is empty, unlike the active one
+    assert!(run_command(
+        vec!["list", "--profile", "Profile Two"],
+        Some("two_profiles_one_empty")
+    )
+    .is_err());
+    assert!(run_command(
+        vec!["list", "--profile", "Nonexistent"],
+        Some("two_profiles_one_empty")
+    )
+    .is_err());
+}
+
 #[test]
 fn profile_switch() -> Result {
     run_command(
@@ -308,6 +1548,197 @@ fn remove_id() -> Result {
     )
 }
 
+#[test]
+fn disable_mods() -> Result {
+    run_command(
+        vec!["disable", "starlight (fabric)", "591388"],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn disable_renames_installed_file() -> Result {
+    let server = mock_modrinth();
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    config.run(vec!["upgrade"])?;
+
+    // The installed file is looked up without any requests
+    let config = config.env("FERIUM_MODRINTH_URL", "http://127.0.0.1:1");
+    config.run(vec!["disable", "MOCKMOD1"])?;
+    assert!(!output_dir.join("mock-mod-1.0.0.jar").exists());
+    assert!(output_dir.join("mock-mod-1.0.0.jar.disabled").exists());
+
+    // Only the files of disabled mods are left alone
+    config.run(vec!["profile", "configure", "--untracked-files", "move"])?;
+    write(output_dir.join("other-mod-1.0.0.jar.disabled"), "jar")?;
+    config.run(vec!["upgrade"])?;
+    assert!(output_dir.join("mock-mod-1.0.0.jar.disabled").exists());
+    assert!(is_backed_up(&output_dir, "other-mod-1.0.0.jar.disabled")?);
+
+    config.run(vec!["enable", "MOCKMOD1"])?;
+    assert!(output_dir.join("mock-mod-1.0.0.jar").exists());
+    assert!(!output_dir.join("mock-mod-1.0.0.jar.disabled").exists());
+    Ok(())
+}
+
+#[test]
+fn remove_forgets_disabled_and_grouped_mod() -> Result {
+    let config = TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", &mock_modrinth());
+    config.run(vec!["add", "mock-mod"])?;
+    config.run(vec!["disable", "MOCKMOD1"])?;
+    config.run(vec!["group", "add", "extras", "MOCKMOD1"])?;
+
+    config.run(vec!["remove", "MOCKMOD1"])?;
+    let profile = &config.read()?["profiles"][0];
+    assert!(profile["mods"].as_array().map_or(true, Vec::is_empty));
+    assert!(profile["disabled"].as_array().map_or(true, Vec::is_empty));
+    assert!(profile["groups"][0]["mods"]
+        .as_array()
+        .map_or(true, Vec::is_empty));
+    Ok(())
+}
+
+#[test]
+fn enable_enabled_mod() {
+    assert!(run_command(vec!["enable", "incendium"], Some("one_profile_full")).is_err());
+}
+
+#[test]
+fn mod_configure() -> Result {
+    run_command(
+        vec![
+            "mod",
+            "configure",
+            "Incendium",
+            "--release-channel",
+            "beta",
+            "--filename",
+            "fabric",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn mod_filters_replace_profile_filters() -> Result {
+    let mut routes = modrinth_routes("");
+    routes.push(("/files/mock-mod-1.1.0.jar".to_owned(), "new jar".to_owned()));
+    let server = mock_server(routes);
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+
+    // The mod's game version is used instead of the profile's, rather than as well as it
+    config.run(vec!["add", "mock-mod", "--game-version-strict", "1.19.2"])?;
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        read_to_string(output_dir.join("mock-mod-1.1.0.jar"))?,
+        "new jar"
+    );
+    assert!(!output_dir.join("mock-mod-1.0.0.jar").exists());
+    Ok(())
+}
+
+#[test]
+fn mod_configure_invalid_pattern() {
+    assert!(run_command(
+        vec!["mod", "configure", "Incendium", "--filename", "fabric["],
+        Some("one_profile_full")
+    )
+    .is_err());
+}
+
+#[test]
+fn profile_configure_filters() -> Result {
+    run_command(
+        vec![
+            "profile",
+            "configure",
+            "--release-channel",
+            "beta",
+            "--filename",
+            "fabric",
+            "--remove-filter",
+            "title",
+            "--filter-order",
+            "filename,release-channel",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn profile_configure_invalid_pattern() {
+    assert!(run_command(
+        vec!["profile", "configure", "--title", "["],
+        Some("one_profile_full")
+    )
+    .is_err());
+}
+
+#[test]
+fn profile_channel_fallback() -> Result {
+    run_command(
+        vec![
+            "profile",
+            "configure",
+            "--release-channel",
+            "release",
+            "--channel-fallback",
+            "beta",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn mod_channel_fallback() -> Result {
+    run_command(
+        vec![
+            "mod",
+            "configure",
+            "Incendium",
+            "--channel-fallback",
+            "release",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn group_add() -> Result {
+    run_command(
+        vec![
+            "group",
+            "add",
+            "Performance",
+            "starlight (fabric)",
+            "sodium-fabric",
+        ],
+        Some("one_profile_full"),
+    )
+}
+
+#[test]
+fn group_enable_missing() {
+    assert!(run_command(vec!["group", "enable", "Shaders"], Some("grouped_profile")).is_err());
+}
+
+#[test]
+fn list_groups() -> Result {
+    run_command(vec!["list", "--groups"], Some("grouped_profile"))
+}
+
+#[test]
+fn tui_without_terminal() {
+    assert!(run_command(vec!["tui"], Some("one_profile_full")).is_err());
+}
+
 #[test]
 fn delete_profile() -> Result {
     run_command(
@@ -323,3 +1754,169 @@ fn delete_modpack() -> Result {
         Some("two_modpacks_mdactive"),
     )
 }
+
+#[test]
+fn upgrade_removes_only_part_files() -> Result {
+    let server = mock_modrinth();
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    config.run(vec!["profile", "configure", "--untracked-files", "keep"])?;
+    write(output_dir.join("counterpart.jar"), "jar")?;
+    write(output_dir.join("other-mod-1.0.0.jar.part"), "ja")?;
+
+    config.run(vec!["upgrade"])?;
+    assert!(output_dir.join("counterpart.jar").exists());
+    assert!(!output_dir.join("other-mod-1.0.0.jar.part").exists());
+    let installed: serde_json::Value =
+        serde_json::from_str(&read_to_string(output_dir.join(".ferium-installed.json"))?)?;
+    assert_eq!(installed["kept"][0], "counterpart.jar");
+    Ok(())
+}
+
+/// Whether a backup in the `.old` directory of `output_dir` has the file `filename`
+fn is_backed_up(output_dir: &Path, filename: &str) -> std::io::Result<bool> {
+    for backup in read_dir(output_dir.join(".old"))? {
+        if backup?.path().join(filename).exists() {
+            return Ok(true);
+        }
+    }
+    Ok(false)
+}
+
+#[test]
+fn upgrade_moves_untracked_files() -> Result {
+    let server = mock_modrinth();
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    config.run(vec!["profile", "configure", "--untracked-files", "move"])?;
+    write(output_dir.join("stranger.jar"), "jar")?;
+
+    config.run(vec!["upgrade"])?;
+    assert!(!output_dir.join("stranger.jar").exists());
+    assert!(is_backed_up(&output_dir, "stranger.jar")?);
+    Ok(())
+}
+
+#[test]
+fn upgrade_adopts_untracked_files() -> Result {
+    let hash = format!("{:x}", Sha512::digest("old jar"));
+    let mut routes = modrinth_routes("");
+    routes.extend([
+        (
+            "/v2/version_files".to_owned(),
+            format!(
+                r#"{{"{hash}": {}}}"#,
+                modrinth_version(
+                    "OTHERMOD",
+                    "other-mod-1.0.0.jar",
+                    "1.18.2",
+                    "fabric",
+                    "release",
+                    "2024-01-01T00:00:00Z"
+                )
+            ),
+        ),
+        (
+            "/v1/fingerprints/432".to_owned(),
+            r#"{"data": {"exactMatches": []}}"#.to_owned(),
+        ),
+        (
+            "/v2/project/OTHERMOD".to_owned(),
+            modrinth_project("OTHERMOD", "other-mod", "Other Mod").to_string(),
+        ),
+        (
+            "/v2/project/OTHERMOD/version".to_owned(),
+            format!(
+                "[{}]",
+                modrinth_version(
+                    "OTHERMOD",
+                    "other-mod-2.0.0.jar",
+                    "1.18.2",
+                    "fabric",
+                    "release",
+                    "2024-03-01T00:00:00Z"
+                )
+            ),
+        ),
+        (
+            "/files/other-mod-2.0.0.jar".to_owned(),
+            "new jar".to_owned(),
+        ),
+    ]);
+    let server = mock_server(routes);
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_CURSEFORGE_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    let output_dir = config.with_output_dir()?;
+    config.run(vec!["add", "mock-mod"])?;
+    config.run(vec!["profile", "configure", "--untracked-files", "adopt"])?;
+    write(output_dir.join("other-mod-1.0.0.jar"), "old jar")?;
+
+    config.run(vec!["upgrade"])?;
+    assert_eq!(
+        config.read()?["profiles"][0]["mods"][1]["name"],
+        "Other Mod"
+    );
+    // The adopted file is replaced by the latest file of its mod straight away
+    assert_eq!(
+        read_to_string(output_dir.join("other-mod-2.0.0.jar"))?,
+        "new jar"
+    );
+    assert!(!output_dir.join("other-mod-1.0.0.jar").exists());
+    assert!(is_backed_up(&output_dir, "other-mod-1.0.0.jar")?);
+    Ok(())
+}
+
+#[test]
+fn user_agent_suffix_is_sent() -> Result {
+    let (server, requests) = recording_mock_server(modrinth_routes(""));
+    let config = TestConfig::new("empty_profile")?
+        .env("FERIUM_MODRINTH_URL", &server)
+        .env("FERIUM_DOWNLOAD_HOST", &server);
+    config.with_output_dir()?;
+
+    config.run(vec![
+        "--user-agent-suffix",
+        "test-suffix",
+        "add",
+        "mock-mod",
+    ])?;
+    config.run(vec!["--user-agent-suffix", "test-suffix", "upgrade"])?;
+    let requests = requests.lock().unwrap();
+    // Downloads are sent using the same client as API requests
+    assert!(requests
+        .iter()
+        .any(|request| request.starts_with("GET /files/mock-mod-1.0.0.jar")));
+    for request in requests.iter() {
+        let user_agent = request
+            .lines()
+            .find_map(|line| {
+                line.to_lowercase()
+                    .strip_prefix("user-agent:")
+                    .map(str::to_owned)
+            })
+            .unwrap_or_default();
+        assert!(user_agent.trim().starts_with("ferium/"), "{request}");
+        assert!(user_agent.trim().ends_with(" test-suffix"), "{request}");
+    }
+    Ok(())
+}
+
+#[test]
+fn requests_go_through_proxy() -> Result {
+    // The API is not reachable, so the add only succeeds if the proxy is used
+    let proxy = mock_server(modrinth_routes("http://modrinth.invalid"));
+    let config =
+        TestConfig::new("empty_profile")?.env("FERIUM_MODRINTH_URL", "http://modrinth.invalid");
+
+    config.run(vec!["--proxy", &proxy, "add", "mock-mod"])?;
+    assert_eq!(config.read()?["profiles"][0]["mods"][0]["name"], "Mock Mod");
+    Ok(())
+}
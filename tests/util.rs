@@ -1,7 +1,13 @@
+use serde_json::{json, Value};
 use std::{
-    fs::{copy, create_dir},
-    io::Result,
-    process::Command,
+    collections::HashMap,
+    fs::{copy, create_dir, create_dir_all, read_to_string, write},
+    io::{BufRead, BufReader, Result, Write},
+    net::TcpListener,
+    path::{Path, PathBuf},
+    process::{Command, Output},
+    sync::{Arc, Mutex},
+    thread,
 };
 
 pub fn run_command(args: Vec<&str>, config_file: Option<&str>) -> Result<()> {
@@ -19,7 +25,136 @@ pub fn run_command(args: Vec<&str>, config_file: Option<&str>) -> Result<()> {
     arguments.push(&running);
     arguments.append(&mut args);
     command.args(arguments);
-    let output = command.output()?;
+    check_output(&command.output()?)
+}
+
+/// Копия шаблона `tests/configs/{template}.json`, с которой можно запустить несколько команд подряд
+pub struct TestConfig {
+    path: String,
+    envs: Vec<(String, String)>,
+}
+
+impl TestConfig {
+    pub fn new(template: &str) -> Result<Self> {
+        let _ = create_dir("./tests/configs/running");
+        let path = format!("./tests/configs/running/{}.json", rand::random::<u16>());
+        copy(format!("./tests/configs/{template}.json"), &path)?;
+        Ok(Self {
+            path,
+            envs: Vec::new(),
+        })
+    }
+
+    /// Путь к конфигурации
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// Задаёт переменную окружения для всех команд
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Перенаправляет папки вывода всех профилей в новую пустую папку и возвращает её
+    pub fn with_output_dir(&self) -> Result<PathBuf> {
+        let directory = PathBuf::from(format!("{}-mods", self.path.trim_end_matches(".json")));
+        create_dir_all(&directory)?;
+        let directory = directory.canonicalize()?;
+        let mut config = self.read()?;
+        if let Some(profiles) = config["profiles"].as_array_mut() {
+            for profile in profiles {
+                profile["output_dir"] = json!(directory);
+            }
+        }
+        write(&self.path, config.to_string())?;
+        Ok(directory)
+    }
+
+    /// Запускает ferium с этой конфигурацией и возвращает его вывод
+    pub fn run(&self, args: Vec<&str>) -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_ferium"))
+            .arg("--config-file")
+            .arg(&self.path)
+            .args(args)
+            .envs(self.envs.clone())
+            .output()?;
+        check_output(&output)?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Читает конфигурацию, которую записал ferium
+    pub fn read(&self) -> Result<Value> {
+        Ok(serde_json::from_str(&read_to_string(&self.path)?)?)
+    }
+}
+
+/// Запускает ferium в подпапке папки с копией `tests/configs/{workspace}.toml` под названием `ferium.toml`
+///
+/// Путь к конфигурации не передаётся, поэтому ferium должен сам найти файл в родительской папке
+pub fn run_in_workspace(args: Vec<&str>, workspace: &str) -> Result<()> {
+    TestWorkspace::new(workspace)?.run(args).map(drop)
+}
+
+/// Папка с копией `tests/configs/{template}.toml` под названием `ferium.toml`, в которой можно запустить несколько команд подряд
+pub struct TestWorkspace {
+    directory: PathBuf,
+    envs: Vec<(String, String)>,
+}
+
+impl TestWorkspace {
+    pub fn new(template: &str) -> Result<Self> {
+        let directory = PathBuf::from(format!(
+            "./tests/configs/running/workspace-{}",
+            rand::random::<u16>()
+        ));
+        create_dir_all(directory.join("server"))?;
+        copy(
+            format!("./tests/configs/{template}.toml"),
+            directory.join("ferium.toml"),
+        )?;
+        Ok(Self {
+            directory: directory.canonicalize()?,
+            envs: Vec::new(),
+        })
+    }
+
+    /// Абсолютный путь к папке с `ferium.toml`
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    /// Задаёт переменную окружения для всех команд
+    pub fn env(mut self, key: &str, value: &str) -> Self {
+        self.envs.push((key.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Запускает ferium в подпапке `server` и возвращает его вывод
+    pub fn run(&self, args: Vec<&str>) -> Result<String> {
+        let output = Command::new(env!("CARGO_BIN_EXE_ferium"))
+            .args(args)
+            .current_dir(self.directory.join("server"))
+            .env_remove("FERIUM_CONFIG_FILE")
+            .envs(self.envs.clone())
+            .output()?;
+        check_output(&output)?;
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    }
+
+    /// Читает `ferium.toml`, который записал ferium
+    pub fn read(&self) -> Result<toml::Table> {
+        toml::from_str(&read_to_string(self.directory.join("ferium.toml"))?)
+            .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidData, err))
+    }
+
+    /// Заменяет содержимое `ferium.toml`
+    pub fn write(&self, contents: &str) -> Result<()> {
+        write(self.directory.join("ferium.toml"), contents)
+    }
+}
+
+fn check_output(output: &Output) -> Result<()> {
     if output.status.success() {
         Ok(())
     } else {
@@ -34,3 +169,334 @@ pub fn run_command(args: Vec<&str>, config_file: Option<&str>) -> Result<()> {
         ))
     }
 }
+
+/// Запускает локальный HTTP сервер, который отвечает на запросы к `routes` (путь, тело ответа)
+///
+/// Возвращает базовый URL сервера
+pub fn mock_server<P: Into<String>>(routes: Vec<(P, String)>) -> String {
+    recording_mock_server(routes).0
+}
+
+/// Как [`mock_server`], но также возвращает полученные сервером запросы (строку запроса с заголовками)
+pub fn recording_mock_server<P: Into<String>>(
+    routes: Vec<(P, String)>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    scripted_mock_server(
+        routes
+            .into_iter()
+            .map(|(path, body)| (path, vec![MockResponse::ok(body)]))
+            .collect(),
+    )
+}
+
+/// Ответ тестового сервера
+#[derive(Clone)]
+pub struct MockResponse {
+    status: String,
+    headers: Vec<(String, String)>,
+    body: String,
+    /// Длина в заголовке `Content-Length`, если она отличается от длины тела
+    length: Option<usize>,
+    /// Отвечать на запросы с заголовком `Range` только запрошенной частью тела
+    ranges: bool,
+}
+
+impl MockResponse {
+    /// Ответ `200 OK` с телом `body`
+    pub fn ok(body: impl Into<String>) -> Self {
+        Self::status("200 OK").body(body)
+    }
+
+    /// Ответ с кодом `status` (например `503 Service Unavailable`) и пустым телом
+    pub fn status(status: &str) -> Self {
+        Self {
+            status: status.to_owned(),
+            headers: Vec::new(),
+            body: String::new(),
+            length: None,
+            ranges: false,
+        }
+    }
+
+    pub fn body(mut self, body: impl Into<String>) -> Self {
+        self.body = body.into();
+        self
+    }
+
+    pub fn header(mut self, name: &str, value: &str) -> Self {
+        self.headers.push((name.to_owned(), value.to_owned()));
+        self
+    }
+
+    /// Обрывает соединение после тела, хотя в `Content-Length` указано `length` байтов
+    pub fn truncated(mut self, length: usize) -> Self {
+        self.length = Some(length);
+        self
+    }
+
+    /// Отвечает `206 Partial Content` на запросы части тела
+    pub const fn with_ranges(mut self) -> Self {
+        self.ranges = true;
+        self
+    }
+}
+
+/// Как [`recording_mock_server`], но для каждого пути задаётся последовательность ответов
+///
+/// `n`-й запрос к пути получает `n`-й ответ, а запросы после последнего ответа получают последний.
+pub fn scripted_mock_server<P: Into<String>>(
+    routes: Vec<(P, Vec<MockResponse>)>,
+) -> (String, Arc<Mutex<Vec<String>>>) {
+    let routes: Vec<(String, Vec<MockResponse>)> = routes
+        .into_iter()
+        .map(|(path, responses)| (path.into(), responses))
+        .collect();
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let received = Arc::clone(&requests);
+    thread::spawn(move || {
+        let mut counts = HashMap::<String, usize>::new();
+        for stream in listener.incoming().flatten() {
+            let mut reader = BufReader::new(&stream);
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut request = request_line.clone();
+            let mut range_start = None;
+            let mut header = String::new();
+            while reader.read_line(&mut header).unwrap_or(0) > 2 {
+                if let Some(range) = header.to_lowercase().strip_prefix("range: bytes=") {
+                    range_start = range.trim().trim_end_matches('-').parse::<usize>().ok();
+                }
+                request.push_str(&header);
+                header.clear();
+            }
+            received.lock().unwrap().push(request);
+
+            let path = request_line
+                .split_whitespace()
+                .nth(1)
+                .unwrap_or_default()
+                .split('?')
+                .next()
+                .unwrap_or_default()
+                .to_owned();
+            let count = counts.entry(path.clone()).or_default();
+            let mut response = routes
+                .iter()
+                .find(|(route, _)| *route == path)
+                .and_then(|(_, responses)| {
+                    responses.get(*count).or_else(|| responses.last()).cloned()
+                })
+                .unwrap_or_else(|| MockResponse::status("404 Not Found"));
+            *count += 1;
+
+            if let Some(start) = range_start.filter(|_| response.ranges) {
+                let total = response.body.len();
+                response.status = "206 Partial Content".to_owned();
+                response.headers.push((
+                    "Content-Range".to_owned(),
+                    format!("bytes {start}-{}/{total}", total.saturating_sub(1)),
+                ));
+                response.body = response.body.get(start..).unwrap_or_default().to_owned();
+            }
+            let headers = response
+                .headers
+                .iter()
+                .map(|(name, value)| format!("{name}: {value}\r\n"))
+                .collect::<String>();
+            let _ = write!(
+                &stream,
+                "HTTP/1.1 {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n{headers}Connection: close\r\n\r\n{}",
+                response.status,
+                response.length.unwrap_or(response.body.len()),
+                response.body
+            );
+        }
+    });
+
+    (url, requests)
+}
+
+/// Проект Modrinth с `id`, `slug` и `title`, в том виде, в котором его возвращает API
+pub fn modrinth_project(id: &str, slug: &str, title: &str) -> Value {
+    json!({
+        "id": id,
+        "slug": slug,
+        "project_type": "mod",
+        "team": "TEAMTEAM",
+        "title": title,
+        "description": "A mod for testing",
+        "body": "",
+        "body_url": null,
+        "published": "2024-01-01T00:00:00Z",
+        "updated": "2024-01-01T00:00:00Z",
+        "approved": "2024-01-01T00:00:00Z",
+        "queued": null,
+        "status": "approved",
+        "requested_status": null,
+        "moderator_message": null,
+        "license": { "id": "MIT", "name": "MIT License", "url": null },
+        "client_side": "required",
+        "server_side": "optional",
+        "downloads": 0,
+        "followers": 0,
+        "categories": [],
+        "additional_categories": [],
+        "game_versions": [],
+        "loaders": [],
+        "versions": [],
+        "icon_url": null,
+        "issues_url": null,
+        "source_url": null,
+        "wiki_url": null,
+        "discord_url": null,
+        "donation_urls": [],
+        "gallery": [],
+        "color": null,
+        "thread_id": null,
+        "monetization_status": "monetized",
+    })
+}
+
+/// Версия проекта Modrinth `project_id` с одним файлом `filename`, который лежит по пути `/files/{filename}`
+///
+/// `game_versions` и `loaders` перечисляются через запятую.
+pub fn modrinth_version(
+    project_id: &str,
+    filename: &str,
+    game_versions: &str,
+    loaders: &str,
+    version_type: &str,
+    date_published: &str,
+) -> Value {
+    json!({
+        "id": filename.replace(['.', '-', '+'], ""),
+        "project_id": project_id,
+        "author_id": "AUTHORID",
+        "featured": false,
+        "name": filename,
+        "version_number": filename,
+        "changelog": "",
+        "changelog_url": null,
+        "date_published": date_published,
+        "downloads": 0,
+        "version_type": version_type,
+        "status": "listed",
+        "requested_status": null,
+        "files": [{
+            "hashes": { "sha1": "", "sha512": "" },
+            "url": format!("https://cdn.modrinth.com/files/{filename}"),
+            "filename": filename,
+            "primary": true,
+            "size": 3,
+            "file_type": null,
+        }],
+        "dependencies": [],
+        "game_versions": game_versions.split(',').collect::<Vec<_>>(),
+        "loaders": loaders.split(',').collect::<Vec<_>>(),
+    })
+}
+
+/// Релиз GitHub `tag` с одним файлом `asset`, в том виде, в котором его возвращает API
+pub fn github_release(tag: &str, asset: &str) -> Value {
+    let url = "https://api.github.com/repos/ferium/test-mod/releases/1";
+    json!({
+        "url": url,
+        "html_url": format!("https://github.com/ferium/test-mod/releases/tag/{tag}"),
+        "assets_url": format!("{url}/assets"),
+        "upload_url": format!("{url}/assets"),
+        "tarball_url": null,
+        "zipball_url": null,
+        "id": 1,
+        "node_id": "RELEASE",
+        "tag_name": tag,
+        "target_commitish": "main",
+        "name": tag,
+        "body": "",
+        "draft": false,
+        "prerelease": false,
+        "created_at": "2024-01-01T00:00:00Z",
+        "published_at": "2024-01-01T00:00:00Z",
+        "author": null,
+        "assets": [{
+            "url": "https://api.github.com/repos/ferium/test-mod/releases/assets/1",
+            "browser_download_url": format!("https://github.com/ferium/test-mod/releases/download/{tag}/{asset}"),
+            "id": 1,
+            "node_id": "ASSET",
+            "name": asset,
+            "label": null,
+            "state": "uploaded",
+            "content_type": "application/java-archive",
+            "size": 3,
+            "download_count": 0,
+            "created_at": "2024-01-01T00:00:00Z",
+            "updated_at": "2024-01-01T00:00:00Z",
+            "uploader": null,
+        }],
+    })
+}
+
+/// Мод CurseForge с `id`, `slug` и `name`, в том виде, в котором его возвращает API
+pub fn curseforge_mod(id: i32, slug: &str, name: &str) -> Value {
+    json!({
+        "id": id,
+        "gameId": 432,
+        "name": name,
+        "slug": slug,
+        "links": {
+            "websiteUrl": format!("https://www.curseforge.com/minecraft/mc-mods/{slug}"),
+            "wikiUrl": "",
+            "issuesUrl": "",
+            "sourceUrl": "",
+        },
+        "summary": "A mod for testing",
+        "status": 4,
+        "downloadCount": 0,
+        "isFeatured": false,
+        "primaryCategoryId": 0,
+        "categories": [],
+        "classId": 6,
+        "authors": [],
+        "logo": null,
+        "screenshots": [],
+        "mainFileId": 1,
+        "latestFiles": [],
+        "latestFilesIndexes": [],
+        "dateCreated": "2024-01-01T00:00:00Z",
+        "dateModified": "2024-01-01T00:00:00Z",
+        "dateReleased": "2024-01-01T00:00:00Z",
+        "allowModDistribution": true,
+        "gamePopularityRank": 0,
+        "isAvailable": true,
+        "thumbsUpCount": 0,
+        "latestEarlyAccessFilesIndexes": [],
+    })
+}
+
+/// Файл `filename` мода CurseForge `mod_id`, в том виде, в котором его возвращает API
+///
+/// `game_versions` перечисляются через запятую, вместе с загрузчиками модов.
+pub fn curseforge_file(mod_id: i32, filename: &str, game_versions: &str) -> Value {
+    json!({
+        "id": 1,
+        "gameId": 432,
+        "modId": mod_id,
+        "isAvailable": true,
+        "displayName": filename,
+        "fileName": filename,
+        "releaseType": 1,
+        "fileStatus": 4,
+        "hashes": [],
+        "fileDate": "2024-01-01T00:00:00Z",
+        "fileLength": 3,
+        "downloadCount": 0,
+        "downloadUrl": format!("https://edge.forgecdn.net/files/{filename}"),
+        "gameVersions": game_versions.split(',').collect::<Vec<_>>(),
+        "sortableGameVersions": [],
+        "dependencies": [],
+        "fileFingerprint": 0,
+        "modules": [],
+    })
+}